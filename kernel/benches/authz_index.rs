@@ -0,0 +1,105 @@
+// AuthZ Index Benchmark
+// Shows per-request authorization cost stays flat as routes and capabilities grow
+//
+// Usage: cargo bench --bench authz_index
+
+use chrono::Utc;
+use kernel::authz::authorize::{authorize, AuthContext};
+use kernel::authz::capabilities::CapabilityRequirement;
+use kernel::authz::roles::{Role, RoleIndex};
+use kernel::routing::authorize_route::authorize_route;
+use kernel::routing::graph::{Route, RouteNode, RoutingGraph};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::Instant;
+
+const ITERATIONS: u32 = 20_000;
+
+/// Builds a policy with `size` modules, each with its own capability patterns and routes
+fn build_policy(size: usize) -> (RoleIndex, HashMap<String, CapabilityRequirement>, RoutingGraph) {
+    let mut capabilities = Vec::new();
+    let mut requirements = HashMap::new();
+    let mut routes = Vec::new();
+    
+    for i in 0..size {
+        capabilities.push(format!("mod{}.items.*", i));
+        capabilities.push(format!("mod{}.admin.purge", i));
+        
+        requirements.insert(format!("mod{}.items.get", i), CapabilityRequirement {
+            required_scopes: Some(vec!["data:read".to_string()]),
+            required_roles: None,
+            conditions: None,
+        });
+        
+        routes.push(Route {
+            id: format!("ui-to-mod{}", i),
+            from: RouteNode { r#type: "ui".to_string(), id: "main_ui".to_string(), capability: None, tenant: None },
+            to: RouteNode { r#type: "module".to_string(), id: format!("mod{}", i), capability: None, tenant: None },
+            allowed_capabilities: Some(vec![format!("mod{}.items.*", i)]),
+            deny: None,
+            conditions: None,
+            enabled: true,
+            internal: false,
+            cross_tenant: false,
+            canary: None,
+            metadata: None,
+        });
+    }
+    
+    let mut roles = HashMap::new();
+    roles.insert("operator".to_string(), Role {
+        description: "Benchmark role".to_string(),
+        inherits: None,
+        scopes: vec!["data:read".to_string()],
+        capabilities: Some(capabilities),
+        deny: None,
+        rate_limit_per_minute: 1000,
+        max_request_size_bytes: 1024,
+    });
+    
+    let roles = RoleIndex::compile(roles).expect("valid roles");
+    let graph = RoutingGraph::new(routes, HashMap::new()).expect("valid routes");
+    (roles, requirements, graph)
+}
+
+fn main() {
+    let context = AuthContext {
+        actor_id: "bench".to_string(),
+        actor_type: "user".to_string(),
+        role: "operator".to_string(),
+        scopes: vec!["data:read".to_string()],
+        args: Value::Null,
+        request_time: Utc::now(),
+        tenant_id: None,
+        caller: Default::default(),
+    };
+    
+    println!("{:>8} {:>8} {:>14} {:>14}", "routes", "caps", "authorize_ns", "route_ns");
+    
+    for size in [10, 100, 1_000, 5_000, 20_000] {
+        let (roles, requirements, graph) = build_policy(size);
+        
+        // Target the last module so a linear scan would have to walk everything
+        let module = format!("mod{}", size - 1);
+        let capability = format!("{}.items.get", module);
+        
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            let decision = authorize(&context, black_box(&capability), &roles, &requirements);
+            assert!(decision.is_allowed());
+        }
+        let authz_ns = start.elapsed().as_nanos() / ITERATIONS as u128;
+        
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            let decision = authorize_route(
+                &graph, "ui", "main_ui", "module", black_box(&module), &capability, &context, None,
+            );
+            assert!(decision.is_allowed());
+        }
+        let route_ns = start.elapsed().as_nanos() / ITERATIONS as u128;
+        
+        println!("{:>8} {:>8} {:>14} {:>14}", size, size * 2, authz_ns, route_ns);
+    }
+}
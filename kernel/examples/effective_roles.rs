@@ -0,0 +1,36 @@
+// Example: Print Effective Roles
+// Flattens `inherits` in an access policy and prints the resulting roles for review
+//
+// Usage: cargo run --example effective_roles [path/to/access.yaml]
+
+use std::env;
+use std::fs;
+use std::process;
+
+fn main() {
+    let policy_path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "../system/policy/access.yaml".to_string());
+    
+    let content = match fs::read_to_string(&policy_path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", policy_path, e);
+            process::exit(1);
+        }
+    };
+    
+    let rendered = kernel::authz::roles::parse_roles(&content)
+        .and_then(|roles| kernel::authz::roles::render_effective_roles(&roles));
+    
+    match rendered {
+        Ok(yaml) => {
+            println!("# Effective roles from {}", policy_path);
+            print!("{}", yaml);
+        }
+        Err(e) => {
+            eprintln!("Invalid access policy: {}", e);
+            process::exit(1);
+        }
+    }
+}
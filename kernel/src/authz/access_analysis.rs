@@ -0,0 +1,514 @@
+// Access Analysis
+// Answers who-can / what-can questions by running policy through the real authz and routing checks
+//
+// Each role is evaluated as an actor holding all of the role's scopes. Capabilities whose
+// requirements include attribute conditions are reported as "conditional": they depend on
+// the actor and arguments of an actual request.
+
+use super::authorize::{self, AuthContext};
+use super::capabilities::CapabilityRequirement;
+use super::grants::Grant;
+use super::roles::RoleIndex;
+use crate::primitives::capability_pattern;
+use crate::routing::authorize_route::authorize_route;
+use crate::routing::graph::RoutingGraph;
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Access {
+    Allowed,
+    Conditional,
+    Denied,
+}
+
+impl Access {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Access::Allowed => "allowed",
+            Access::Conditional => "conditional",
+            Access::Denied => "denied",
+        }
+    }
+}
+
+/// Policy loaded for analysis
+pub struct AccessModel {
+    pub roles: RoleIndex,
+    pub capability_requirements: HashMap<String, CapabilityRequirement>,
+    pub grants: Vec<Grant>,
+    pub graph: RoutingGraph,
+}
+
+/// "Who can invoke X": one row per principal and UI entry point
+#[derive(Debug, Clone, Serialize)]
+pub struct WhoCanRow {
+    pub principal: String,
+    pub kind: String,
+    pub access: Access,
+    pub ui: Option<String>,
+    pub target: Option<String>,
+    pub route: Option<String>,
+    pub note: Option<String>,
+}
+
+/// "What can role Y do from UI Z": one row per reachable capability
+#[derive(Debug, Clone, Serialize)]
+pub struct WhatCanRow {
+    pub capability: String,
+    pub access: Access,
+    pub target: String,
+    pub route: String,
+}
+
+/// Capability a role is granted but cannot reach from any UI
+#[derive(Debug, Clone, Serialize)]
+pub struct UnreachableRow {
+    pub role: String,
+    pub capability: String,
+    pub access: Access,
+}
+
+/// Route pattern that no role holds
+#[derive(Debug, Clone, Serialize)]
+pub struct OrphanRouteRow {
+    pub route: String,
+    pub from: String,
+    pub to: String,
+    pub capability: String,
+}
+
+/// Rows that can be rendered as an aligned text table
+pub trait TableRow {
+    fn headers() -> Vec<&'static str>;
+    fn cells(&self) -> Vec<String>;
+}
+
+impl AccessModel {
+    /// Every concrete capability named anywhere in access or routing policy, sorted
+    pub fn capability_universe(&self) -> Vec<String> {
+        let mut universe = BTreeSet::new();
+        
+        universe.extend(self.capability_requirements.keys().cloned());
+        for role in self.roles.roles().values() {
+            universe.extend(role.capabilities.iter().flatten().cloned());
+        }
+        for route in &self.graph.model.routes {
+            universe.extend(route.allowed_capabilities.iter().flatten().cloned());
+        }
+        for (parent, children) in &self.graph.model.capability_chains {
+            universe.insert(parent.clone());
+            universe.extend(children.iter().cloned());
+        }
+        
+        universe.into_iter().filter(|c| !capability_pattern::is_pattern(c)).collect()
+    }
+    
+    /// Runs the capability through `authorize` for an actor holding all of the role's scopes
+    pub fn role_access(&self, role: &str, capability: &str) -> Access {
+        let context = role_context(&self.roles, role);
+        let decision = authorize::authorize(&context, capability, &self.roles, &self.capability_requirements);
+        
+        if decision.is_allowed() {
+            Access::Allowed
+        } else if decision.deciding_rule.as_deref().is_some_and(|r| r.contains(".conditions[")) {
+            Access::Conditional
+        } else {
+            Access::Denied
+        }
+    }
+    
+    /// Distinct (ui, to_type, to_id) edges of enabled UI routes, sorted
+    fn ui_edges(&self) -> Vec<(String, String, String)> {
+        let edges: BTreeSet<(String, String, String)> = self.graph.model.routes.iter()
+            .filter(|r| r.enabled && r.from.r#type == "ui")
+            .map(|r| (r.from.id.clone(), r.to.r#type.clone(), r.to.id.clone()))
+            .collect();
+        edges.into_iter().collect()
+    }
+    
+    /// Route ID that lets the role's actor reach the capability over this edge, if any
+    fn route_for(&self, role: &str, ui: &str, to_type: &str, to_id: &str, capability: &str) -> Option<String> {
+        let context = role_context(&self.roles, role);
+        let decision = authorize_route(&self.graph, "ui", ui, to_type, to_id, capability, &context, None);
+        
+        if !decision.is_allowed() {
+            return None;
+        }
+        
+        decision.deciding_rule.as_deref()
+            .and_then(|rule| rule.strip_prefix("route["))
+            .and_then(|rest| rest.split(']').next())
+            .map(|id| id.to_string())
+    }
+    
+    /// Reachable UI entry points for the role and capability: (ui, target, route)
+    fn reachable_from_uis(&self, role: &str, capability: &str) -> Vec<(String, String, String)> {
+        self.ui_edges().into_iter()
+            .filter_map(|(ui, to_type, to_id)| {
+                self.route_for(role, &ui, &to_type, &to_id, capability)
+                    .map(|route| (ui, format!("{}:{}", to_type, to_id), route))
+            })
+            .collect()
+    }
+    
+    /// Roles (and grant holders) that can invoke the capability, with the UIs they can reach it from
+    pub fn who_can(&self, capability: &str) -> Vec<WhoCanRow> {
+        let mut rows = Vec::new();
+        
+        for role in sorted_role_names(&self.roles) {
+            let access = self.role_access(&role, capability);
+            if access == Access::Denied {
+                continue;
+            }
+            
+            let reachable = self.reachable_from_uis(&role, capability);
+            if reachable.is_empty() {
+                rows.push(WhoCanRow {
+                    principal: role.clone(),
+                    kind: "role".to_string(),
+                    access,
+                    ui: None,
+                    target: None,
+                    route: None,
+                    note: Some("no route from any UI".to_string()),
+                });
+            }
+            
+            for (ui, target, route) in reachable {
+                rows.push(WhoCanRow {
+                    principal: role.clone(),
+                    kind: "role".to_string(),
+                    access,
+                    ui: Some(ui),
+                    target: Some(target),
+                    route: Some(route),
+                    note: None,
+                });
+            }
+        }
+        
+        let now = Utc::now();
+        for grant in &self.grants {
+            let covers = grant.capabilities.iter().any(|p| capability_pattern::pattern_matches(p, capability));
+            if covers && grant.is_active(now) {
+                rows.push(WhoCanRow {
+                    principal: grant.actor_id.clone(),
+                    kind: "grant".to_string(),
+                    access: Access::Allowed,
+                    ui: None,
+                    target: None,
+                    route: None,
+                    note: Some(format!("grant {} until {}", grant.id, grant.expires_at.to_rfc3339())),
+                });
+            }
+        }
+        
+        rows
+    }
+    
+    /// Capabilities the role can invoke and reach from the UI
+    pub fn what_can(&self, role: &str, ui: &str) -> Vec<WhatCanRow> {
+        let edges: Vec<(String, String, String)> = self.ui_edges().into_iter()
+            .filter(|(edge_ui, _, _)| edge_ui == ui)
+            .collect();
+        
+        let mut rows = Vec::new();
+        for capability in self.capability_universe() {
+            let access = self.role_access(role, &capability);
+            if access == Access::Denied {
+                continue;
+            }
+            
+            for (_, to_type, to_id) in &edges {
+                if let Some(route) = self.route_for(role, ui, to_type, to_id, &capability) {
+                    rows.push(WhatCanRow {
+                        capability: capability.clone(),
+                        access,
+                        target: format!("{}:{}", to_type, to_id),
+                        route,
+                    });
+                }
+            }
+        }
+        
+        rows
+    }
+    
+    /// Capabilities a role may invoke but cannot reach from any UI route
+    pub fn unreachable_capabilities(&self) -> Vec<UnreachableRow> {
+        let universe = self.capability_universe();
+        let mut rows = Vec::new();
+        
+        for role in sorted_role_names(&self.roles) {
+            for capability in &universe {
+                let access = self.role_access(&role, capability);
+                if access != Access::Denied && self.reachable_from_uis(&role, capability).is_empty() {
+                    rows.push(UnreachableRow {
+                        role: role.clone(),
+                        capability: capability.clone(),
+                        access,
+                    });
+                }
+            }
+        }
+        
+        rows
+    }
+    
+    /// Enabled, non-internal route patterns that no role's capabilities overlap
+    /// Internal routes carry chain capabilities invoked by modules, so they are skipped
+    pub fn orphan_routes(&self) -> Vec<OrphanRouteRow> {
+        let role_patterns: Vec<(&str, &str)> = self.roles.roles().iter()
+            .flat_map(|(name, role)| role.capabilities.iter().flatten().map(move |p| (name.as_str(), p.as_str())))
+            .collect();
+        
+        let mut rows = Vec::new();
+        for route in self.graph.model.routes.iter().filter(|r| r.enabled && !r.internal) {
+            for pattern in route.allowed_capabilities.iter().flatten() {
+                let held = role_patterns.iter().any(|(role, role_pattern)| {
+                    if capability_pattern::is_pattern(pattern) {
+                        capability_pattern::patterns_overlap(role_pattern, pattern)
+                    } else {
+                        self.role_access(role, pattern) != Access::Denied
+                    }
+                });
+                
+                if !held {
+                    rows.push(OrphanRouteRow {
+                        route: route.id.clone(),
+                        from: format!("{}:{}", route.from.r#type, route.from.id),
+                        to: format!("{}:{}", route.to.r#type, route.to.id),
+                        capability: pattern.clone(),
+                    });
+                }
+            }
+        }
+        
+        rows
+    }
+}
+
+/// Analysis actor for a role: holds every scope the role grants, no arguments
+fn role_context(roles: &RoleIndex, role: &str) -> AuthContext {
+    let scopes = roles.roles().get(role)
+        .map(|r| r.scopes.clone())
+        .unwrap_or_default();
+    
+    AuthContext {
+        actor_id: format!("analysis:{}", role),
+        actor_type: "user".to_string(),
+        role: role.to_string(),
+        scopes,
+        args: Value::Null,
+        request_time: Utc::now(),
+        tenant_id: None,
+        caller: Default::default(),
+    }
+}
+
+fn sorted_role_names(roles: &RoleIndex) -> Vec<String> {
+    let mut names: Vec<String> = roles.roles().keys().cloned().collect();
+    names.sort();
+    names
+}
+
+/// Renders rows as a left-aligned text table
+pub fn render_table<T: TableRow>(rows: &[T]) -> String {
+    let headers = T::headers();
+    let cells: Vec<Vec<String>> = rows.iter().map(|r| r.cells()).collect();
+    
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in &cells {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(cell.len());
+        }
+    }
+    
+    let format_row = |values: Vec<&str>| -> String {
+        values.iter()
+            .enumerate()
+            .map(|(i, v)| format!("{:width$}", v, width = widths[i]))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    
+    let mut out = String::new();
+    out.push_str(&format_row(headers.clone()));
+    out.push('\n');
+    let rule: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+    out.push_str(&format_row(rule.iter().map(|s| s.as_str()).collect()));
+    out.push('\n');
+    for row in &cells {
+        out.push_str(&format_row(row.iter().map(|c| c.as_str()).collect()));
+        out.push('\n');
+    }
+    out
+}
+
+fn or_dash(value: &Option<String>) -> String {
+    value.clone().unwrap_or_else(|| "-".to_string())
+}
+
+impl TableRow for WhoCanRow {
+    fn headers() -> Vec<&'static str> {
+        vec!["PRINCIPAL", "KIND", "ACCESS", "UI", "TARGET", "ROUTE", "NOTE"]
+    }
+    
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.principal.clone(),
+            self.kind.clone(),
+            self.access.as_str().to_string(),
+            or_dash(&self.ui),
+            or_dash(&self.target),
+            or_dash(&self.route),
+            or_dash(&self.note),
+        ]
+    }
+}
+
+impl TableRow for WhatCanRow {
+    fn headers() -> Vec<&'static str> {
+        vec!["CAPABILITY", "ACCESS", "TARGET", "ROUTE"]
+    }
+    
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.capability.clone(),
+            self.access.as_str().to_string(),
+            self.target.clone(),
+            self.route.clone(),
+        ]
+    }
+}
+
+impl TableRow for UnreachableRow {
+    fn headers() -> Vec<&'static str> {
+        vec!["ROLE", "CAPABILITY", "ACCESS"]
+    }
+    
+    fn cells(&self) -> Vec<String> {
+        vec![self.role.clone(), self.capability.clone(), self.access.as_str().to_string()]
+    }
+}
+
+impl TableRow for OrphanRouteRow {
+    fn headers() -> Vec<&'static str> {
+        vec!["ROUTE", "FROM", "TO", "CAPABILITY"]
+    }
+    
+    fn cells(&self) -> Vec<String> {
+        vec![self.route.clone(), self.from.clone(), self.to.clone(), self.capability.clone()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authz::{capabilities, roles};
+    
+    fn model() -> AccessModel {
+        let access = r#"
+version: v1.0.0
+policy: deny_by_default
+roles:
+  admin:
+    description: "Admin"
+    scopes: ["storage:read", "storage:write", "storage:delete"]
+    capabilities: ["storage.**", "reports.export"]
+    rate_limit_per_minute: 100
+    max_request_size_bytes: 1024
+  viewer:
+    description: "Viewer"
+    scopes: ["storage:read"]
+    capabilities: ["storage.listings.get"]
+    rate_limit_per_minute: 100
+    max_request_size_bytes: 1024
+capability_requirements:
+  "storage.listings.get":
+    required_scopes: ["storage:read"]
+  "storage.listings.delete":
+    required_scopes: ["storage:delete"]
+    required_roles: ["admin"]
+  "storage.listings.update":
+    required_scopes: ["storage:write"]
+    conditions: ["args.owner_id == actor.id"]
+  "reports.export":
+    required_scopes: []
+"#;
+        let routing = r#"
+version: v1.0.0
+policy: deny_by_default
+routes:
+  - id: ui-storage
+    from: {type: ui, id: main_ui}
+    to: {type: module, id: storage}
+    allowed_capabilities: ["storage.listings.*"]
+    conditions:
+      required_scopes: ["storage:read"]
+    enabled: true
+  - id: ui-billing
+    from: {type: ui, id: main_ui}
+    to: {type: module, id: billing}
+    allowed_capabilities: ["billing.*"]
+    enabled: true
+"#;
+        AccessModel {
+            roles: roles::parse_roles(access).and_then(RoleIndex::compile).unwrap(),
+            capability_requirements: capabilities::parse_capability_requirements(access).unwrap(),
+            grants: Vec::new(),
+            graph: RoutingGraph::parse(routing).unwrap(),
+        }
+    }
+    
+    #[test]
+    fn test_who_can() {
+        let rows = model().who_can("storage.listings.delete");
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].principal, "admin");
+        assert_eq!(rows[0].ui.as_deref(), Some("main_ui"));
+        assert_eq!(rows[0].route.as_deref(), Some("ui-storage"));
+        
+        let conditional = model().who_can("storage.listings.update");
+        assert_eq!(conditional[0].access, Access::Conditional);
+    }
+    
+    #[test]
+    fn test_what_can() {
+        let rows = model().what_can("viewer", "main_ui");
+        let caps: Vec<&str> = rows.iter().map(|r| r.capability.as_str()).collect();
+        assert_eq!(caps, vec!["storage.listings.get"]);
+    }
+    
+    #[test]
+    fn test_unreachable_and_orphans() {
+        let model = model();
+        
+        let unreachable = model.unreachable_capabilities();
+        assert!(unreachable.iter().any(|r| r.role == "admin" && r.capability == "reports.export"));
+        assert!(!unreachable.iter().any(|r| r.capability == "storage.listings.get"));
+        
+        let orphans = model.orphan_routes();
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].route, "ui-billing");
+    }
+    
+    #[test]
+    fn test_render_table() {
+        let rows = vec![UnreachableRow {
+            role: "admin".to_string(),
+            capability: "reports.export".to_string(),
+            access: Access::Allowed,
+        }];
+        
+        let table = render_table(&rows);
+        assert_eq!(table.lines().next(), Some("ROLE   CAPABILITY      ACCESS"));
+        assert_eq!(table.lines().nth(2), Some("admin  reports.export  allowed"));
+    }
+}
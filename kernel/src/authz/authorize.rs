@@ -1,8 +1,13 @@
 // Authorization
 // Unified authorization check point (deny-by-default)
 
-use super::roles::{self, Role};
+use super::roles::RoleIndex;
 use super::capabilities;
+use super::decision::Decision;
+use super::grants::Grant;
+use super::tokens::{self, TokenVerifier};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
 use std::collections::HashMap;
 use std::error::Error;
 
@@ -11,39 +16,200 @@ pub struct AuthContext {
     pub actor_type: String,
     pub role: String,
     pub scopes: Vec<String>,
+    /// Command arguments, exposed to policy conditions as `args.*`
+    pub args: Value,
+    /// Time the request was received, exposed to policy conditions as `time.*`
+    pub request_time: DateTime<Utc>,
+    /// Tenant the actor belongs to; None means the shared base policy
+    pub tenant_id: Option<String>,
+    /// Attributes set by the transport, exposed to route conditions as `caller`
+    pub caller: CallerAttributes,
+}
+
+/// Facts about the caller that the transport vouches for; never read from the command payload
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CallerAttributes {
+    /// Peer uid of a local socket connection (SO_PEERCRED)
+    pub peer_uid: Option<u32>,
 }
 
 /// Main authorization check: can this actor invoke this capability?
-/// Returns Ok(()) if allowed, Err with PERMISSION_DENIED otherwise
+/// Returns a Decision tracing every rule checked; denials carry PERMISSION_DENIED
 pub fn authorize(
     context: &AuthContext,
     capability: &str,
-    roles_map: &HashMap<String, Role>,
+    roles: &RoleIndex,
     capability_requirements: &HashMap<String, capabilities::CapabilityRequirement>,
-) -> Result<(), Box<dyn Error>> {
+) -> Decision {
+    authorize_with_grants(context, capability, roles, capability_requirements, &[])
+}
+
+/// Authorization check that also honours break-glass grants
+/// Order: role lookup, role deny (wins over everything), role allow or active grant, requirements
+pub fn authorize_with_grants(
+    context: &AuthContext,
+    capability: &str,
+    roles: &RoleIndex,
+    capability_requirements: &HashMap<String, capabilities::CapabilityRequirement>,
+    grants: &[Grant],
+) -> Decision {
+    let mut decision = Decision::new();
+    
     // 1. Validate role exists
-    let role = roles::get_role(&context.role, roles_map)?;
-    
-    // 2. Verify role has the capability
-    if !roles::role_has_capability(role, capability) {
-        return Err(format!(
-            "PERMISSION_DENIED: Role '{}' does not have capability '{}'",
-            context.role, capability
-        ).into());
+    match roles.get_role(&context.role) {
+        Ok(_) => decision.pass("role_lookup", format!("role '{}' exists", context.role)),
+        Err(e) => {
+            decision.fail("role_lookup", format!("role '{}' not defined", context.role));
+            return decision.deny("role_lookup", format!("PERMISSION_DENIED: {}", e));
+        }
+    }
+    
+    // 2. Explicit deny takes precedence over role allows and grants
+    let deny_rule = format!("role[{}].deny", context.role);
+    if let Some(pattern) = roles.matching_deny_pattern(&context.role, capability) {
+        decision.fail(&deny_rule, format!("deny pattern '{}' matches '{}'", pattern, capability));
+        return decision.deny(deny_rule, format!(
+            "PERMISSION_DENIED: Capability '{}' explicitly denied for role '{}'",
+            capability, context.role
+        ));
     }
+    decision.pass(&deny_rule, format!("no deny pattern matches '{}'", capability));
     
-    // 3. Check capability-specific requirements
-    capabilities::check_capability_allowed(
-        capability,
-        &context.role,
-        &context.scopes,
-        capability_requirements,
-    )?;
+    // 3. Verify role has the capability, or an active grant covers it
+    let mut grant = None;
+    match roles.matching_capability_pattern(&context.role, capability) {
+        Some(pattern) => decision.pass(
+            "role_capability",
+            format!("role '{}' pattern '{}' matches '{}'", context.role, pattern, capability),
+        ),
+        None => {
+            decision.fail(
+                "role_capability",
+                format!("no capability pattern of role '{}' matches '{}'", context.role, capability),
+            );
+            
+            grant = find_grant(context, capability, grants, &mut decision);
+            if grant.is_none() {
+                return decision.deny("role_capability", format!(
+                    "PERMISSION_DENIED: Role '{}' does not have capability '{}'",
+                    context.role, capability
+                ));
+            }
+        }
+    }
+    
+    // 4. Check capability-specific requirements
+    // A grant waives required_roles and adds its scopes; conditions still apply
+    let outcome = match grant {
+        Some(grant) => {
+            let mut requirements = HashMap::new();
+            if let Some(req) = capability_requirements.get(capability) {
+                let mut req = req.clone();
+                req.required_roles = None;
+                requirements.insert(capability.to_string(), req);
+            }
+            let mut scopes = context.scopes.clone();
+            scopes.extend(grant.scopes.iter().cloned());
+            let elevated = AuthContext {
+                actor_id: context.actor_id.clone(),
+                actor_type: context.actor_type.clone(),
+                role: context.role.clone(),
+                scopes,
+                args: context.args.clone(),
+                request_time: context.request_time,
+                tenant_id: context.tenant_id.clone(),
+                caller: context.caller.clone(),
+            };
+            capabilities::check_requirements(capability, &elevated, &requirements, &mut decision)
+        }
+        None => capabilities::check_requirements(capability, context, capability_requirements, &mut decision),
+    };
     
-    Ok(())
+    if let Err((rule, reason)) = outcome {
+        return decision.deny(rule, reason);
+    }
+    
+    let mut decision = decision.allow();
+    decision.grant_id = grant.map(|g| g.id.clone());
+    decision
+}
+
+/// Finds the first active grant for this actor and capability, tracing expired candidates
+fn find_grant<'a>(
+    context: &AuthContext,
+    capability: &str,
+    grants: &'a [Grant],
+    decision: &mut Decision,
+) -> Option<&'a Grant> {
+    for grant in grants.iter().filter(|g| g.covers(&context.actor_id, capability)) {
+        let rule = format!("grants[{}]", grant.id);
+        if grant.is_active(context.request_time) {
+            decision.pass(&rule, format!("active until {}: {}", grant.expires_at.to_rfc3339(), grant.reason));
+            return Some(grant);
+        }
+        decision.fail(&rule, format!("expired at {}", grant.expires_at.to_rfc3339()));
+    }
+    None
+}
+
+/// Authenticates the actor from a signed `context.actor_token`
+/// Identity, tenant, role and scopes come from verified claims only; scopes are clamped
+/// to the grants of the role in the tenant's policy (`roles_for_tenant`)
+pub fn authenticate<'a, F>(
+    command: &Value,
+    verifier: &TokenVerifier,
+    roles_for_tenant: F,
+    now: DateTime<Utc>,
+) -> Result<AuthContext, Box<dyn Error>>
+where
+    F: FnOnce(Option<&str>) -> Result<&'a RoleIndex, Box<dyn Error>>,
+{
+    let context_obj = command.get("context")
+        .ok_or("UNAUTHENTICATED: Missing context field in command")?;
+    
+    let token = context_obj.get("actor_token")
+        .and_then(|v| v.as_str())
+        .ok_or("UNAUTHENTICATED: Missing actor_token")?;
+    
+    let claims = verifier.verify(token, now.timestamp())?;
+    
+    // A caller-supplied actor is informational only and must agree with the token
+    if let Some(claimed_id) = context_obj.get("actor").and_then(|a| a.get("id")).and_then(|v| v.as_str()) {
+        if claimed_id != claims.sub {
+            return Err(format!(
+                "UNAUTHENTICATED: context.actor.id '{}' does not match token subject",
+                claimed_id
+            ).into());
+        }
+    }
+    
+    // The tenant is fixed by the token; a caller may restate it but never change it
+    let claimed_tenant = context_obj.get("tenant_id").and_then(|v| v.as_str());
+    if claimed_tenant.is_some() && claimed_tenant != claims.tenant_id.as_deref() {
+        return Err("UNAUTHENTICATED: context.tenant_id does not match token tenant".into());
+    }
+    
+    let role = claims.roles.first()
+        .ok_or("UNAUTHENTICATED: Actor token carries no roles")?
+        .to_string();
+    
+    let roles = roles_for_tenant(claims.tenant_id.as_deref())?;
+    let scopes = tokens::clamp_scopes(&claims.scopes, &role, roles);
+    
+    Ok(AuthContext {
+        actor_id: claims.sub,
+        actor_type: claims.typ,
+        role,
+        scopes,
+        args: command.get("args").cloned().unwrap_or(Value::Null),
+        request_time: now,
+        tenant_id: claims.tenant_id,
+        caller: Default::default(),
+    })
 }
 
 /// Extract authorization context from command payload
+/// Unverified: trusts `context.actor` as supplied; use `authenticate` on the request path
 pub fn extract_auth_context(command: &serde_json::Value) -> Result<AuthContext, Box<dyn Error>> {
     let context_obj = command.get("context")
         .ok_or("Missing context field in command")?;
@@ -82,17 +248,30 @@ pub fn extract_auth_context(command: &serde_json::Value) -> Result<AuthContext,
         })
         .unwrap_or_else(Vec::new);
     
+    let args = command.get("args")
+        .cloned()
+        .unwrap_or(Value::Null);
+    
+    let tenant_id = context_obj.get("tenant_id")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    
     Ok(AuthContext {
         actor_id,
         actor_type,
         role,
         scopes,
+        args,
+        request_time: Utc::now(),
+        tenant_id,
+        caller: Default::default(),
     })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::authz::roles;
     use serde_json::json;
     
     #[test]
@@ -118,4 +297,178 @@ mod tests {
         assert_eq!(context.role, "admin");
         assert_eq!(context.scopes.len(), 2);
     }
+    
+    #[test]
+    fn test_authorize_traces_rules() {
+        let roles = roles::parse_roles(r#"
+version: v1.0.0
+policy: deny_by_default
+roles:
+  editor:
+    description: "Editor"
+    scopes: ["storage:write"]
+    capabilities: ["storage.listings.*"]
+    rate_limit_per_minute: 100
+    max_request_size_bytes: 1024
+"#).and_then(RoleIndex::compile).unwrap();
+        let requirements = capabilities::parse_capability_requirements(r#"
+capability_requirements:
+  "storage.listings.create":
+    required_scopes: ["storage:write"]
+  "storage.listings.delete":
+    required_scopes: ["storage:delete"]
+"#).unwrap();
+        
+        let context = AuthContext {
+            actor_id: "user-123".to_string(),
+            actor_type: "user".to_string(),
+            role: "editor".to_string(),
+            scopes: vec!["storage:write".to_string()],
+            args: Value::Null,
+            request_time: Utc::now(),
+            tenant_id: None,
+            caller: Default::default(),
+        };
+        
+        let allowed = authorize(&context, "storage.listings.create", &roles, &requirements);
+        assert!(allowed.is_allowed());
+        let rules: Vec<&str> = allowed.checks.iter().map(|c| c.rule.as_str()).collect();
+        assert_eq!(rules, vec![
+            "role_lookup",
+            "role[editor].deny",
+            "role_capability",
+            "capability_requirements[storage.listings.create]",
+            "capability_requirements[storage.listings.create].required_scopes[storage:write]",
+        ]);
+        
+        let denied = authorize(&context, "storage.listings.delete", &roles, &requirements);
+        assert!(!denied.is_allowed());
+        assert_eq!(
+            denied.deciding_rule.as_deref(),
+            Some("capability_requirements[storage.listings.delete].required_scopes[storage:delete]")
+        );
+        assert!(denied.reason().starts_with("PERMISSION_DENIED"));
+    }
+    
+    #[test]
+    fn test_authenticate_uses_verified_claims() {
+        use crate::authz::tokens::{sign_token, ActorClaims, SigningMaterial, TokenKey};
+        
+        let roles = roles::parse_roles(r#"
+version: v1.0.0
+policy: deny_by_default
+roles:
+  viewer:
+    description: "Viewer"
+    scopes: ["storage:read"]
+    rate_limit_per_minute: 100
+    max_request_size_bytes: 1024
+"#).and_then(RoleIndex::compile).unwrap();
+        
+        let mut keys = HashMap::new();
+        keys.insert("hs".to_string(), TokenKey::Hs256(vec![3u8; 32]));
+        let verifier = TokenVerifier::new("cabinet-kernel", keys, 3600);
+        
+        let now = Utc::now();
+        let claims = ActorClaims {
+            sub: "user-7".to_string(),
+            typ: "user".to_string(),
+            roles: vec!["viewer".to_string()],
+            scopes: vec!["storage:read".to_string(), "storage:write".to_string()],
+            aud: "cabinet-kernel".to_string(),
+            exp: now.timestamp() + 60,
+            iat: now.timestamp(),
+            jti: "t-1".to_string(),
+            tenant_id: Some("dealer-a".to_string()),
+        };
+        let token = sign_token(&claims, "hs", &SigningMaterial::Hs256(vec![3u8; 32])).unwrap();
+        
+        let command = json!({
+            "context": {"actor_token": token, "actor": {"id": "user-7", "type": "user"}}
+        });
+        let context = authenticate(&command, &verifier, |_| Ok(&roles), now).unwrap();
+        assert_eq!(context.role, "viewer");
+        assert_eq!(context.scopes, vec!["storage:read"]);
+        assert_eq!(context.tenant_id.as_deref(), Some("dealer-a"));
+        
+        let other_tenant = json!({
+            "context": {"actor_token": token, "tenant_id": "dealer-b"}
+        });
+        assert!(authenticate(&other_tenant, &verifier, |_| Ok(&roles), now).is_err());
+        
+        let spoofed = json!({
+            "context": {"actor_token": token, "actor": {"id": "admin-1", "type": "user"}}
+        });
+        assert!(authenticate(&spoofed, &verifier, |_| Ok(&roles), now).is_err());
+        
+        let unsigned = json!({"context": {"actor": {"id": "user-7", "type": "user", "roles": ["admin"]}}});
+        let err = authenticate(&unsigned, &verifier, |_| Ok(&roles), now).err().unwrap();
+        assert!(err.to_string().starts_with("UNAUTHENTICATED"));
+    }
+    
+    #[test]
+    fn test_deny_wins_and_grants_elevate() {
+        let roles = roles::parse_roles(r#"
+version: v1.0.0
+policy: deny_by_default
+roles:
+  editor:
+    description: "Editor"
+    scopes: ["storage:write"]
+    capabilities: ["storage.**"]
+    deny: ["storage.listings.delete"]
+    rate_limit_per_minute: 100
+    max_request_size_bytes: 1024
+  viewer:
+    description: "Viewer"
+    scopes: ["storage:read"]
+    capabilities: ["storage.listings.get"]
+    rate_limit_per_minute: 100
+    max_request_size_bytes: 1024
+"#).and_then(RoleIndex::compile).unwrap();
+        let requirements = capabilities::parse_capability_requirements(r#"
+capability_requirements:
+  "storage.listings.delete":
+    required_scopes: ["storage:delete"]
+    required_roles: ["admin"]
+"#).unwrap();
+        let grants = crate::authz::grants::parse_grants(r#"
+version: v1.0.0
+grants:
+  - id: bg-7
+    actor_id: user-1
+    capabilities: ["storage.listings.delete"]
+    scopes: ["storage:delete"]
+    expires_at: "2099-01-01T00:00:00Z"
+    reason: "cleanup after incident"
+"#).unwrap();
+        
+        let mut context = AuthContext {
+            actor_id: "user-1".to_string(),
+            actor_type: "user".to_string(),
+            role: "editor".to_string(),
+            scopes: vec!["storage:write".to_string()],
+            args: Value::Null,
+            request_time: Utc::now(),
+            tenant_id: None,
+            caller: Default::default(),
+        };
+        
+        // Editor deny beats both the storage.** allow and the grant
+        let denied = authorize_with_grants(&context, "storage.listings.delete", &roles, &requirements, &grants);
+        assert!(!denied.is_allowed());
+        assert_eq!(denied.deciding_rule.as_deref(), Some("role[editor].deny"));
+        
+        // Viewer lacks the capability; the grant lets it through and is reported
+        context.role = "viewer".to_string();
+        let granted = authorize_with_grants(&context, "storage.listings.delete", &roles, &requirements, &grants);
+        assert!(granted.is_allowed());
+        assert_eq!(granted.grant_id.as_deref(), Some("bg-7"));
+        
+        // Expired grants are ignored
+        context.request_time = chrono::DateTime::parse_from_rfc3339("2099-06-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let expired = authorize_with_grants(&context, "storage.listings.delete", &roles, &requirements, &grants);
+        assert!(!expired.is_allowed());
+        assert!(expired.checks.iter().any(|c| c.rule == "grants[bg-7]"));
+    }
 }
@@ -1,6 +1,9 @@
 // Capability Checks
 // Validates capability requirements from policy
 
+use super::authorize::AuthContext;
+use super::conditions::{self, PolicyCondition};
+use super::decision::Decision;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::error::Error;
@@ -10,6 +13,10 @@ use std::fs;
 pub struct CapabilityRequirement {
     pub required_scopes: Option<Vec<String>>,
     pub required_roles: Option<Vec<String>>,
+    /// Attribute conditions over actor/args/time (see authz::conditions); all must hold
+    /// Parsed as the policy is read, so a malformed expression fails the load
+    #[serde(default)]
+    pub conditions: Option<Vec<PolicyCondition>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -23,46 +30,113 @@ pub fn load_capability_requirements() -> Result<HashMap<String, CapabilityRequir
     let content = fs::read_to_string(policy_path)
         .map_err(|e| format!("Failed to read access policy: {}", e))?;
     
-    let policy: AccessPolicy = serde_yaml::from_str(&content)
+    parse_capability_requirements(&content)
+}
+
+/// Parses capability requirements from access policy content
+/// Fails if any condition expression does not parse
+pub fn parse_capability_requirements(content: &str) -> Result<HashMap<String, CapabilityRequirement>, Box<dyn Error>> {
+    let policy: AccessPolicy = serde_yaml::from_str(content)
         .map_err(|e| format!("Failed to parse access policy: {}", e))?;
     
     Ok(policy.capability_requirements)
 }
 
-/// Checks if a capability can be invoked with the given role
+/// Checks if a capability can be invoked in the given authorization context
 pub fn check_capability_allowed(
     capability: &str,
-    role: &str,
-    scopes: &[String],
+    context: &AuthContext,
     requirements: &HashMap<String, CapabilityRequirement>,
 ) -> Result<(), Box<dyn Error>> {
+    let mut decision = Decision::new();
+    check_requirements(capability, context, requirements, &mut decision)
+        .map_err(|(_, reason)| reason.into())
+}
+
+/// Evaluates the capability's requirements, recording each rule in `decision`
+/// Returns the failing rule and reason on the first denial
+pub fn check_requirements(
+    capability: &str,
+    context: &AuthContext,
+    requirements: &HashMap<String, CapabilityRequirement>,
+    decision: &mut Decision,
+) -> Result<(), (String, String)> {
+    let role = context.role.as_str();
+    let scopes = &context.scopes;
+    let rule = format!("capability_requirements[{}]", capability);
+    
     // Get requirements for this capability
     let req = match requirements.get(capability) {
-        Some(r) => r,
+        Some(r) => {
+            decision.pass(&rule, "requirement entry exists");
+            r
+        }
         None => {
             // No explicit requirements = deny by default
-            return Err(format!("PERMISSION_DENIED: Capability '{}' has no policy (deny-by-default)", capability).into());
+            decision.fail(&rule, "no requirement entry (deny-by-default)");
+            return Err((rule, format!("PERMISSION_DENIED: Capability '{}' has no policy (deny-by-default)", capability)));
         }
     };
     
     // Check role requirement
     if let Some(required_roles) = &req.required_roles {
+        let roles_rule = format!("{}.required_roles", rule);
         if !required_roles.contains(&role.to_string()) {
-            return Err(format!(
+            decision.fail(&roles_rule, format!("role '{}' not in {:?}", role, required_roles));
+            return Err((roles_rule, format!(
                 "PERMISSION_DENIED: Role '{}' not authorized for capability '{}'",
                 role, capability
-            ).into());
+            )));
         }
+        decision.pass(&roles_rule, format!("role '{}' in {:?}", role, required_roles));
     }
     
     // Check scope requirements
     if let Some(required_scopes) = &req.required_scopes {
         for required_scope in required_scopes {
+            let scope_rule = format!("{}.required_scopes[{}]", rule, required_scope);
             if !scopes.contains(required_scope) {
-                return Err(format!(
+                decision.fail(&scope_rule, "scope not held by actor");
+                return Err((scope_rule, format!(
                     "PERMISSION_DENIED: Missing required scope '{}' for capability '{}'",
                     required_scope, capability
-                ).into());
+                )));
+            }
+            decision.pass(&scope_rule, "scope held by actor");
+        }
+    }
+    
+    // Check attribute conditions
+    if let Some(condition_list) = &req.conditions {
+        let facts = conditions::build_facts(
+            &context.actor_id,
+            &context.actor_type,
+            role,
+            scopes,
+            &context.args,
+            context.request_time,
+        );
+        
+        for (idx, PolicyCondition { source, condition }) in condition_list.iter().enumerate() {
+            let condition_rule = format!("{}.conditions[{}]", rule, idx);
+            let outcome = condition.evaluate(&facts);
+            
+            match outcome {
+                Ok(true) => decision.pass(&condition_rule, source.as_str()),
+                Ok(false) => {
+                    decision.fail(&condition_rule, source.as_str());
+                    return Err((condition_rule, format!(
+                        "PERMISSION_DENIED: Condition not satisfied: {} for capability '{}'",
+                        source, capability
+                    )));
+                }
+                Err(e) => {
+                    decision.fail(&condition_rule, format!("{} (error: {})", source, e));
+                    return Err((condition_rule, format!(
+                        "PERMISSION_DENIED: {} for capability '{}'",
+                        e, capability
+                    )));
+                }
             }
         }
     }
@@ -73,6 +147,20 @@ pub fn check_capability_allowed(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
+    
+    fn context(role: &str, scopes: &[&str]) -> AuthContext {
+        AuthContext {
+            actor_id: "user-123".to_string(),
+            actor_type: "user".to_string(),
+            role: role.to_string(),
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+            args: json!({}),
+            request_time: chrono::Utc::now(),
+            tenant_id: None,
+            caller: Default::default(),
+        }
+    }
     
     #[test]
     fn test_check_capability_allowed_success() {
@@ -82,14 +170,13 @@ mod tests {
             CapabilityRequirement {
                 required_scopes: Some(vec!["storage:write".to_string()]),
                 required_roles: Some(vec!["admin".to_string(), "editor".to_string()]),
+                conditions: None,
             }
         );
         
-        let scopes = vec!["storage:write".to_string()];
         let result = check_capability_allowed(
             "storage.listings.create",
-            "admin",
-            &scopes,
+            &context("admin", &["storage:write"]),
             &requirements
         );
         
@@ -104,14 +191,13 @@ mod tests {
             CapabilityRequirement {
                 required_scopes: Some(vec!["storage:write".to_string()]),
                 required_roles: Some(vec!["admin".to_string()]),
+                conditions: None,
             }
         );
         
-        let scopes = vec!["storage:write".to_string()];
         let result = check_capability_allowed(
             "storage.listings.create",
-            "viewer",
-            &scopes,
+            &context("viewer", &["storage:write"]),
             &requirements
         );
         
@@ -126,17 +212,53 @@ mod tests {
             CapabilityRequirement {
                 required_scopes: Some(vec!["storage:write".to_string()]),
                 required_roles: Some(vec!["admin".to_string()]),
+                conditions: None,
             }
         );
         
-        let scopes = vec!["storage:read".to_string()];
         let result = check_capability_allowed(
             "storage.listings.create",
-            "admin",
-            &scopes,
+            &context("admin", &["storage:read"]),
             &requirements
         );
         
         assert!(result.is_err());
     }
+    
+    #[test]
+    fn test_check_capability_conditions() {
+        let mut requirements = HashMap::new();
+        requirements.insert(
+            "storage.listings.update".to_string(),
+            CapabilityRequirement {
+                required_scopes: Some(vec!["storage:write".to_string()]),
+                required_roles: None,
+                conditions: Some(vec![PolicyCondition::parse("actor.role == 'admin' || args.owner_id == actor.id").unwrap()]),
+            }
+        );
+        
+        let mut editor = context("editor", &["storage:write"]);
+        editor.args = json!({"owner_id": "user-123"});
+        assert!(check_capability_allowed("storage.listings.update", &editor, &requirements).is_ok());
+        
+        editor.args = json!({"owner_id": "user-456"});
+        let err = check_capability_allowed("storage.listings.update", &editor, &requirements).unwrap_err();
+        assert!(err.to_string().contains("PERMISSION_DENIED"));
+        
+        let mut admin = context("admin", &["storage:write"]);
+        admin.args = json!({"owner_id": "user-456"});
+        assert!(check_capability_allowed("storage.listings.update", &admin, &requirements).is_ok());
+    }
+    
+    #[test]
+    fn test_parse_rejects_invalid_condition() {
+        let policy = r#"
+capability_requirements:
+  "storage.listings.update":
+    required_scopes: ["storage:write"]
+    conditions:
+      - "args.owner_id =="
+"#;
+        assert!(parse_capability_requirements(policy).is_err());
+    }
 }
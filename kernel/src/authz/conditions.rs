@@ -0,0 +1,561 @@
+// Policy Conditions
+// Small, deterministic, side-effect-free expression language for attribute-based checks
+//
+// Grammar:
+//   expr       := and ( "||" and )*
+//   and        := unary ( "&&" unary )*
+//   unary      := "!" unary | "(" expr ")" | comparison
+//   comparison := operand ( ("==" | "!=" | "<" | "<=" | ">" | ">=" | "in" | "not in") operand )?
+//   operand    := path | "string" | 'string' | number | true | false | null | list
+//   list       := "[" ( item ( "," item )* )? "]"   (bare words in lists are strings)
+//   path       := root ( "." segment )*              (root: actor, args, time)
+//
+// Conditions are three-valued. A missing attribute resolves to null, and a comparison with a
+// null operand (or null on its own) is unknown, whatever the operator. Unknown stays unknown
+// under `!`; `&&` and `||` follow Kleene logic (false && unknown is false, true || unknown is
+// true). A condition is satisfied only if it is true, so neither `args.owner_id != actor.id` nor
+// `!(args.owner_id == actor.id)` holds for an attribute that is not there.
+//
+// Examples:
+//   args.owner_id == actor.id
+//   actor.type in [user, service]
+//   time.hhmm >= "09:00" && time.hhmm < "18:00"
+
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::error::Error;
+
+/// Roots a condition path may start with
+const PATH_ROOTS: &[&str] = &["actor", "args", "time"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Or(Box<Condition>, Box<Condition>),
+    And(Box<Condition>, Box<Condition>),
+    Not(Box<Condition>),
+    Compare(Operand, CompareOp, Operand),
+    Truthy(Operand),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Path(Vec<String>),
+    Literal(Value),
+    List(Vec<Operand>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    In,
+    NotIn,
+}
+
+/// A condition as written in policy, parsed when the policy is loaded
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct PolicyCondition {
+    pub source: String,
+    pub condition: Condition,
+}
+
+impl PolicyCondition {
+    pub fn parse(source: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(PolicyCondition {
+            source: source.to_string(),
+            condition: Condition::parse(source)?,
+        })
+    }
+}
+
+impl TryFrom<String> for PolicyCondition {
+    type Error = String;
+    
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        PolicyCondition::parse(&source).map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Op(&'static str),
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+}
+
+impl Condition {
+    /// Parses a condition expression, rejecting unknown path roots
+    pub fn parse(source: &str) -> Result<Condition, Box<dyn Error>> {
+        let tokens = tokenize(source)
+            .map_err(|e| format!("Invalid condition '{}': {}", source, e))?;
+        
+        let mut parser = Parser { tokens, pos: 0 };
+        let condition = parser.parse_or()
+            .map_err(|e| format!("Invalid condition '{}': {}", source, e))?;
+        
+        if parser.pos != parser.tokens.len() {
+            return Err(format!(
+                "Invalid condition '{}': unexpected trailing input",
+                source
+            ).into());
+        }
+        
+        Ok(condition)
+    }
+    
+    /// Evaluates the condition against request facts (see `build_facts`)
+    /// True only if the condition holds; unknown (a missing attribute) is not satisfied
+    pub fn evaluate(&self, facts: &Value) -> Result<bool, Box<dyn Error>> {
+        Ok(self.truth(facts)? == Some(true))
+    }
+    
+    /// Three-valued result: None is unknown
+    fn truth(&self, facts: &Value) -> Result<Option<bool>, Box<dyn Error>> {
+        match self {
+            Condition::Or(a, b) => match a.truth(facts)? {
+                Some(true) => Ok(Some(true)),
+                lhs => Ok(match (lhs, b.truth(facts)?) {
+                    (_, Some(true)) => Some(true),
+                    (Some(false), Some(false)) => Some(false),
+                    _ => None,
+                }),
+            },
+            Condition::And(a, b) => match a.truth(facts)? {
+                Some(false) => Ok(Some(false)),
+                lhs => Ok(match (lhs, b.truth(facts)?) {
+                    (_, Some(false)) => Some(false),
+                    (Some(true), Some(true)) => Some(true),
+                    _ => None,
+                }),
+            },
+            Condition::Not(inner) => Ok(inner.truth(facts)?.map(|value| !value)),
+            Condition::Truthy(operand) => {
+                let value = operand.resolve(facts);
+                Ok((!value.is_null()).then(|| is_truthy(&value)))
+            }
+            Condition::Compare(lhs, op, rhs) => {
+                compare(&lhs.resolve(facts), *op, &rhs.resolve(facts))
+            }
+        }
+    }
+}
+
+impl Operand {
+    fn resolve(&self, facts: &Value) -> Value {
+        match self {
+            Operand::Literal(value) => value.clone(),
+            Operand::List(items) => Value::Array(items.iter().map(|i| i.resolve(facts)).collect()),
+            Operand::Path(segments) => {
+                let mut current = facts;
+                for segment in segments {
+                    current = match current.get(segment.as_str()) {
+                        Some(v) => v,
+                        None => return Value::Null,
+                    };
+                }
+                current.clone()
+            }
+        }
+    }
+}
+
+/// Builds the fact document conditions are evaluated against
+pub fn build_facts(
+    actor_id: &str,
+    actor_type: &str,
+    role: &str,
+    scopes: &[String],
+    args: &Value,
+    now: DateTime<Utc>,
+) -> Value {
+    json!({
+        "actor": {
+            "id": actor_id,
+            "type": actor_type,
+            "role": role,
+            "scopes": scopes,
+        },
+        "args": args,
+        "time": {
+            "hour": now.hour(),
+            "minute": now.minute(),
+            "hhmm": now.format("%H:%M").to_string(),
+            "weekday": now.format("%a").to_string().to_lowercase(),
+            "day": now.day(),
+        },
+    })
+}
+
+/// Evaluates every condition; all must hold. Type errors deny.
+pub fn check_conditions(conditions: &[PolicyCondition], facts: &Value) -> Result<(), Box<dyn Error>> {
+    for PolicyCondition { source, condition } in conditions {
+        if !condition.evaluate(facts)? {
+            return Err(format!("Condition not satisfied: {}", source).into());
+        }
+    }
+    Ok(())
+}
+
+/// None (unknown) when either side is null
+fn compare(lhs: &Value, op: CompareOp, rhs: &Value) -> Result<Option<bool>, Box<dyn Error>> {
+    if lhs.is_null() || rhs.is_null() {
+        return Ok(None);
+    }
+    
+    compare_values(lhs, op, rhs).map(Some)
+}
+
+fn compare_values(lhs: &Value, op: CompareOp, rhs: &Value) -> Result<bool, Box<dyn Error>> {
+    match op {
+        CompareOp::Eq => Ok(values_equal(lhs, rhs)),
+        CompareOp::Ne => Ok(!values_equal(lhs, rhs)),
+        CompareOp::In | CompareOp::NotIn => {
+            let found = match rhs {
+                Value::Array(items) => items.iter().any(|i| values_equal(lhs, i)),
+                Value::String(s) => lhs.as_str().map(|l| s.contains(l)).unwrap_or(false),
+                _ => return Err("Right side of 'in' must be a list or string".into()),
+            };
+            Ok(if op == CompareOp::In { found } else { !found })
+        }
+        CompareOp::Lt | CompareOp::Le | CompareOp::Gt | CompareOp::Ge => {
+            let ordering = match (lhs, rhs) {
+                (Value::Number(a), Value::Number(b)) => {
+                    let (a, b) = (a.as_f64().unwrap_or(f64::NAN), b.as_f64().unwrap_or(f64::NAN));
+                    a.partial_cmp(&b)
+                }
+                (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+                _ => None,
+            };
+            
+            let ordering = ordering.ok_or("Ordering comparison requires two numbers or two strings")?;
+            Ok(match op {
+                CompareOp::Lt => ordering.is_lt(),
+                CompareOp::Le => ordering.is_le(),
+                CompareOp::Gt => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            })
+        }
+    }
+}
+
+/// Equality that treats 1 and 1.0 as equal
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64() == y.as_f64(),
+        _ => a == b,
+    }
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().map(|f| f != 0.0).unwrap_or(false),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        Value::Object(o) => !o.is_empty(),
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            ' ' | '\t' | '\n' | '\r' => i += 1,
+            '(' => { tokens.push(Token::LParen); i += 1; }
+            ')' => { tokens.push(Token::RParen); i += 1; }
+            '[' => { tokens.push(Token::LBracket); i += 1; }
+            ']' => { tokens.push(Token::RBracket); i += 1; }
+            ',' => { tokens.push(Token::Comma); i += 1; }
+            '"' | '\'' => {
+                let end = chars[i + 1..].iter().position(|&ch| ch == c)
+                    .ok_or("unterminated string literal")?;
+                tokens.push(Token::Str(chars[i + 1..i + 1 + end].iter().collect()));
+                i += end + 2;
+            }
+            '=' | '!' | '<' | '>' | '&' | '|' => {
+                let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+                let op = match two.as_str() {
+                    "==" => "==",
+                    "!=" => "!=",
+                    "<=" => "<=",
+                    ">=" => ">=",
+                    "&&" => "&&",
+                    "||" => "||",
+                    _ => match c {
+                        '<' => "<",
+                        '>' => ">",
+                        '!' => "!",
+                        _ => return Err(format!("unexpected character '{}'", c)),
+                    },
+                };
+                tokens.push(Token::Op(op));
+                i += op.len();
+            }
+            c if c.is_ascii_digit() || (c == '-' && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit())) => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let num = text.parse::<f64>().map_err(|_| format!("invalid number '{}'", text))?;
+                tokens.push(Token::Num(num));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || matches!(chars[i], '_' | '.' | '-' | ':')) {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            }
+            _ => return Err(format!("unexpected character '{}'", c)),
+        }
+    }
+    
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+    
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+    
+    fn parse_or(&mut self) -> Result<Condition, String> {
+        let mut lhs = self.parse_and()?;
+        while self.peek() == Some(&Token::Op("||")) {
+            self.pos += 1;
+            let rhs = self.parse_and()?;
+            lhs = Condition::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+    
+    fn parse_and(&mut self) -> Result<Condition, String> {
+        let mut lhs = self.parse_unary()?;
+        while self.peek() == Some(&Token::Op("&&")) {
+            self.pos += 1;
+            let rhs = self.parse_unary()?;
+            lhs = Condition::And(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+    
+    fn parse_unary(&mut self) -> Result<Condition, String> {
+        match self.peek() {
+            Some(Token::Op("!")) => {
+                self.pos += 1;
+                Ok(Condition::Not(Box::new(self.parse_unary()?)))
+            }
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let inner = self.parse_or()?;
+                if self.next() != Some(Token::RParen) {
+                    return Err("expected ')'".to_string());
+                }
+                Ok(inner)
+            }
+            _ => self.parse_comparison(),
+        }
+    }
+    
+    fn parse_comparison(&mut self) -> Result<Condition, String> {
+        let lhs = self.parse_operand()?;
+        
+        let op = match self.peek() {
+            Some(Token::Op("==")) => CompareOp::Eq,
+            Some(Token::Op("!=")) => CompareOp::Ne,
+            Some(Token::Op("<")) => CompareOp::Lt,
+            Some(Token::Op("<=")) => CompareOp::Le,
+            Some(Token::Op(">")) => CompareOp::Gt,
+            Some(Token::Op(">=")) => CompareOp::Ge,
+            Some(Token::Ident(word)) if word == "in" => CompareOp::In,
+            Some(Token::Ident(word)) if word == "not" => {
+                if self.tokens.get(self.pos + 1) != Some(&Token::Ident("in".to_string())) {
+                    return Err("expected 'in' after 'not'".to_string());
+                }
+                self.pos += 1;
+                CompareOp::NotIn
+            }
+            _ => return Ok(Condition::Truthy(lhs)),
+        };
+        self.pos += 1;
+        
+        let rhs = self.parse_operand()?;
+        Ok(Condition::Compare(lhs, op, rhs))
+    }
+    
+    fn parse_operand(&mut self) -> Result<Operand, String> {
+        match self.next() {
+            Some(Token::Str(s)) => Ok(Operand::Literal(Value::String(s))),
+            Some(Token::Num(n)) => Ok(Operand::Literal(json!(n))),
+            Some(Token::Ident(word)) => match word.as_str() {
+                "true" => Ok(Operand::Literal(Value::Bool(true))),
+                "false" => Ok(Operand::Literal(Value::Bool(false))),
+                "null" => Ok(Operand::Literal(Value::Null)),
+                _ => parse_path(&word),
+            },
+            Some(Token::LBracket) => {
+                let mut items = Vec::new();
+                if self.peek() == Some(&Token::RBracket) {
+                    self.pos += 1;
+                    return Ok(Operand::List(items));
+                }
+                loop {
+                    let item = match self.next() {
+                        // Bare words inside lists are string literals: [user, service]
+                        Some(Token::Ident(word)) => Operand::Literal(Value::String(word)),
+                        Some(Token::Str(s)) => Operand::Literal(Value::String(s)),
+                        Some(Token::Num(n)) => Operand::Literal(json!(n)),
+                        other => return Err(format!("unexpected list item {:?}", other)),
+                    };
+                    items.push(item);
+                    match self.next() {
+                        Some(Token::Comma) => continue,
+                        Some(Token::RBracket) => break,
+                        _ => return Err("expected ',' or ']' in list".to_string()),
+                    }
+                }
+                Ok(Operand::List(items))
+            }
+            other => Err(format!("expected operand, found {:?}", other)),
+        }
+    }
+}
+
+fn parse_path(word: &str) -> Result<Operand, String> {
+    let segments: Vec<String> = word.split('.').map(|s| s.to_string()).collect();
+    
+    if segments.iter().any(|s| s.is_empty()) {
+        return Err(format!("invalid path '{}'", word));
+    }
+    if !PATH_ROOTS.contains(&segments[0].as_str()) {
+        return Err(format!(
+            "unknown attribute root '{}' (expected one of: {})",
+            segments[0],
+            PATH_ROOTS.join(", ")
+        ));
+    }
+    
+    Ok(Operand::Path(segments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    
+    fn facts(actor_id: &str, actor_type: &str, args: Value, hour: u32) -> Value {
+        let now = Utc.with_ymd_and_hms(2026, 1, 12, hour, 30, 0).unwrap(); // Monday
+        build_facts(actor_id, actor_type, "editor", &["storage:write".to_string()], &args, now)
+    }
+    
+    fn eval(source: &str, facts: &Value) -> bool {
+        Condition::parse(source).unwrap().evaluate(facts).unwrap()
+    }
+    
+    #[test]
+    fn test_owner_condition() {
+        let own = facts("user-1", "user", json!({"owner_id": "user-1"}), 10);
+        let other = facts("user-1", "user", json!({"owner_id": "user-2"}), 10);
+        
+        assert!(eval("args.owner_id == actor.id", &own));
+        assert!(!eval("args.owner_id == actor.id", &other));
+    }
+    
+    #[test]
+    fn test_in_list_and_boolean_operators() {
+        let f = facts("svc-1", "service", json!({}), 10);
+        
+        assert!(eval("actor.type in [user, service]", &f));
+        assert!(eval("actor.type not in [system]", &f));
+        assert!(eval("!(actor.type == 'user') && actor.role == \"editor\"", &f));
+        assert!(eval("actor.type == 'user' || 'storage:write' in actor.scopes", &f));
+    }
+    
+    #[test]
+    fn test_time_window() {
+        let window = "time.hhmm >= '09:00' && time.hhmm < '18:00' && time.weekday in [mon, tue, wed, thu, fri]";
+        
+        assert!(eval(window, &facts("u", "user", json!({}), 10)));
+        assert!(!eval(window, &facts("u", "user", json!({}), 20)));
+    }
+    
+    #[test]
+    fn test_missing_attribute_is_not_satisfied() {
+        let f = facts("user-1", "user", json!({}), 10);
+        
+        assert!(!eval("args.owner_id == actor.id", &f));
+        assert!(!eval("args.count > 5", &f));
+        assert!(!eval("args.flag", &f));
+        
+        // Negated comparisons do not fail open, nor does negating a comparison
+        assert!(!eval("args.owner_id != actor.id", &f));
+        assert!(!eval("!(args.owner_id == actor.id)", &f));
+        assert!(!eval("!args.flag", &f));
+        assert!(!eval("!(args.count > 5 || args.owner_id == actor.id)", &f));
+        assert!(!eval("args.owner_id not in [user-2]", &f));
+        assert!(!eval("args.owner_id == args.creator_id", &f));
+        assert!(!eval("actor.type in args.allowed_types", &f));
+        assert!(!eval("actor.type not in args.blocked_types", &f));
+        assert!(!eval("args.owner_id == null", &f));
+        
+        // Unknown is decided by the other side of && and || where it can be
+        assert!(eval("actor.type == 'user' || args.owner_id == actor.id", &f));
+        assert!(eval("!(actor.type == 'service' && args.owner_id == actor.id)", &f));
+        assert!(!eval("actor.type == 'user' && args.owner_id == actor.id", &f));
+    }
+    
+    #[test]
+    fn test_policy_condition_deserializes_parsed() {
+        let conditions: Vec<PolicyCondition> = serde_yaml::from_str("['args.owner_id == actor.id']").unwrap();
+        assert_eq!(conditions[0].source, "args.owner_id == actor.id");
+        assert_eq!(conditions[0].condition, Condition::parse("args.owner_id == actor.id").unwrap());
+        
+        assert!(serde_yaml::from_str::<Vec<PolicyCondition>>("['args.owner_id ==']").is_err());
+    }
+    
+    #[test]
+    fn test_parse_errors() {
+        assert!(Condition::parse("args.owner_id ==").is_err());
+        assert!(Condition::parse("request.id == 'x'").is_err()); // unknown root
+        assert!(Condition::parse("(actor.id == 'x'").is_err());
+        assert!(Condition::parse("actor.id = 'x'").is_err());
+        assert!(Condition::parse("actor.id == 'x' actor.type").is_err());
+    }
+    
+    #[test]
+    fn test_type_mismatch_is_error() {
+        let f = facts("user-1", "user", json!({"count": 3}), 10);
+        let condition = Condition::parse("args.count > 'abc'").unwrap();
+        
+        assert!(condition.evaluate(&f).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+// Authorization Decision
+// Structured outcome of authz and routing checks, with a trace of every rule evaluated
+
+use serde::Serialize;
+use serde_json::Value;
+use std::error::Error;
+
+/// Capability an actor needs before `options.explain` attaches the trace to a denial
+pub const EXPLAIN_CAPABILITY: &str = "authz.explain";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleOutcome {
+    Pass,
+    Fail,
+}
+
+/// A single rule that was evaluated while reaching a decision
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleCheck {
+    pub rule: String,
+    pub outcome: RuleOutcome,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Decision {
+    pub allowed: bool,
+    /// Rule that settled the decision (the failing rule on deny, the last rule on allow)
+    pub deciding_rule: Option<String>,
+    /// Human-readable reason; carries the error code prefix on deny
+    pub reason: Option<String>,
+    /// Break-glass grant that allowed the request, if any (audited on every use)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grant_id: Option<String>,
+    pub checks: Vec<RuleCheck>,
+}
+
+impl Decision {
+    /// Starts an undecided (denied) decision with an empty trace
+    pub fn new() -> Self {
+        Decision {
+            allowed: false,
+            deciding_rule: None,
+            reason: None,
+            grant_id: None,
+            checks: Vec::new(),
+        }
+    }
+    
+    /// Records a rule that passed
+    pub fn pass(&mut self, rule: impl Into<String>, detail: impl Into<String>) {
+        self.checks.push(RuleCheck {
+            rule: rule.into(),
+            outcome: RuleOutcome::Pass,
+            detail: detail.into(),
+        });
+    }
+    
+    /// Records a rule that failed
+    pub fn fail(&mut self, rule: impl Into<String>, detail: impl Into<String>) {
+        self.checks.push(RuleCheck {
+            rule: rule.into(),
+            outcome: RuleOutcome::Fail,
+            detail: detail.into(),
+        });
+    }
+    
+    /// Finalizes as allowed; the deciding rule is the last one recorded
+    pub fn allow(mut self) -> Self {
+        self.allowed = true;
+        self.deciding_rule = self.checks.last().map(|c| c.rule.clone());
+        self.reason = None;
+        self
+    }
+    
+    /// Finalizes as denied by `rule`
+    pub fn deny(mut self, rule: impl Into<String>, reason: impl Into<String>) -> Self {
+        self.allowed = false;
+        self.deciding_rule = Some(rule.into());
+        self.reason = Some(reason.into());
+        self
+    }
+    
+    pub fn is_allowed(&self) -> bool {
+        self.allowed
+    }
+    
+    /// Denial reason, or an empty string if allowed
+    pub fn reason(&self) -> &str {
+        self.reason.as_deref().unwrap_or("")
+    }
+    
+    /// Converts to the legacy Result form: Ok if allowed, Err(reason) if denied
+    pub fn into_result(self) -> Result<Decision, Box<dyn Error>> {
+        if self.allowed {
+            Ok(self)
+        } else {
+            Err(self.reason().to_string().into())
+        }
+    }
+    
+    /// JSON form used in `details.context.decision` of explained error envelopes
+    pub fn to_json(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+}
+
+impl Default for Decision {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_deny_records_deciding_rule() {
+        let mut decision = Decision::new();
+        decision.pass("role_lookup", "role 'viewer' exists");
+        decision.fail("role_capability", "no pattern matches");
+        let decision = decision.deny("role_capability", "PERMISSION_DENIED: nope");
+        
+        assert!(!decision.is_allowed());
+        assert_eq!(decision.deciding_rule.as_deref(), Some("role_capability"));
+        assert_eq!(decision.checks.len(), 2);
+        assert_eq!(decision.checks[1].outcome, RuleOutcome::Fail);
+        assert!(decision.into_result().unwrap_err().to_string().contains("PERMISSION_DENIED"));
+    }
+    
+    #[test]
+    fn test_allow_uses_last_rule() {
+        let mut decision = Decision::new();
+        decision.pass("role_lookup", "ok");
+        decision.pass("capability_requirements[x]", "ok");
+        let decision = decision.allow();
+        
+        assert!(decision.is_allowed());
+        assert_eq!(decision.deciding_rule.as_deref(), Some("capability_requirements[x]"));
+        
+        let json = decision.to_json();
+        assert_eq!(json["checks"][0]["outcome"], "pass");
+    }
+}
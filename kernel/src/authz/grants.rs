@@ -0,0 +1,154 @@
+// Break-Glass Grants
+// Time-boxed, per-actor capability grants from system/policy/grants.yaml
+//
+// A grant stands in for the role's capability allow-list and `required_roles`
+// for the capabilities it names; explicit `deny` rules and attribute conditions
+// still apply. Expired grants are ignored.
+
+use crate::primitives::capability_pattern;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+
+#[derive(Debug, Clone)]
+pub struct Grant {
+    pub id: String,
+    pub actor_id: String,
+    /// Capability patterns covered by the grant
+    pub capabilities: Vec<String>,
+    /// Extra scopes held while the grant is used
+    pub scopes: Vec<String>,
+    pub expires_at: DateTime<Utc>,
+    /// Why the grant exists (mandatory, recorded in audit)
+    pub reason: String,
+    pub granted_by: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GrantsPolicy {
+    #[serde(default)]
+    grants: Vec<GrantEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GrantEntry {
+    id: String,
+    actor_id: String,
+    capabilities: Vec<String>,
+    #[serde(default)]
+    scopes: Vec<String>,
+    expires_at: String,
+    #[serde(default)]
+    reason: String,
+    granted_by: Option<String>,
+}
+
+/// Loads grants from system/policy/grants.yaml
+pub fn load_grants() -> Result<Vec<Grant>, Box<dyn Error>> {
+    let policy_path = "/home/runner/work/cabinet/cabinet/system/policy/grants.yaml";
+    let content = fs::read_to_string(policy_path)
+        .map_err(|e| format!("Failed to read grants policy: {}", e))?;
+    
+    parse_grants(&content)
+}
+
+/// Parses and validates grants; every grant needs a unique id, a reason and an RFC 3339 expiry
+pub fn parse_grants(content: &str) -> Result<Vec<Grant>, Box<dyn Error>> {
+    let policy: GrantsPolicy = serde_yaml::from_str(content)
+        .map_err(|e| format!("Failed to parse grants policy: {}", e))?;
+    
+    let mut seen = HashSet::new();
+    let mut grants = Vec::new();
+    
+    for entry in policy.grants {
+        if !seen.insert(entry.id.clone()) {
+            return Err(format!("Duplicate grant id: {}", entry.id).into());
+        }
+        
+        if entry.reason.trim().is_empty() {
+            return Err(format!("Grant '{}': reason is required", entry.id).into());
+        }
+        
+        if entry.capabilities.is_empty() {
+            return Err(format!("Grant '{}': at least one capability is required", entry.id).into());
+        }
+        
+        for pattern in &entry.capabilities {
+            capability_pattern::validate_pattern(pattern)
+                .map_err(|e| format!("Grant '{}': {}", entry.id, e))?;
+        }
+        
+        let expires_at = DateTime::parse_from_rfc3339(&entry.expires_at)
+            .map_err(|e| format!("Grant '{}': invalid expires_at '{}': {}", entry.id, entry.expires_at, e))?
+            .with_timezone(&Utc);
+        
+        grants.push(Grant {
+            id: entry.id,
+            actor_id: entry.actor_id,
+            capabilities: entry.capabilities,
+            scopes: entry.scopes,
+            expires_at,
+            reason: entry.reason,
+            granted_by: entry.granted_by,
+        });
+    }
+    
+    Ok(grants)
+}
+
+impl Grant {
+    pub fn covers(&self, actor_id: &str, capability: &str) -> bool {
+        self.actor_id == actor_id
+            && self.capabilities.iter().any(|p| capability_pattern::pattern_matches(p, capability))
+    }
+    
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        now < self.expires_at
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const GRANTS: &str = r#"
+version: v1.0.0
+grants:
+  - id: bg-001
+    actor_id: user-42
+    capabilities: ["storage.listings.delete"]
+    scopes: ["storage:delete"]
+    expires_at: "2026-03-01T00:00:00Z"
+    reason: "INC-981: purge duplicated listings"
+    granted_by: ops-lead
+"#;
+    
+    #[test]
+    fn test_parse_and_match_grant() {
+        let grants = parse_grants(GRANTS).unwrap();
+        let grant = &grants[0];
+        
+        assert!(grant.covers("user-42", "storage.listings.delete"));
+        assert!(!grant.covers("user-43", "storage.listings.delete"));
+        assert!(!grant.covers("user-42", "storage.listings.create"));
+        
+        let before = DateTime::parse_from_rfc3339("2026-02-28T23:59:59Z").unwrap().with_timezone(&Utc);
+        let after = DateTime::parse_from_rfc3339("2026-03-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        assert!(grant.is_active(before));
+        assert!(!grant.is_active(after));
+    }
+    
+    #[test]
+    fn test_reason_is_mandatory() {
+        let missing = GRANTS.replace("    reason: \"INC-981: purge duplicated listings\"\n", "");
+        assert!(parse_grants(&missing).unwrap_err().to_string().contains("reason is required"));
+    }
+    
+    #[test]
+    fn test_invalid_expiry_rejected() {
+        let bad = GRANTS.replace("2026-03-01T00:00:00Z", "next tuesday");
+        assert!(parse_grants(&bad).is_err());
+    }
+}
@@ -3,4 +3,9 @@
 
 pub mod roles;
 pub mod capabilities;
+pub mod conditions;
+pub mod decision;
+pub mod grants;
+pub mod tokens;
 pub mod authorize;
+pub mod access_analysis;
@@ -1,16 +1,24 @@
 // Role Management
 // Maps and validates roles from access policy
 
+use crate::primitives::capability_pattern;
+use crate::primitives::capability_trie::CapabilityTrie;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 use std::fs;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Role {
     pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inherits: Option<Vec<String>>,
     pub scopes: Vec<String>,
     pub capabilities: Option<Vec<String>>,
+    /// Capability patterns this role may never invoke, even if an allow or grant matches
+    /// Not inherited: a role that inherits from this one keeps its own allow-list
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deny: Option<Vec<String>>,
     pub rate_limit_per_minute: u32,
     pub max_request_size_bytes: u64,
 }
@@ -23,12 +31,18 @@ struct AccessPolicy {
 }
 
 /// Loads roles from system/policy/access.yaml
+/// Returned roles are flattened: inherited scopes and capabilities are already merged in
 pub fn load_roles() -> Result<HashMap<String, Role>, Box<dyn Error>> {
     let policy_path = "/home/runner/work/cabinet/cabinet/system/policy/access.yaml";
     let content = fs::read_to_string(policy_path)
         .map_err(|e| format!("Failed to read access policy: {}", e))?;
     
-    let policy: AccessPolicy = serde_yaml::from_str(&content)
+    parse_roles(&content)
+}
+
+/// Parses roles from access policy content and resolves inheritance
+pub fn parse_roles(content: &str) -> Result<HashMap<String, Role>, Box<dyn Error>> {
+    let policy: AccessPolicy = serde_yaml::from_str(content)
         .map_err(|e| format!("Failed to parse access policy: {}", e))?;
     
     // Verify deny-by-default policy
@@ -36,7 +50,96 @@ pub fn load_roles() -> Result<HashMap<String, Role>, Box<dyn Error>> {
         return Err("Access policy must be deny_by_default".into());
     }
     
-    Ok(policy.roles)
+    resolve_inheritance(&policy.roles)
+}
+
+/// Resolves `inherits` into effective scopes and capabilities for every role
+/// Parents are merged first, so a role's own entries follow its inherited ones
+pub fn resolve_inheritance(roles: &HashMap<String, Role>) -> Result<HashMap<String, Role>, Box<dyn Error>> {
+    let mut resolved = HashMap::new();
+    
+    // Sorted for deterministic error messages
+    let mut names: Vec<&String> = roles.keys().collect();
+    names.sort();
+    
+    for name in names {
+        let mut stack = Vec::new();
+        resolve_role(name, roles, &mut resolved, &mut stack)?;
+    }
+    
+    Ok(resolved)
+}
+
+fn resolve_role(
+    name: &str,
+    roles: &HashMap<String, Role>,
+    resolved: &mut HashMap<String, Role>,
+    stack: &mut Vec<String>,
+) -> Result<(), Box<dyn Error>> {
+    if resolved.contains_key(name) {
+        return Ok(());
+    }
+    
+    if stack.iter().any(|s| s == name) {
+        stack.push(name.to_string());
+        return Err(format!("Role inheritance cycle detected: {}", stack.join(" -> ")).into());
+    }
+    
+    let role = roles.get(name)
+        .ok_or_else(|| format!("Unknown role: {}", name))?;
+    
+    stack.push(name.to_string());
+    
+    let mut scopes = Vec::new();
+    let mut capabilities = Vec::new();
+    
+    for parent_name in role.inherits.iter().flatten() {
+        if !roles.contains_key(parent_name) {
+            return Err(format!(
+                "Role '{}' inherits from unknown role '{}'",
+                name, parent_name
+            ).into());
+        }
+        
+        resolve_role(parent_name, roles, resolved, stack)?;
+        
+        let parent = &resolved[parent_name.as_str()];
+        merge_unique(&mut scopes, &parent.scopes);
+        merge_unique(&mut capabilities, parent.capabilities.as_deref().unwrap_or_default());
+    }
+    
+    stack.pop();
+    
+    merge_unique(&mut scopes, &role.scopes);
+    merge_unique(&mut capabilities, role.capabilities.as_deref().unwrap_or_default());
+    
+    let mut effective = role.clone();
+    effective.scopes = scopes;
+    effective.capabilities = if capabilities.is_empty() && role.capabilities.is_none() {
+        None
+    } else {
+        Some(capabilities)
+    };
+    
+    resolved.insert(name.to_string(), effective);
+    Ok(())
+}
+
+/// Appends entries not yet present, preserving first-seen order
+fn merge_unique(target: &mut Vec<String>, source: &[String]) {
+    for item in source {
+        if !target.contains(item) {
+            target.push(item.clone());
+        }
+    }
+}
+
+/// Renders flattened effective roles as YAML for review (sorted by role name)
+pub fn render_effective_roles(roles: &HashMap<String, Role>) -> Result<String, Box<dyn Error>> {
+    let sorted: BTreeMap<&String, &Role> = roles.iter().collect();
+    
+    serde_yaml::to_string(&sorted)
+        .map_err(|e| format!("Failed to render effective roles: {}", e).into())
 }
 
 /// Validates that a role exists and returns it
@@ -52,21 +155,84 @@ pub fn role_has_scope(role: &Role, scope: &str) -> bool {
 
 /// Checks if a role has a specific capability (supports wildcard matching)
 pub fn role_has_capability(role: &Role, capability: &str) -> bool {
-    if let Some(caps) = &role.capabilities {
-        for cap in caps {
-            if cap == capability {
-                return true;
+    matching_capability_pattern(role, capability).is_some()
+}
+
+/// Returns the first capability pattern of the role that grants `capability`
+pub fn matching_capability_pattern<'a>(role: &'a Role, capability: &str) -> Option<&'a str> {
+    role.capabilities.iter()
+        .flatten()
+        .find(|cap| capability_pattern::pattern_matches(cap, capability))
+        .map(|cap| cap.as_str())
+}
+
+/// Roles compiled once at load time for request-time lookups
+/// Capability patterns of each role live in a segment trie, so matching cost
+/// does not grow with the number of patterns a role holds
+pub struct RoleIndex {
+    roles: HashMap<String, Role>,
+    /// Per role: trie of capability patterns -> position in `Role.capabilities`
+    capability_tries: HashMap<String, CapabilityTrie<usize>>,
+    /// Per role: trie of deny patterns -> position in `Role.deny`
+    deny_tries: HashMap<String, CapabilityTrie<usize>>,
+}
+
+impl RoleIndex {
+    /// Compiles flattened roles; fails on malformed capability patterns
+    pub fn compile(roles: HashMap<String, Role>) -> Result<Self, Box<dyn Error>> {
+        let mut capability_tries = HashMap::new();
+        let mut deny_tries = HashMap::new();
+        
+        for (name, role) in &roles {
+            let mut trie = CapabilityTrie::new();
+            for (position, pattern) in role.capabilities.iter().flatten().enumerate() {
+                trie.insert(pattern, position)
+                    .map_err(|e| format!("Role '{}': {}", name, e))?;
             }
-            // Wildcard matching: "storage.*" matches "storage.listings.create"
-            if cap.ends_with(".*") {
-                let prefix = &cap[..cap.len() - 2];
-                if capability.starts_with(prefix) {
-                    return true;
-                }
+            capability_tries.insert(name.clone(), trie);
+            
+            let mut deny_trie = CapabilityTrie::new();
+            for (position, pattern) in role.deny.iter().flatten().enumerate() {
+                deny_trie.insert(pattern, position)
+                    .map_err(|e| format!("Role '{}' deny: {}", name, e))?;
             }
+            deny_tries.insert(name.clone(), deny_trie);
         }
+        
+        Ok(RoleIndex { roles, capability_tries, deny_tries })
+    }
+    
+    /// All compiled roles by name
+    pub fn roles(&self) -> &HashMap<String, Role> {
+        &self.roles
+    }
+    
+    /// Validates that a role exists and returns it
+    pub fn get_role(&self, role_name: &str) -> Result<&Role, Box<dyn Error>> {
+        get_role(role_name, &self.roles)
+    }
+    
+    /// Returns the first (in policy order) pattern of the role that grants `capability`
+    pub fn matching_capability_pattern(&self, role_name: &str, capability: &str) -> Option<&str> {
+        let trie = self.capability_tries.get(role_name)?;
+        let position = trie.matches(capability).into_iter().min()?;
+        
+        self.roles.get(role_name)?
+            .capabilities.as_ref()?
+            .get(*position)
+            .map(|p| p.as_str())
+    }
+    
+    /// Returns the first (in policy order) deny pattern of the role that matches `capability`
+    pub fn matching_deny_pattern(&self, role_name: &str, capability: &str) -> Option<&str> {
+        let trie = self.deny_tries.get(role_name)?;
+        let position = trie.matches(capability).into_iter().min()?;
+        
+        self.roles.get(role_name)?
+            .deny.as_ref()?
+            .get(*position)
+            .map(|p| p.as_str())
     }
-    false
 }
 
 #[cfg(test)]
@@ -77,8 +243,10 @@ mod tests {
     fn test_role_has_scope() {
         let role = Role {
             description: "Test".to_string(),
+            inherits: None,
             scopes: vec!["storage:read".to_string(), "storage:write".to_string()],
             capabilities: None,
+            deny: None,
             rate_limit_per_minute: 100,
             max_request_size_bytes: 1024,
         };
@@ -92,11 +260,13 @@ mod tests {
     fn test_role_has_capability() {
         let role = Role {
             description: "Test".to_string(),
+            inherits: None,
             scopes: vec![],
             capabilities: Some(vec![
                 "storage.listings.create".to_string(),
-                "storage.*".to_string()
+                "storage.**".to_string()
             ]),
+            deny: None,
             rate_limit_per_minute: 100,
             max_request_size_bytes: 1024,
         };
@@ -104,6 +274,95 @@ mod tests {
         assert!(role_has_capability(&role, "storage.listings.create"));
         assert!(role_has_capability(&role, "storage.listings.get"));
         assert!(role_has_capability(&role, "storage.imports.register"));
+        assert!(!role_has_capability(&role, "storage"));
         assert!(!role_has_capability(&role, "pricing.calculate"));
     }
+    
+    const INHERITING_POLICY: &str = r#"
+version: v1.0.0
+policy: deny_by_default
+roles:
+  viewer:
+    description: "Viewer"
+    scopes: ["storage:read"]
+    capabilities: ["storage.listings.get"]
+    rate_limit_per_minute: 200
+    max_request_size_bytes: 1024
+  editor:
+    description: "Editor"
+    inherits: [viewer]
+    scopes: ["storage:write"]
+    capabilities: ["storage.listings.create"]
+    rate_limit_per_minute: 100
+    max_request_size_bytes: 1024
+  admin:
+    description: "Admin"
+    inherits: [editor]
+    scopes: ["admin"]
+    capabilities: ["storage.**"]
+    rate_limit_per_minute: 1000
+    max_request_size_bytes: 1024
+"#;
+    
+    #[test]
+    fn test_inheritance_flattens_scopes_and_capabilities() {
+        let roles = parse_roles(INHERITING_POLICY).unwrap();
+        
+        let editor = &roles["editor"];
+        assert_eq!(editor.scopes, vec!["storage:read", "storage:write"]);
+        assert!(role_has_capability(editor, "storage.listings.get"));
+        assert!(role_has_capability(editor, "storage.listings.create"));
+        
+        let admin = &roles["admin"];
+        assert_eq!(admin.scopes, vec!["storage:read", "storage:write", "admin"]);
+        assert_eq!(admin.rate_limit_per_minute, 1000);
+    }
+    
+    #[test]
+    fn test_inheritance_cycle_rejected() {
+        let policy = INHERITING_POLICY.replace(
+            "description: \"Viewer\"",
+            "description: \"Viewer\"\n    inherits: [admin]",
+        );
+        
+        let err = parse_roles(&policy).unwrap_err().to_string();
+        assert!(err.contains("cycle"), "unexpected error: {}", err);
+    }
+    
+    #[test]
+    fn test_inheritance_unknown_parent_rejected() {
+        let policy = INHERITING_POLICY.replace("inherits: [viewer]", "inherits: [ghost]");
+        
+        let err = parse_roles(&policy).unwrap_err().to_string();
+        assert!(err.contains("unknown role 'ghost'"), "unexpected error: {}", err);
+    }
+    
+    #[test]
+    fn test_render_effective_roles() {
+        let roles = parse_roles(INHERITING_POLICY).unwrap();
+        let rendered = render_effective_roles(&roles).unwrap();
+        
+        // Sorted by role name, with inherited entries present
+        assert!(rendered.find("admin:").unwrap() < rendered.find("editor:").unwrap());
+        assert!(rendered.contains("storage.listings.get"));
+    }
+    
+    #[test]
+    fn test_role_index_matches_like_linear_scan() {
+        let roles = parse_roles(INHERITING_POLICY).unwrap();
+        let index = RoleIndex::compile(roles.clone()).unwrap();
+        
+        for capability in ["storage.listings.get", "storage.listings.create", "storage.imports.register", "pricing.calculate"] {
+            for (name, role) in &roles {
+                assert_eq!(
+                    index.matching_capability_pattern(name, capability),
+                    matching_capability_pattern(role, capability),
+                    "{} / {}", name, capability
+                );
+            }
+        }
+        
+        assert_eq!(index.matching_capability_pattern("admin", "storage.listings.get"), Some("storage.listings.get"));
+        assert!(index.matching_capability_pattern("ghost", "storage.listings.get").is_none());
+    }
 }
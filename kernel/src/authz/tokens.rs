@@ -0,0 +1,455 @@
+// Actor Tokens
+// Verifies signed actor tokens so the kernel never trusts caller-supplied identity
+//
+// Token format (JWT compact serialization):
+//   base64url(header) "." base64url(claims) "." base64url(signature)
+//   header: {"alg": "HS256" | "EdDSA", "kid": "<key id in keyring>"}
+//   claims: {"sub", "typ", "roles", "scopes", "aud", "exp", "iat", "jti", "tenant_id"?}
+
+use super::roles::RoleIndex;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenHeader {
+    pub alg: String,
+    pub kid: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActorClaims {
+    /// Actor ID
+    pub sub: String,
+    /// Actor type: user, service, system
+    pub typ: String,
+    pub roles: Vec<String>,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    pub aud: String,
+    /// Expiry (unix seconds)
+    pub exp: i64,
+    /// Issued at (unix seconds)
+    pub iat: i64,
+    /// Token ID, checked against the revocation list
+    pub jti: String,
+    /// Tenant (company) the actor belongs to; absent for single-tenant deployments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+}
+
+/// Key material from the keyring
+pub enum TokenKey {
+    Hs256(Vec<u8>),
+    Ed25519(VerifyingKey),
+}
+
+/// Key material used to issue tokens
+pub enum SigningMaterial {
+    Hs256(Vec<u8>),
+    Ed25519(SigningKey),
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenPolicy {
+    audience: String,
+    keyring_path: String,
+    revocation_list_path: String,
+    #[serde(default)]
+    clock_skew_seconds: i64,
+    max_token_lifetime_seconds: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Keyring {
+    keys: Vec<KeyringEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeyringEntry {
+    kid: String,
+    alg: String,
+    /// Base64 shared secret (HS256)
+    secret: Option<String>,
+    /// Base64 32-byte public key (EdDSA)
+    public_key: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RevocationList {
+    #[serde(default)]
+    revoked_token_ids: Vec<String>,
+}
+
+pub struct TokenVerifier {
+    audience: String,
+    keys: HashMap<String, TokenKey>,
+    revoked: HashSet<String>,
+    clock_skew_seconds: i64,
+    max_token_lifetime_seconds: i64,
+}
+
+impl TokenVerifier {
+    pub fn new(audience: &str, keys: HashMap<String, TokenKey>, max_token_lifetime_seconds: i64) -> Self {
+        TokenVerifier {
+            audience: audience.to_string(),
+            keys,
+            revoked: HashSet::new(),
+            clock_skew_seconds: 0,
+            max_token_lifetime_seconds,
+        }
+    }
+    
+    /// Marks token IDs as revoked
+    pub fn with_revoked(mut self, token_ids: impl IntoIterator<Item = String>) -> Self {
+        self.revoked.extend(token_ids);
+        self
+    }
+    
+    pub fn with_clock_skew(mut self, seconds: i64) -> Self {
+        self.clock_skew_seconds = seconds;
+        self
+    }
+    
+    /// Verifies signature, audience, expiry and revocation; returns the claims
+    pub fn verify(&self, token: &str, now_unix: i64) -> Result<ActorClaims, Box<dyn Error>> {
+        let parts: Vec<&str> = token.split('.').collect();
+        if parts.len() != 3 {
+            return Err("UNAUTHENTICATED: Malformed actor token".into());
+        }
+        
+        let header: TokenHeader = decode_segment(parts[0])
+            .map_err(|_| "UNAUTHENTICATED: Malformed actor token header")?;
+        
+        let key = self.keys.get(&header.kid)
+            .ok_or_else(|| format!("UNAUTHENTICATED: Unknown signing key '{}'", header.kid))?;
+        
+        let signature = URL_SAFE_NO_PAD.decode(parts[2])
+            .map_err(|_| "UNAUTHENTICATED: Malformed actor token signature")?;
+        let signed = format!("{}.{}", parts[0], parts[1]);
+        
+        // The header's alg must agree with the key type; never let the token pick
+        let valid = match (key, header.alg.as_str()) {
+            (TokenKey::Hs256(secret), "HS256") => {
+                let mut mac = HmacSha256::new_from_slice(secret)
+                    .map_err(|_| "UNAUTHENTICATED: Invalid HS256 key")?;
+                mac.update(signed.as_bytes());
+                mac.verify_slice(&signature).is_ok()
+            }
+            (TokenKey::Ed25519(public_key), "EdDSA") => {
+                match Signature::from_slice(&signature) {
+                    Ok(sig) => public_key.verify(signed.as_bytes(), &sig).is_ok(),
+                    Err(_) => false,
+                }
+            }
+            _ => false,
+        };
+        
+        if !valid {
+            return Err("UNAUTHENTICATED: Invalid actor token signature".into());
+        }
+        
+        let claims: ActorClaims = decode_segment(parts[1])
+            .map_err(|_| "UNAUTHENTICATED: Malformed actor token claims")?;
+        
+        if claims.aud != self.audience {
+            return Err(format!("UNAUTHENTICATED: Token audience '{}' not accepted", claims.aud).into());
+        }
+        
+        if now_unix > claims.exp + self.clock_skew_seconds {
+            return Err("UNAUTHENTICATED: Actor token expired".into());
+        }
+        
+        if claims.iat > now_unix + self.clock_skew_seconds {
+            return Err("UNAUTHENTICATED: Actor token issued in the future".into());
+        }
+        
+        if claims.exp - claims.iat > self.max_token_lifetime_seconds {
+            return Err("UNAUTHENTICATED: Actor token lifetime exceeds policy".into());
+        }
+        
+        if self.revoked.contains(&claims.jti) {
+            return Err(format!("UNAUTHENTICATED: Actor token '{}' revoked", claims.jti).into());
+        }
+        
+        Ok(claims)
+    }
+}
+
+/// Snapshot key of the keyring named by actor token policy
+pub const KEYRING_SOURCE: &str = "actor_tokens/keyring";
+
+/// Snapshot key of the revocation list named by actor token policy
+pub const REVOCATION_LIST_SOURCE: &str = "actor_tokens/revocation_list";
+
+/// Loads token policy, keyring and revocation list
+pub fn load_token_verifier() -> Result<TokenVerifier, Box<dyn Error>> {
+    let policy_path = "/home/runner/work/cabinet/cabinet/system/policy/actor_tokens.yaml";
+    let content = fs::read_to_string(policy_path)
+        .map_err(|e| format!("Failed to read actor token policy: {}", e))?;
+    
+    let [(_, keyring_path), (_, revocation_path)] = token_files(&content)?;
+    let keyring = fs::read_to_string(&keyring_path)
+        .map_err(|e| format!("Failed to read keyring: {}", e))?;
+    let revocations = fs::read_to_string(&revocation_path)
+        .map_err(|e| format!("Failed to read revocation list: {}", e))?;
+    
+    parse_token_verifier(&content, &keyring, &revocations)
+}
+
+/// Files actor token policy names outside the policy directory, as (snapshot key, path)
+/// They are read into the policy snapshot so its hash and the watcher cover them
+pub fn token_files(content: &str) -> Result<[(&'static str, PathBuf); 2], Box<dyn Error>> {
+    let policy: TokenPolicy = serde_yaml::from_str(content)
+        .map_err(|e| format!("Failed to parse actor token policy: {}", e))?;
+    
+    Ok([
+        (KEYRING_SOURCE, PathBuf::from(policy.keyring_path)),
+        (REVOCATION_LIST_SOURCE, PathBuf::from(policy.revocation_list_path)),
+    ])
+}
+
+/// Builds a verifier from actor token policy, keyring and revocation list content
+pub fn parse_token_verifier(content: &str, keyring: &str, revocations: &str) -> Result<TokenVerifier, Box<dyn Error>> {
+    let policy: TokenPolicy = serde_yaml::from_str(content)
+        .map_err(|e| format!("Failed to parse actor token policy: {}", e))?;
+    
+    let keys = parse_keyring(keyring)?;
+    let revoked = parse_revocation_list(revocations)?;
+    
+    Ok(TokenVerifier::new(&policy.audience, keys, policy.max_token_lifetime_seconds)
+        .with_revoked(revoked)
+        .with_clock_skew(policy.clock_skew_seconds))
+}
+
+/// Parses a keyring file into verification keys by key ID
+pub fn parse_keyring(content: &str) -> Result<HashMap<String, TokenKey>, Box<dyn Error>> {
+    let keyring: Keyring = serde_yaml::from_str(content)
+        .map_err(|e| format!("Failed to parse keyring: {}", e))?;
+    
+    let mut keys = HashMap::new();
+    for entry in keyring.keys {
+        let key = match entry.alg.as_str() {
+            "HS256" => {
+                let secret = entry.secret.as_deref()
+                    .ok_or_else(|| format!("Key '{}': HS256 requires secret", entry.kid))?;
+                let bytes = STANDARD.decode(secret)
+                    .map_err(|_| format!("Key '{}': secret is not valid base64", entry.kid))?;
+                if bytes.len() < 32 {
+                    return Err(format!("Key '{}': HS256 secret must be at least 32 bytes", entry.kid).into());
+                }
+                TokenKey::Hs256(bytes)
+            }
+            "EdDSA" => {
+                let public_key = entry.public_key.as_deref()
+                    .ok_or_else(|| format!("Key '{}': EdDSA requires public_key", entry.kid))?;
+                let bytes: [u8; 32] = STANDARD.decode(public_key)
+                    .ok()
+                    .and_then(|b| b.try_into().ok())
+                    .ok_or_else(|| format!("Key '{}': public_key must be 32 base64 bytes", entry.kid))?;
+                let verifying_key = VerifyingKey::from_bytes(&bytes)
+                    .map_err(|_| format!("Key '{}': invalid Ed25519 public key", entry.kid))?;
+                TokenKey::Ed25519(verifying_key)
+            }
+            other => return Err(format!("Key '{}': unsupported alg '{}'", entry.kid, other).into()),
+        };
+        
+        if keys.insert(entry.kid.clone(), key).is_some() {
+            return Err(format!("Duplicate key id in keyring: {}", entry.kid).into());
+        }
+    }
+    
+    Ok(keys)
+}
+
+/// Parses the revocation list into token IDs
+pub fn parse_revocation_list(content: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let list: RevocationList = serde_yaml::from_str(content)
+        .map_err(|e| format!("Failed to parse revocation list: {}", e))?;
+    Ok(list.revoked_token_ids)
+}
+
+/// Issues a signed actor token (used by operators' tooling and tests)
+pub fn sign_token(claims: &ActorClaims, kid: &str, key: &SigningMaterial) -> Result<String, Box<dyn Error>> {
+    let alg = match key {
+        SigningMaterial::Hs256(_) => "HS256",
+        SigningMaterial::Ed25519(_) => "EdDSA",
+    };
+    let header = TokenHeader { alg: alg.to_string(), kid: kid.to_string() };
+    
+    let signed = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header)?),
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(claims)?)
+    );
+    
+    let signature = match key {
+        SigningMaterial::Hs256(secret) => {
+            let mut mac = HmacSha256::new_from_slice(secret)
+                .map_err(|_| "Invalid HS256 key")?;
+            mac.update(signed.as_bytes());
+            mac.finalize().into_bytes().to_vec()
+        }
+        SigningMaterial::Ed25519(signing_key) => signing_key.sign(signed.as_bytes()).to_bytes().to_vec(),
+    };
+    
+    Ok(format!("{}.{}", signed, URL_SAFE_NO_PAD.encode(signature)))
+}
+
+/// Keeps only the claimed scopes that the role actually grants
+pub fn clamp_scopes(claimed: &[String], role: &str, roles: &RoleIndex) -> Vec<String> {
+    match roles.roles().get(role) {
+        Some(r) => claimed.iter()
+            .filter(|s| r.scopes.contains(s))
+            .cloned()
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+fn decode_segment<T: for<'de> Deserialize<'de>>(segment: &str) -> Result<T, Box<dyn Error>> {
+    let bytes = URL_SAFE_NO_PAD.decode(segment)?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authz::roles;
+    
+    const NOW: i64 = 1_767_225_600; // 2026-01-01T00:00:00Z
+    
+    fn claims() -> ActorClaims {
+        ActorClaims {
+            sub: "user-123".to_string(),
+            typ: "user".to_string(),
+            roles: vec!["editor".to_string()],
+            scopes: vec!["storage:read".to_string()],
+            aud: "cabinet-kernel".to_string(),
+            exp: NOW + 600,
+            iat: NOW,
+            jti: "tok-1".to_string(),
+            tenant_id: None,
+        }
+    }
+    
+    fn hs256_verifier() -> TokenVerifier {
+        let mut keys = HashMap::new();
+        keys.insert("hs".to_string(), TokenKey::Hs256(vec![7u8; 32]));
+        TokenVerifier::new("cabinet-kernel", keys, 3600)
+    }
+    
+    #[test]
+    fn test_hs256_roundtrip() {
+        let token = sign_token(&claims(), "hs", &SigningMaterial::Hs256(vec![7u8; 32])).unwrap();
+        let verified = hs256_verifier().verify(&token, NOW + 10).unwrap();
+        
+        assert_eq!(verified.sub, "user-123");
+        assert_eq!(verified.roles, vec!["editor"]);
+    }
+    
+    #[test]
+    fn test_ed25519_roundtrip() {
+        let signing_key = SigningKey::from_bytes(&[9u8; 32]);
+        let mut keys = HashMap::new();
+        keys.insert("ed".to_string(), TokenKey::Ed25519(signing_key.verifying_key()));
+        let verifier = TokenVerifier::new("cabinet-kernel", keys, 3600);
+        
+        let token = sign_token(&claims(), "ed", &SigningMaterial::Ed25519(signing_key)).unwrap();
+        assert!(verifier.verify(&token, NOW).is_ok());
+    }
+    
+    #[test]
+    fn test_tampered_claims_rejected() {
+        let token = sign_token(&claims(), "hs", &SigningMaterial::Hs256(vec![7u8; 32])).unwrap();
+        
+        let mut forged = claims();
+        forged.roles = vec!["admin".to_string()];
+        let parts: Vec<&str> = token.split('.').collect();
+        let tampered = format!(
+            "{}.{}.{}",
+            parts[0],
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&forged).unwrap()),
+            parts[2]
+        );
+        
+        let err = hs256_verifier().verify(&tampered, NOW).unwrap_err();
+        assert!(err.to_string().contains("signature"));
+    }
+    
+    #[test]
+    fn test_expiry_audience_and_revocation() {
+        let key = SigningMaterial::Hs256(vec![7u8; 32]);
+        let token = sign_token(&claims(), "hs", &key).unwrap();
+        
+        assert!(hs256_verifier().verify(&token, NOW + 601).unwrap_err().to_string().contains("expired"));
+        
+        let mut other_aud = claims();
+        other_aud.aud = "someone-else".to_string();
+        let token_aud = sign_token(&other_aud, "hs", &key).unwrap();
+        assert!(hs256_verifier().verify(&token_aud, NOW).unwrap_err().to_string().contains("audience"));
+        
+        let revoked = hs256_verifier().with_revoked(vec!["tok-1".to_string()]);
+        assert!(revoked.verify(&token, NOW).unwrap_err().to_string().contains("revoked"));
+    }
+    
+    #[test]
+    fn test_alg_must_match_key_type() {
+        // An Ed25519 public key must never be usable as an HMAC secret
+        let signing_key = SigningKey::from_bytes(&[9u8; 32]);
+        let public_bytes = signing_key.verifying_key().to_bytes().to_vec();
+        let mut keys = HashMap::new();
+        keys.insert("ed".to_string(), TokenKey::Ed25519(signing_key.verifying_key()));
+        let verifier = TokenVerifier::new("cabinet-kernel", keys, 3600);
+        
+        let token = sign_token(&claims(), "ed", &SigningMaterial::Hs256(public_bytes)).unwrap();
+        assert!(verifier.verify(&token, NOW).is_err());
+    }
+    
+    #[test]
+    fn test_parse_keyring() {
+        let public_key = STANDARD.encode(SigningKey::from_bytes(&[9u8; 32]).verifying_key().to_bytes());
+        let keyring = format!(r#"
+keys:
+  - kid: hs
+    alg: HS256
+    secret: "{}"
+  - kid: ed
+    alg: EdDSA
+    public_key: "{}"
+"#, STANDARD.encode([7u8; 32]), public_key);
+        
+        let keys = parse_keyring(&keyring).unwrap();
+        assert_eq!(keys.len(), 2);
+        
+        assert!(parse_keyring("keys:\n  - kid: weak\n    alg: HS256\n    secret: \"c2hvcnQ=\"\n").is_err());
+    }
+    
+    #[test]
+    fn test_clamp_scopes() {
+        let roles = roles::parse_roles(r#"
+version: v1.0.0
+policy: deny_by_default
+roles:
+  editor:
+    description: "Editor"
+    scopes: ["storage:read", "storage:write"]
+    rate_limit_per_minute: 100
+    max_request_size_bytes: 1024
+"#).and_then(RoleIndex::compile).unwrap();
+        
+        let claimed = vec!["storage:write".to_string(), "admin".to_string()];
+        assert_eq!(clamp_scopes(&claimed, "editor", &roles), vec!["storage:write"]);
+        assert!(clamp_scopes(&claimed, "ghost", &roles).is_empty());
+    }
+}
@@ -5,3 +5,6 @@
 pub mod load_manifests;
 pub mod load_routes;
 pub mod load_system;
+pub mod module_registry;
+pub mod policy_set;
+pub mod policy_reload;
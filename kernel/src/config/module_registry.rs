@@ -0,0 +1,220 @@
+// Module Identity Registry
+// Canonical module IDs and their aliases from system/policy/modules.yaml
+//
+// Routing, limits, manifests and status name modules in different ways
+// (`storage`, `storage-module`, `car-storage`). Every such reference is resolved
+// here to one canonical ID when policy loads; unknown or ambiguous names fail the load.
+
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Where module directories (manifest.yaml, runtime files) live, one per canonical ID
+pub const MODULES_DIR: &str = "/home/runner/work/cabinet/cabinet/extensions/modules";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModuleIdentity {
+    /// Canonical ID; the module's directory under extensions/modules/
+    pub id: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Picks the module's sandbox backend unless limits.yaml names one
+    #[serde(default)]
+    pub trust_level: TrustLevel,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrustLevel {
+    Trusted,
+    #[default]
+    Untrusted,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModulesPolicy {
+    modules: Vec<ModuleIdentity>,
+    /// MODULES_DIR if unset
+    #[serde(default)]
+    modules_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ModuleRegistry {
+    /// Canonical ID -> identity
+    modules: BTreeMap<String, ModuleIdentity>,
+    /// Every known name (canonical or alias) -> canonical ID
+    names: HashMap<String, String>,
+    /// MODULES_DIR unless set
+    modules_dir: Option<PathBuf>,
+}
+
+/// Parses module registry content
+pub fn parse_module_registry(content: &str) -> Result<ModuleRegistry, Box<dyn Error>> {
+    let policy: ModulesPolicy = serde_yaml::from_str(content)
+        .map_err(|e| format!("Failed to parse module registry: {}", e))?;
+    
+    let mut registry = ModuleRegistry::new(policy.modules)?;
+    registry.modules_dir = policy.modules_dir;
+    Ok(registry)
+}
+
+impl ModuleRegistry {
+    /// Builds the registry; a name claimed by two modules is ambiguous and rejected
+    pub fn new(modules: Vec<ModuleIdentity>) -> Result<Self, Box<dyn Error>> {
+        let mut registry = ModuleRegistry::default();
+        
+        for module in modules {
+            if module.id.trim().is_empty() {
+                return Err("Module registry: empty module id".into());
+            }
+            
+            for name in std::iter::once(&module.id).chain(module.aliases.iter()) {
+                match registry.names.get(name) {
+                    Some(owner) if *owner != module.id => {
+                        return Err(format!(
+                            "Module registry: ambiguous module reference '{}' (claimed by '{}' and '{}')",
+                            name, owner, module.id
+                        ).into());
+                    }
+                    Some(_) if *name == module.id => {
+                        return Err(format!("Module registry: duplicate module id '{}'", name).into());
+                    }
+                    _ => {
+                        registry.names.insert(name.clone(), module.id.clone());
+                    }
+                }
+            }
+            
+            registry.modules.insert(module.id.clone(), module);
+        }
+        
+        Ok(registry)
+    }
+    
+    /// Canonical ID for a module ID or alias
+    pub fn resolve(&self, reference: &str) -> Result<&str, Box<dyn Error>> {
+        self.names.get(reference)
+            .map(|id| id.as_str())
+            .ok_or_else(|| format!("Unknown module '{}' (not in module registry)", reference).into())
+    }
+    
+    /// Re-keys per-module settings by canonical ID; `section` prefixes errors
+    /// Unknown modules fail, as do two entries naming the same module
+    pub fn rekey<V>(&self, entries: HashMap<String, V>, section: &str) -> Result<HashMap<String, V>, Box<dyn Error>> {
+        let mut resolved = HashMap::new();
+        let mut sources: HashMap<String, String> = HashMap::new();
+        
+        for (reference, value) in entries {
+            let id = self.resolve(&reference)
+                .map_err(|e| format!("{}: {}", section, e))?
+                .to_string();
+            if let Some(other) = sources.insert(id.clone(), reference.clone()) {
+                return Err(format!(
+                    "{}: '{}' and '{}' both name module '{}'",
+                    section, other, reference, id
+                ).into());
+            }
+            resolved.insert(id, value);
+        }
+        
+        Ok(resolved)
+    }
+    
+    /// Canonical IDs, sorted
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.modules.keys().map(|id| id.as_str())
+    }
+    
+    pub fn get(&self, id: &str) -> Option<&ModuleIdentity> {
+        self.modules.get(id)
+    }
+    
+    /// Trust level of a module (by canonical ID); unknown modules are untrusted
+    pub fn trust_level(&self, id: &str) -> TrustLevel {
+        self.get(id).map(|module| module.trust_level).unwrap_or_default()
+    }
+    
+    /// Directory holding the module directories
+    pub fn modules_dir(&self) -> &Path {
+        self.modules_dir.as_deref().unwrap_or(Path::new(MODULES_DIR))
+    }
+    
+    /// Directory of a module (by canonical ID)
+    pub fn module_dir(&self, id: &str) -> PathBuf {
+        self.modules_dir().join(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn module(id: &str, aliases: &[&str]) -> ModuleIdentity {
+        ModuleIdentity {
+            id: id.to_string(),
+            aliases: aliases.iter().map(|a| a.to_string()).collect(),
+            trust_level: TrustLevel::default(),
+        }
+    }
+    
+    #[test]
+    fn test_resolve_canonical_and_aliases() {
+        let registry = ModuleRegistry::new(vec![
+            module("storage", &["storage-module", "car-storage"]),
+            module("pricing", &[]),
+        ]).unwrap();
+        
+        for name in ["storage", "storage-module", "car-storage"] {
+            assert_eq!(registry.resolve(name).unwrap(), "storage");
+        }
+        assert_eq!(registry.resolve("pricing").unwrap(), "pricing");
+        assert!(registry.resolve("pricing-module").is_err());
+        assert_eq!(registry.ids().collect::<Vec<_>>(), vec!["pricing", "storage"]);
+    }
+    
+    #[test]
+    fn test_ambiguous_names_rejected() {
+        let shared_alias = ModuleRegistry::new(vec![
+            module("storage", &["store"]),
+            module("archive", &["store"]),
+        ]);
+        assert!(shared_alias.unwrap_err().to_string().contains("ambiguous module reference 'store'"));
+        
+        let alias_is_other_id = ModuleRegistry::new(vec![
+            module("storage", &[]),
+            module("archive", &["storage"]),
+        ]);
+        assert!(alias_is_other_id.is_err());
+        
+        assert!(ModuleRegistry::new(vec![module("storage", &[]), module("storage", &[])]).is_err());
+    }
+    
+    #[test]
+    fn test_rekey_by_canonical_id() {
+        let registry = ModuleRegistry::new(vec![module("storage", &["car-storage"]), module("pricing", &[])]).unwrap();
+        let entries = |names: &[&str]| names.iter().map(|n| (n.to_string(), n.len())).collect::<HashMap<_, _>>();
+        
+        let resolved = registry.rekey(entries(&["car-storage", "pricing"]), "Limits policy: workers").unwrap();
+        assert_eq!(resolved.get("storage"), Some(&"car-storage".len()));
+        assert_eq!(resolved.get("pricing"), Some(&"pricing".len()));
+        
+        let unknown = registry.rekey(entries(&["ghost"]), "Limits policy: workers").unwrap_err().to_string();
+        assert!(unknown.starts_with("Limits policy: workers: Unknown module 'ghost'"));
+        
+        let twice = registry.rekey(entries(&["storage", "car-storage"]), "Limits policy").unwrap_err().to_string();
+        assert!(twice.contains("both name module 'storage'"));
+    }
+    
+    #[test]
+    fn test_repository_registry_parses() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../system/policy/modules.yaml");
+        let registry = parse_module_registry(&std::fs::read_to_string(path).unwrap()).unwrap();
+        
+        assert_eq!(registry.resolve("car-storage").unwrap(), "storage");
+        assert_eq!(registry.trust_level("storage"), TrustLevel::Untrusted);
+        assert_eq!(registry.module_dir("storage"), Path::new(MODULES_DIR).join("storage"));
+    }
+}
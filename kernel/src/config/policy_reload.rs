@@ -0,0 +1,222 @@
+// Policy Reload
+// Detects policy changes and swaps in a new set only if the whole set validates
+//
+// Triggers:
+//   - file changes in system/policy/ or the token files it names (polled by PolicyWatcher)
+//   - a reload flag, e.g. set from a SIGHUP handler via `Kernel::reload_flag()`
+//   - an explicit `Kernel::reload_policy()` call
+
+use super::policy_set::{PolicySet, PolicySources};
+use crate::authz::tokens;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Result of a successful reload attempt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReloadOutcome {
+    /// Sources hash to the active set; nothing swapped
+    Unchanged,
+    Reloaded { old_hash: String, new_hash: String },
+}
+
+/// Validates `sources` as a complete set and swaps it into `active`
+/// On error `active` is left untouched (last-known-good)
+pub fn swap_if_valid(active: &mut PolicySet, sources: &PolicySources) -> Result<ReloadOutcome, Box<dyn Error>> {
+    let new_hash = sources.hash();
+    if new_hash == active.hash {
+        return Ok(ReloadOutcome::Unchanged);
+    }
+    
+    let candidate = PolicySet::from_sources(sources)?;
+    let old = std::mem::replace(active, candidate);
+    
+    Ok(ReloadOutcome::Reloaded { old_hash: old.hash, new_hash })
+}
+
+/// (file name, modified time, length) for every `*.yaml` file, tenant overlays as `tenants/<id>/<file>`,
+/// token files under their snapshot keys
+type Fingerprint = Vec<(String, SystemTime, u64)>;
+
+/// Polls the policy directory for changes, at most once per interval
+pub struct PolicyWatcher {
+    dir: PathBuf,
+    interval: Duration,
+    last_check: Instant,
+    fingerprint: Fingerprint,
+}
+
+impl PolicyWatcher {
+    pub fn new(dir: impl Into<PathBuf>, interval: Duration) -> Self {
+        let dir = dir.into();
+        let fingerprint = fingerprint(&dir).unwrap_or_default();
+        
+        PolicyWatcher {
+            dir,
+            interval,
+            last_check: Instant::now(),
+            fingerprint,
+        }
+    }
+    
+    /// True if any policy file was added, removed or modified since the last poll
+    /// Returns false without touching the filesystem until the interval has passed
+    pub fn poll(&mut self) -> bool {
+        if self.last_check.elapsed() < self.interval {
+            return false;
+        }
+        self.last_check = Instant::now();
+        
+        match fingerprint(&self.dir) {
+            Ok(current) if current != self.fingerprint => {
+                self.fingerprint = current;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn fingerprint(dir: &Path) -> Result<Fingerprint, Box<dyn Error>> {
+    let mut files = Vec::new();
+    fingerprint_yaml_files(dir, "", &mut files)?;
+    
+    // Tenant overlays, read the same way as PolicySources::read_dir
+    let tenants_dir = dir.join("tenants");
+    if tenants_dir.is_dir() {
+        for entry in fs::read_dir(&tenants_dir)? {
+            let path = entry?.path();
+            if !path.is_dir() {
+                continue;
+            }
+            
+            let tenant_id = path.file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or_default()
+                .to_string();
+            fingerprint_yaml_files(&path, &format!("tenants/{}/", tenant_id), &mut files)?;
+        }
+    }
+    
+    // Keyring and revocation list, wherever actor_tokens.yaml points
+    let token_files = fs::read_to_string(dir.join("actor_tokens.yaml")).ok()
+        .and_then(|content| tokens::token_files(&content).ok());
+    for (key, path) in token_files.into_iter().flatten() {
+        if let Ok(metadata) = fs::metadata(&path) {
+            files.push((key.to_string(), metadata.modified()?, metadata.len()));
+        }
+    }
+    
+    files.sort();
+    Ok(files)
+}
+
+fn fingerprint_yaml_files(dir: &Path, prefix: &str, files: &mut Fingerprint) -> Result<(), Box<dyn Error>> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("yaml") {
+            continue;
+        }
+        
+        let metadata = fs::metadata(&path)?;
+        let name = path.file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        files.push((format!("{}{}", prefix, name), metadata.modified()?, metadata.len()));
+    }
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_watcher_detects_changes() {
+        let dir = std::env::temp_dir().join(format!("policy-watch-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("access.yaml"), "a").unwrap();
+        
+        let mut watcher = PolicyWatcher::new(&dir, Duration::ZERO);
+        assert!(!watcher.poll());
+        
+        fs::write(dir.join("access.yaml"), "ab").unwrap();
+        assert!(watcher.poll());
+        assert!(!watcher.poll());
+        
+        // Non-policy files are ignored
+        fs::write(dir.join("notes.txt"), "x").unwrap();
+        assert!(!watcher.poll());
+        
+        // Tenant overlays are watched too
+        let tenant_dir = dir.join("tenants/dealer-a");
+        fs::create_dir_all(&tenant_dir).unwrap();
+        fs::write(tenant_dir.join("limits.yaml"), "a").unwrap();
+        assert!(watcher.poll());
+        assert!(!watcher.poll());
+        
+        fs::write(tenant_dir.join("limits.yaml"), "ab").unwrap();
+        assert!(watcher.poll());
+        
+        fs::remove_file(tenant_dir.join("limits.yaml")).unwrap();
+        assert!(watcher.poll());
+        
+        // Token files outside the directory are watched through actor_tokens.yaml
+        let revoked = std::env::temp_dir().join(format!("policy-watch-revoked-{}.yaml", uuid::Uuid::new_v4()));
+        fs::write(&revoked, "revoked_token_ids: []\n").unwrap();
+        fs::write(dir.join("actor_tokens.yaml"), format!(
+            "audience: cabinet-kernel\nkeyring_path: /nonexistent/keyring.yaml\nrevocation_list_path: {}\nmax_token_lifetime_seconds: 3600\n",
+            revoked.display()
+        )).unwrap();
+        assert!(watcher.poll());
+        assert!(!watcher.poll());
+        
+        fs::write(&revoked, "revoked_token_ids: [leaked]\n").unwrap();
+        assert!(watcher.poll());
+        
+        fs::remove_file(&revoked).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    /// Repository policy with an empty token keyring and revocation list
+    fn repo_sources() -> PolicySources {
+        let policy_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../system/policy");
+        let mut sources = PolicySources::read_dir(&policy_dir).unwrap();
+        sources.insert(tokens::KEYRING_SOURCE, "keys: []\n");
+        sources.insert(tokens::REVOCATION_LIST_SOURCE, "revoked_token_ids: []\n");
+        sources
+    }
+    
+    #[test]
+    fn test_invalid_set_keeps_last_known_good() {
+        let sources = repo_sources();
+        let mut active = PolicySet::from_sources(&sources).unwrap();
+        let original = active.hash.clone();
+        assert_eq!(swap_if_valid(&mut active, &sources).unwrap(), ReloadOutcome::Unchanged);
+        
+        // A broken routing file rejects the whole set
+        let mut broken = sources.clone();
+        broken.insert("routing.yaml", "version: v1.0.0\npolicy: allow_all\nroutes: []\n");
+        assert!(swap_if_valid(&mut active, &broken).is_err());
+        assert_eq!(active.hash, original);
+        
+        // A valid edit is swapped in and reports both hashes
+        let mut edited = sources.clone();
+        edited.insert("grants.yaml", "version: v1.0.0\ngrants: []\n# edited\n");
+        match swap_if_valid(&mut active, &edited).unwrap() {
+            ReloadOutcome::Reloaded { old_hash, new_hash } => {
+                assert_eq!(old_hash, original);
+                assert_eq!(new_hash, edited.hash());
+            }
+            other => panic!("expected reload, got {:?}", other),
+        }
+        assert_eq!(active.hash, edited.hash());
+        
+        // Revoking a token is a change even though no policy file was touched
+        let mut revoked = edited.clone();
+        revoked.insert(tokens::REVOCATION_LIST_SOURCE, "revoked_token_ids: [leaked]\n");
+        assert!(matches!(swap_if_valid(&mut active, &revoked).unwrap(), ReloadOutcome::Reloaded { .. }));
+    }
+}
@@ -0,0 +1,430 @@
+// Policy Set
+// The complete policy the kernel enforces, validated as one unit
+//
+// All files are read into a snapshot first and parsed from that snapshot, so a
+// set is never assembled from files edited half-way through loading. The keyring and
+// revocation list named by actor_tokens.yaml live elsewhere on disk but are part of the
+// snapshot (and its hash) too, so revoking a token is a policy change like any other.
+//
+// Tenants: system/policy/tenants/<tenant_id>/*.yaml overlay the shared base files.
+// Mappings merge recursively (tenant wins), lists of entries with an `id` merge by id,
+// other values are replaced. Actor token policy and the module registry are shared and
+// cannot be overridden. Installed module manifests are checked against the registry as part
+// of loading, so a manifest naming the wrong module fails the set rather than its requests.
+
+use crate::authz::{capabilities, grants, roles, tokens};
+use crate::primitives::{hash, ids};
+use crate::result_gate::redaction;
+use crate::config::module_registry::{self, ModuleRegistry};
+use crate::routing::graph::RoutingGraph;
+use crate::routing::resolve_endpoint;
+use crate::sandbox::limits;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+pub const POLICY_DIR: &str = "/home/runner/work/cabinet/cabinet/system/policy";
+
+/// Policy files that must be present for a set to be valid
+pub const REQUIRED_FILES: [&str; 7] = [
+    "access.yaml",
+    "actor_tokens.yaml",
+    "grants.yaml",
+    "limits.yaml",
+    "modules.yaml",
+    "result_profiles.yaml",
+    "routing.yaml",
+];
+
+/// Base files a tenant may overlay
+pub const TENANT_FILES: [&str; 5] = [
+    "access.yaml",
+    "grants.yaml",
+    "limits.yaml",
+    "result_profiles.yaml",
+    "routing.yaml",
+];
+
+/// Raw contents of every `*.yaml` policy file, by file name
+/// Tenant overlays are keyed as `tenants/<tenant_id>/<file>`, the token keyring and
+/// revocation list as `tokens::KEYRING_SOURCE` and `tokens::REVOCATION_LIST_SOURCE`
+#[derive(Debug, Clone, Default)]
+pub struct PolicySources {
+    files: BTreeMap<String, String>,
+}
+
+impl PolicySources {
+    /// Reads every `*.yaml` file directly under `dir` and under `dir/tenants/<tenant_id>/`,
+    /// plus the token files actor_tokens.yaml names
+    /// Missing or unparseable files are left out here and reported by `PolicySet::from_sources`
+    pub fn read_dir(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut files = BTreeMap::new();
+        read_yaml_files(dir, "", &mut files)?;
+        
+        let tenants_dir = dir.join("tenants");
+        if tenants_dir.is_dir() {
+            let entries = fs::read_dir(&tenants_dir)
+                .map_err(|e| format!("Failed to read tenant policy directory: {}", e))?;
+            
+            for entry in entries {
+                let path = entry?.path();
+                if !path.is_dir() {
+                    continue;
+                }
+                
+                let tenant_id = path.file_name()
+                    .and_then(|n| n.to_str())
+                    .ok_or("Invalid tenant directory name")?
+                    .to_string();
+                ids::validate_tenant_id(&tenant_id)
+                    .map_err(|e| format!("Tenant directory '{}': {}", tenant_id, e))?;
+                
+                read_yaml_files(&path, &format!("tenants/{}/", tenant_id), &mut files)?;
+            }
+        }
+        
+        let token_files = files.get("actor_tokens.yaml")
+            .and_then(|content| tokens::token_files(content).ok());
+        for (key, path) in token_files.into_iter().flatten() {
+            match fs::read_to_string(&path) {
+                Ok(content) => {
+                    files.insert(key.to_string(), content);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e).into()),
+            }
+        }
+        
+        Ok(PolicySources { files })
+    }
+    
+    pub fn insert(&mut self, name: &str, content: &str) {
+        self.files.insert(name.to_string(), content.to_string());
+    }
+    
+    pub fn get(&self, name: &str) -> Result<&str, Box<dyn Error>> {
+        self.files.get(name)
+            .map(|c| c.as_str())
+            .ok_or_else(|| format!("Missing policy file: {}", name).into())
+    }
+    
+    /// Tenant IDs that have an overlay directory, sorted
+    pub fn tenant_ids(&self) -> Vec<String> {
+        let mut tenants: Vec<String> = self.files.keys()
+            .filter_map(|name| name.strip_prefix("tenants/"))
+            .filter_map(|rest| rest.split('/').next())
+            .map(|id| id.to_string())
+            .collect();
+        tenants.dedup();
+        tenants
+    }
+    
+    /// Content of a base file with the tenant's overlay merged in (if it has one)
+    pub fn merged(&self, tenant_id: &str, name: &str) -> Result<String, Box<dyn Error>> {
+        let base = self.get(name)?;
+        let overlay = match self.files.get(&format!("tenants/{}/{}", tenant_id, name)) {
+            Some(overlay) => overlay,
+            None => return Ok(base.to_string()),
+        };
+        
+        let mut merged: serde_yaml::Value = serde_yaml::from_str(base)
+            .map_err(|e| format!("Failed to parse {}: {}", name, e))?;
+        let overlay: serde_yaml::Value = serde_yaml::from_str(overlay)
+            .map_err(|e| format!("Tenant '{}': failed to parse {}: {}", tenant_id, name, e))?;
+        merge_yaml(&mut merged, overlay);
+        
+        Ok(serde_yaml::to_string(&merged)?)
+    }
+    
+    /// SHA-256 over file names and contents in name order
+    pub fn hash(&self) -> String {
+        let mut data = Vec::new();
+        for (name, content) in &self.files {
+            data.extend_from_slice(name.as_bytes());
+            data.push(0);
+            data.extend_from_slice(content.as_bytes());
+            data.push(0);
+        }
+        hash::hash_bytes(&data)
+    }
+}
+
+/// Policy that can differ per tenant
+pub struct TenantPolicy {
+    pub roles: roles::RoleIndex,
+    pub capability_requirements: HashMap<String, capabilities::CapabilityRequirement>,
+    pub grants: Vec<grants::Grant>,
+    pub routing_graph: RoutingGraph,
+    pub limits_policy: limits::LimitsPolicy,
+    pub result_profiles: redaction::ResultProfilesPolicy,
+}
+
+impl TenantPolicy {
+    /// Parses one policy tree; `read` returns the (merged) content of a file
+    /// Module references in routing and limits are resolved to canonical IDs
+    fn parse<F>(read: F, modules: &ModuleRegistry) -> Result<Self, Box<dyn Error>>
+    where
+        F: Fn(&str) -> Result<String, Box<dyn Error>>,
+    {
+        let access = read("access.yaml")?;
+        let routing_graph = RoutingGraph::parse_resolved(&read("routing.yaml")?, modules)?;
+        
+        let mut limits_policy = limits::parse_limits(&read("limits.yaml")?)?;
+        limits_policy.resolve_modules(modules)?;
+        
+        Ok(TenantPolicy {
+            roles: roles::parse_roles(&access).and_then(roles::RoleIndex::compile)?,
+            capability_requirements: capabilities::parse_capability_requirements(&access)?,
+            grants: grants::parse_grants(&read("grants.yaml")?)?,
+            routing_graph,
+            limits_policy,
+            result_profiles: redaction::parse_result_profiles(&read("result_profiles.yaml")?)?,
+        })
+    }
+}
+
+/// Every policy the request pipeline reads, compiled and validated together
+pub struct PolicySet {
+    pub token_verifier: tokens::TokenVerifier,
+    /// Canonical module IDs and aliases, shared by every tenant
+    pub modules: ModuleRegistry,
+    /// Shared base policy, used for requests without a tenant
+    pub base: TenantPolicy,
+    /// Base merged with each tenant's overlay
+    pub tenants: HashMap<String, TenantPolicy>,
+    /// Hash of the sources this set was built from
+    pub hash: String,
+}
+
+impl PolicySet {
+    /// Loads and validates the policy set from system/policy/
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let sources = PolicySources::read_dir(Path::new(POLICY_DIR))?;
+        PolicySet::from_sources(&sources)
+    }
+    
+    /// Builds a set from a snapshot; fails if any file is missing or any tree is invalid
+    pub fn from_sources(sources: &PolicySources) -> Result<Self, Box<dyn Error>> {
+        for name in REQUIRED_FILES {
+            sources.get(name)?;
+        }
+        
+        // Tenants may only overlay per-tenant files; token policy stays shared
+        for name in sources.files.keys().filter_map(|name| name.strip_prefix("tenants/")) {
+            let file = name.split('/').nth(1).unwrap_or_default();
+            if !TENANT_FILES.contains(&file) {
+                return Err(format!("Tenant policy file not allowed: tenants/{}", name).into());
+            }
+        }
+        
+        let modules = module_registry::parse_module_registry(sources.get("modules.yaml")?)?;
+        resolve_endpoint::check_manifests(&modules)?;
+        
+        let mut tenants = HashMap::new();
+        for tenant_id in sources.tenant_ids() {
+            let policy = TenantPolicy::parse(|name| sources.merged(&tenant_id, name), &modules)
+                .map_err(|e| format!("Tenant '{}': {}", tenant_id, e))?;
+            tenants.insert(tenant_id, policy);
+        }
+        
+        Ok(PolicySet {
+            token_verifier: tokens::parse_token_verifier(
+                sources.get("actor_tokens.yaml")?,
+                sources.get(tokens::KEYRING_SOURCE)?,
+                sources.get(tokens::REVOCATION_LIST_SOURCE)?,
+            )?,
+            base: TenantPolicy::parse(|name| sources.get(name).map(|c| c.to_string()), &modules)?,
+            modules,
+            tenants,
+            hash: sources.hash(),
+        })
+    }
+    
+    /// Policy for the tenant, or the base policy when there is no tenant
+    /// Unknown tenants are rejected rather than falling back to the base
+    pub fn tenant(&self, tenant_id: Option<&str>) -> Result<&TenantPolicy, Box<dyn Error>> {
+        match tenant_id {
+            None => Ok(&self.base),
+            Some(id) => self.tenants.get(id)
+                .ok_or_else(|| format!("PERMISSION_DENIED: Unknown tenant '{}'", id).into()),
+        }
+    }
+}
+
+fn read_yaml_files(dir: &Path, prefix: &str, files: &mut BTreeMap<String, String>) -> Result<(), Box<dyn Error>> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read policy directory: {}", e))?;
+    
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("yaml") {
+            continue;
+        }
+        
+        let name = path.file_name()
+            .and_then(|n| n.to_str())
+            .ok_or("Invalid policy file name")?
+            .to_string();
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read policy file {}{}: {}", prefix, name, e))?;
+        files.insert(format!("{}{}", prefix, name), content);
+    }
+    
+    Ok(())
+}
+
+/// Merges `overlay` into `base`: mappings recursively, `id`-keyed lists by id, anything else replaced
+fn merge_yaml(base: &mut serde_yaml::Value, overlay: serde_yaml::Value) {
+    use serde_yaml::Value;
+    
+    match (base, overlay) {
+        (Value::Mapping(base_map), Value::Mapping(overlay_map)) => {
+            for (key, value) in overlay_map {
+                match base_map.get_mut(&key) {
+                    Some(existing) => merge_yaml(existing, value),
+                    None => {
+                        base_map.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Sequence(base_seq), Value::Sequence(overlay_seq))
+            if base_seq.iter().chain(overlay_seq.iter()).all(|v| v.get("id").is_some()) =>
+        {
+            for item in overlay_seq {
+                match base_seq.iter_mut().find(|b| b.get("id") == item.get("id")) {
+                    Some(existing) => *existing = item,
+                    None => base_seq.push(item),
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_sources_hash_is_order_independent_and_content_sensitive() {
+        let mut a = PolicySources::default();
+        a.insert("routing.yaml", "x");
+        a.insert("access.yaml", "y");
+        
+        let mut b = PolicySources::default();
+        b.insert("access.yaml", "y");
+        b.insert("routing.yaml", "x");
+        assert_eq!(a.hash(), b.hash());
+        
+        b.insert("routing.yaml", "x2");
+        assert_ne!(a.hash(), b.hash());
+    }
+    
+    #[test]
+    fn test_missing_file_fails_whole_set() {
+        let mut sources = PolicySources::default();
+        sources.insert("access.yaml", "version: v1.0.0\npolicy: deny_by_default\nroles: {}\n");
+        
+        let err = PolicySet::from_sources(&sources).err().unwrap();
+        assert!(err.to_string().contains("Missing policy file: actor_tokens.yaml"));
+    }
+    
+    #[test]
+    fn test_tenant_overlay_merges_into_base() {
+        let mut sources = PolicySources::default();
+        sources.insert("routing.yaml", r#"
+policy: deny_by_default
+routes:
+  - id: shared
+    enabled: true
+  - id: legacy
+    enabled: true
+restrictions:
+  max_chain_depth: 10
+  blocked_capabilities: ["system.shutdown"]
+"#);
+        sources.insert("tenants/dealer-a/routing.yaml", r#"
+routes:
+  - id: legacy
+    enabled: false
+  - id: dealer-only
+    enabled: true
+restrictions:
+  blocked_capabilities: ["import.run"]
+"#);
+        
+        assert_eq!(sources.tenant_ids(), vec!["dealer-a"]);
+        
+        let merged: serde_yaml::Value = serde_yaml::from_str(&sources.merged("dealer-a", "routing.yaml").unwrap()).unwrap();
+        let routes = merged["routes"].as_sequence().unwrap();
+        let ids: Vec<&str> = routes.iter().map(|r| r["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, vec!["shared", "legacy", "dealer-only"]);
+        assert_eq!(routes[1]["enabled"], serde_yaml::Value::Bool(false));
+        assert_eq!(merged["policy"].as_str(), Some("deny_by_default"));
+        assert_eq!(merged["restrictions"]["max_chain_depth"].as_u64(), Some(10));
+        assert_eq!(merged["restrictions"]["blocked_capabilities"][0].as_str(), Some("import.run"));
+        
+        // Tenants without an overlay file get the base content unchanged
+        assert_eq!(sources.merged("dealer-b", "routing.yaml").unwrap(), sources.get("routing.yaml").unwrap());
+    }
+    
+    #[test]
+    fn test_module_references_resolve_to_canonical_ids() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../system/policy");
+        let sources = PolicySources::read_dir(&dir).unwrap();
+        let modules = module_registry::parse_module_registry(sources.get("modules.yaml").unwrap()).unwrap();
+        let parse = |sources: &PolicySources| TenantPolicy::parse(|name| sources.get(name).map(|c| c.to_string()), &modules);
+        
+        // routing.yaml and limits.yaml say `storage-module`; both resolve to `storage`
+        let policy = parse(&sources).unwrap();
+        assert!(!policy.routing_graph.find_routes("ui", "main_ui", "module", "storage", "storage.listings.list").is_empty());
+        assert_eq!(limits::get_module_limits("storage", &policy.limits_policy).timeout_ms, 60000);
+        assert!(policy.limits_policy.circuit_breaker.thresholds_for("automation").per_capability);
+        
+        let mut unknown = sources.clone();
+        unknown.insert("routing.yaml", &sources.get("routing.yaml").unwrap().replace("id: storage-module", "id: ghost-module"));
+        assert!(parse(&unknown).err().unwrap().to_string().contains("Unknown module 'ghost-module'"));
+        
+        let mut duplicate = sources.clone();
+        duplicate.insert("limits.yaml", &sources.get("limits.yaml").unwrap().replace("  pricing-module:", "  car-storage:"));
+        assert!(parse(&duplicate).err().unwrap().to_string().contains("both name module 'storage'"));
+    }
+    
+    #[test]
+    fn test_tenant_cannot_override_token_policy() {
+        let mut sources = PolicySources::default();
+        for name in REQUIRED_FILES {
+            sources.insert(name, "");
+        }
+        sources.insert("tenants/dealer-a/actor_tokens.yaml", "audience: other\n");
+        
+        let err = PolicySet::from_sources(&sources).err().unwrap();
+        assert!(err.to_string().contains("not allowed: tenants/dealer-a/actor_tokens.yaml"));
+    }
+    
+    #[test]
+    fn test_token_files_are_part_of_the_snapshot() {
+        let dir = std::env::temp_dir().join(format!("policy-token-files-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let revoked = dir.join("revoked.yaml");
+        fs::write(dir.join("actor_tokens.yaml"), format!(
+            "audience: cabinet-kernel\nkeyring_path: {}\nrevocation_list_path: {}\nmax_token_lifetime_seconds: 3600\n",
+            dir.join("keyring.yaml").display(),
+            revoked.display()
+        )).unwrap();
+        fs::write(&revoked, "revoked_token_ids: []\n").unwrap();
+        
+        // A missing keyring is left for from_sources to report
+        let sources = PolicySources::read_dir(&dir).unwrap();
+        assert!(sources.get(tokens::KEYRING_SOURCE).is_err());
+        assert_eq!(sources.get(tokens::REVOCATION_LIST_SOURCE).unwrap(), "revoked_token_ids: []\n");
+        
+        // Revoking a token changes the snapshot hash
+        fs::write(&revoked, "revoked_token_ids: [leaked]\n").unwrap();
+        assert_ne!(PolicySources::read_dir(&dir).unwrap().hash(), sources.hash());
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -62,16 +62,73 @@ fn canonicalize_json(value: &Value) -> String {
     }
 }
 
+/// Most retries error.schema.yaml lets an envelope recommend
+pub const MAX_ADVISED_RETRIES: u32 = 10;
+
+/// `retry` object of an error envelope: whether the client may send the same command again
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetryAdvice {
+    pub retryable: bool,
+    pub retry_after_seconds: Option<u64>,
+    pub max_retries: Option<u32>,
+}
+
+impl RetryAdvice {
+    /// The same command would fail the same way (or could apply twice)
+    pub fn never() -> Self {
+        RetryAdvice::default()
+    }
+    
+    /// Retryable, after `retry_after` (rounded up to whole seconds)
+    pub fn after(retry_after: std::time::Duration) -> Self {
+        RetryAdvice {
+            retryable: true,
+            retry_after_seconds: Some(retry_after.as_millis().div_ceil(1000) as u64),
+            max_retries: None,
+        }
+    }
+    
+    /// Retryable, at most `max_retries` times (capped at MAX_ADVISED_RETRIES)
+    pub fn up_to(max_retries: u32) -> Self {
+        RetryAdvice {
+            retryable: true,
+            retry_after_seconds: None,
+            max_retries: Some(max_retries.min(MAX_ADVISED_RETRIES)),
+        }
+    }
+    
+    fn to_json(self) -> Value {
+        let mut retry = json!({ "retryable": self.retryable });
+        if let Some(seconds) = self.retry_after_seconds {
+            retry["retry_after_seconds"] = json!(seconds);
+        }
+        if let Some(max_retries) = self.max_retries {
+            retry["max_retries"] = json!(max_retries);
+        }
+        retry
+    }
+}
+
 /// Creates a result envelope
+/// `capability_version` is the version that actually ran, reported as metadata.capability_version;
+/// `attempts` counts kernel-side tries, the first one included
 pub fn encode_result(
     correlation_id: &str,
     data: Value,
     execution_time_ms: Option<u64>,
+    capability_version: Option<&str>,
+    attempts: Option<u32>,
 ) -> Value {
     let mut metadata = BTreeMap::new();
     if let Some(time) = execution_time_ms {
         metadata.insert("execution_time_ms".to_string(), json!(time));
     }
+    if let Some(version) = capability_version {
+        metadata.insert("capability_version".to_string(), json!(version));
+    }
+    if let Some(attempts) = attempts {
+        metadata.insert("attempts".to_string(), json!(attempts));
+    }
     metadata.insert("cached".to_string(), json!(false));
     
     json!({
@@ -89,11 +146,14 @@ pub fn encode_result(
 }
 
 /// Creates an error envelope
+/// `details` follows the `details` object of error.schema.yaml
 pub fn encode_error(
     correlation_id: Option<&str>,
     error_code: &str,
     message: &str,
     severity: &str,
+    details: Option<Value>,
+    retry: RetryAdvice,
 ) -> Value {
     let mut envelope = json!({
         "version": "v1.0.0",
@@ -104,9 +164,7 @@ pub fn encode_error(
             "error_code": error_code,
             "message": message,
             "severity": severity,
-            "retry": {
-                "retryable": false
-            }
+            "retry": retry.to_json()
         }
     });
     
@@ -114,6 +172,10 @@ pub fn encode_error(
         envelope["correlation_id"] = json!(corr_id);
     }
     
+    if let Some(details) = details {
+        envelope["payload"]["details"] = details;
+    }
+    
     envelope
 }
 
@@ -158,4 +220,28 @@ mod tests {
         let canonical = encode_canonical(&value);
         assert!(canonical.starts_with(r#"{"other":"value","outer":{"a":1,"z":3}"#));
     }
+    
+    #[test]
+    fn test_result_reports_capability_version() {
+        let envelope = encode_result("msg-1", json!({}), Some(5), Some("v2.1.0"), None);
+        assert_eq!(envelope["payload"]["metadata"]["capability_version"], "v2.1.0");
+        
+        let envelope = encode_result("msg-1", json!({}), Some(5), None, Some(2));
+        assert!(envelope["payload"]["metadata"].get("capability_version").is_none());
+        assert_eq!(envelope["payload"]["metadata"]["attempts"], 2);
+    }
+    
+    #[test]
+    fn test_error_retry_advice() {
+        let envelope = encode_error(Some("msg-1"), "PERMISSION_DENIED", "denied", "error", None, RetryAdvice::never());
+        assert_eq!(envelope["payload"]["retry"], json!({"retryable": false}));
+        
+        let advice = RetryAdvice::after(std::time::Duration::from_millis(1500));
+        let envelope = encode_error(Some("msg-1"), "MODULE_UNAVAILABLE", "open", "error", None, advice);
+        assert_eq!(envelope["payload"]["retry"], json!({"retryable": true, "retry_after_seconds": 2}));
+        
+        let envelope = encode_error(Some("msg-1"), "TIMEOUT", "slow", "error", None, RetryAdvice::up_to(3));
+        assert_eq!(envelope["payload"]["retry"], json!({"retryable": true, "max_retries": 3}));
+        assert_eq!(RetryAdvice::up_to(50).max_retries, Some(MAX_ADVISED_RETRIES));
+    }
 }
@@ -16,34 +16,229 @@ mod tests;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Main kernel request processing pipeline
 pub struct Kernel {
-    roles: HashMap<String, authz::roles::Role>,
-    capability_requirements: HashMap<String, authz::capabilities::CapabilityRequirement>,
-    routing_graph: routing::graph::RoutingGraph,
-    limits_policy: sandbox::limits::LimitsPolicy,
-    result_profiles: result_gate::redaction::ResultProfilesPolicy,
+    /// Active policy set; replaced whole on reload, only between requests
+    policy: config::policy_set::PolicySet,
+    policy_watcher: config::policy_reload::PolicyWatcher,
+    reload_requested: Arc<AtomicBool>,
     module_statuses: HashMap<String, observed::module_status::ModuleStatus>,
+    /// Instance rotation per canonical module ID, built from each module's manifest
+    module_instances: HashMap<String, sandbox::instances::InstancePool>,
+    /// Circuit breakers per canonical module ID (or `module/capability`)
+    circuit_breakers: HashMap<String, sandbox::circuit_breaker::CircuitBreaker>,
+    /// Warm processes of modules whose manifest allows persistent workers
+    workers: sandbox::workers::WorkerPools,
+    /// Sandbox backend of each kind; each module runs on the one its policy picks
+    backends: sandbox::backend::Backends,
+    /// Canary shadow calls of requests already answered, run by `run_shadows`
+    pending_shadows: Vec<PendingShadow>,
+}
+
+/// A canary shadow call, queued so it never delays the response to its request
+struct PendingShadow {
+    route_id: String,
+    actor_id: String,
+    role: String,
+    capability: String,
+    stable_version: String,
+    shadow_version: String,
+    /// Result the stable version answered with, compared with the shadow's
+    stable_result: Value,
+    spawn_config: sandbox::spawn::SpawnConfig,
+    backend: sandbox::backend::BackendKind,
+    persistent_worker: bool,
+    limits: sandbox::limits::ModuleLimits,
+    tenant_id: Option<String>,
 }
 
 impl Kernel {
     /// Initialize kernel with all policies
     pub fn new() -> Result<Self, Box<dyn Error>> {
+        Kernel::from_policy(config::policy_set::PolicySet::load()?)
+    }
+    
+    /// Kernel over a policy set that is already built, e.g. one assembled in a test
+    /// Reloads still read system/policy/
+    pub fn from_policy(policy: config::policy_set::PolicySet) -> Result<Self, Box<dyn Error>> {
+        let mut backends = sandbox::backend::Backends::new()?;
+        backends.configure(&policy.base.limits_policy);
+        
         Ok(Kernel {
-            roles: authz::roles::load_roles()?,
-            capability_requirements: authz::capabilities::load_capability_requirements()?,
-            routing_graph: routing::graph::RoutingGraph::load()?,
-            limits_policy: sandbox::limits::load_limits()?,
-            result_profiles: result_gate::redaction::load_result_profiles()?,
+            policy,
+            policy_watcher: config::policy_reload::PolicyWatcher::new(
+                config::policy_set::POLICY_DIR,
+                Duration::from_secs(1),
+            ),
+            reload_requested: Arc::new(AtomicBool::new(false)),
             module_statuses: HashMap::new(),
+            module_instances: HashMap::new(),
+            circuit_breakers: HashMap::new(),
+            workers: sandbox::workers::WorkerPools::new(),
+            backends,
+            pending_shadows: Vec::new(),
         })
     }
     
+    /// Replaces the sandbox backend of a kind, e.g. with a scripted FakeBackend to test the pipeline
+    pub fn set_backend(&mut self, kind: sandbox::backend::BackendKind, backend: Box<dyn sandbox::backend::SandboxBackend>) {
+        self.backends.set(kind, backend);
+    }
+    
+    /// Flag that requests a policy reload before the next request
+    /// (e.g. `signal_hook::flag::register(SIGHUP, kernel.reload_flag())`)
+    pub fn reload_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.reload_requested)
+    }
+    
+    /// Hash of the active policy set
+    pub fn policy_hash(&self) -> &str {
+        &self.policy.hash
+    }
+    
+    /// Validates the complete policy set on disk and swaps it in
+    /// On failure the active set is kept (last-known-good) and the error returned;
+    /// both outcomes are audited with the old and new policy hashes
+    pub fn reload_policy(&mut self) -> Result<config::policy_reload::ReloadOutcome, Box<dyn Error>> {
+        use config::policy_reload::ReloadOutcome;
+        use config::policy_set::{PolicySources, POLICY_DIR};
+        
+        let old_hash = self.policy.hash.clone();
+        
+        let sources = match PolicySources::read_dir(std::path::Path::new(POLICY_DIR)) {
+            Ok(sources) => sources,
+            Err(e) => {
+                let event = observed::audit_events::audit_policy_reload(&old_hash, None, false, Some(&e.to_string()));
+                let _ = observed::audit_events::record_audit_event(event);
+                return Err(e);
+            }
+        };
+        
+        match config::policy_reload::swap_if_valid(&mut self.policy, &sources) {
+            Ok(ReloadOutcome::Unchanged) => Ok(ReloadOutcome::Unchanged),
+            Ok(ReloadOutcome::Reloaded { old_hash, new_hash }) => {
+                // Warm workers were sandboxed under the old policy
+                self.workers.clear();
+                self.backends.configure(&self.policy.base.limits_policy);
+                let event = observed::audit_events::audit_policy_reload(&old_hash, Some(&new_hash), true, None);
+                let _ = observed::audit_events::record_audit_event(event);
+                
+                Ok(ReloadOutcome::Reloaded { old_hash, new_hash })
+            }
+            Err(e) => {
+                let new_hash = sources.hash();
+                let event = observed::audit_events::audit_policy_reload(
+                    &old_hash,
+                    Some(&new_hash),
+                    false,
+                    Some(&e.to_string()),
+                );
+                let _ = observed::audit_events::record_audit_event(event);
+                
+                Err(format!("POLICY_RELOAD_FAILED: keeping policy {}: {}", old_hash, e).into())
+            }
+        }
+    }
+    
+    /// Reloads if the reload flag is set or a policy file changed
+    /// A failed reload leaves the previous set active, so requests keep being served
+    fn reload_policy_if_needed(&mut self) {
+        let flagged = self.reload_requested.swap(false, Ordering::SeqCst);
+        if flagged || self.policy_watcher.poll() {
+            let _ = self.reload_policy();
+        }
+    }
+    
+    /// Probes the instances of every module whose health-check interval has passed
+    fn probe_instances_if_due(&mut self) {
+        let now = std::time::Instant::now();
+        for (module_id, pool) in self.module_instances.iter_mut() {
+            if pool.probe_due(now) {
+                pool.probe(&sandbox::instances::ConnectProbe, now);
+                observed::module_status::record_instances(module_id, pool.status(), &mut self.module_statuses);
+            }
+        }
+    }
+    
+    /// Runs the canary shadow calls queued by answered requests and audits how each compared
+    /// Transports call this once a response is written; otherwise the next request runs them
+    pub fn run_shadows(&mut self) {
+        for shadow in std::mem::take(&mut self.pending_shadows) {
+            let output = self.invoke_module(
+                shadow.spawn_config,
+                shadow.backend,
+                shadow.persistent_worker,
+                &shadow.limits,
+                shadow.tenant_id.as_deref(),
+            );
+            let shadow_result = output.map_err(|e| e.to_string()).and_then(|output| {
+                serde_json::from_str::<Value>(&output).map_err(|e| format!("Invalid result: {}", e))
+            });
+            
+            let differences = shadow_result.as_ref()
+                .map(|shadow_result| routing::canary::differences(&shadow.stable_result, shadow_result, 20));
+            let event = observed::audit_events::audit_canary_shadow(
+                &shadow.actor_id,
+                &shadow.role,
+                &shadow.capability,
+                &shadow.route_id,
+                &shadow.stable_version,
+                &shadow.shadow_version,
+                differences.as_deref().map_err(|e| e.as_str()),
+            );
+            let _ = observed::audit_events::record_audit_event(event);
+        }
+    }
+    
+    /// Runs a module once: on a warm worker if its manifest asks for one and its backend runs
+    /// processes, else on the backend itself; records the resources it used
+    fn invoke_module(
+        &mut self,
+        spawn_config: sandbox::spawn::SpawnConfig,
+        backend: sandbox::backend::BackendKind,
+        persistent_worker: bool,
+        limits: &sandbox::limits::ModuleLimits,
+        tenant_id: Option<&str>,
+    ) -> Result<String, Box<dyn Error>> {
+        let module_id = spawn_config.module_id.clone();
+        if persistent_worker && backend.is_process() {
+            // Workers are always jailed
+            let settings = self.policy.base.limits_policy.workers.settings_for(&module_id);
+            let (output, usage) = self.workers.invoke(spawn_config, settings, tenant_id);
+            let worker_backend = sandbox::backend::BackendKind::JailedProcess;
+            observed::module_status::record_usage(&module_id, worker_backend.as_str(), usage, &mut self.module_statuses);
+            output
+        } else {
+            let (output, usage) = self.backends.invoke(backend, &spawn_config, limits);
+            observed::module_status::record_usage(&module_id, backend.as_str(), usage, &mut self.module_statuses);
+            output
+        }
+    }
+    
     /// Process a request through the full pipeline
     pub fn process_request(&mut self, input: &str) -> Result<String, Box<dyn Error>> {
+        self.process_request_from(input, authz::authorize::CallerAttributes::default())
+    }
+    
+    /// Process a request with caller attributes established by the transport
+    /// (e.g. the peer uid of a local socket), used by route conditions
+    pub fn process_request_from(
+        &mut self,
+        input: &str,
+        caller: authz::authorize::CallerAttributes,
+    ) -> Result<String, Box<dyn Error>> {
         let start_time = std::time::Instant::now();
         
+        // Shadow calls left by the previous request run under the policy they were prepared with;
+        // policy changes are picked up between requests, never in the middle of one
+        self.run_shadows();
+        self.reload_policy_if_needed();
+        self.probe_instances_if_due();
+        
         // 1. IPC Decode
         let envelope = ipc::decode::decode_message(input)?;
         ipc::decode::validate_basic_structure(&envelope)?;
@@ -76,56 +271,110 @@ impl Kernel {
         
         ipc::validate::validate_command(command)?;
         
-        // 3. AuthZ - Extract context
-        let auth_context = authz::authorize::extract_auth_context(command)
-            .map_err(|e| format!("AUTH_CONTEXT_ERROR: {}", e))?;
+        // 3. AuthN - Verify actor token; identity is never taken from context.actor
+        let mut auth_context = match authz::authorize::authenticate(
+            command,
+            &self.policy.token_verifier,
+            |tenant_id| self.policy.tenant(tenant_id).map(|p| &p.roles),
+            chrono::Utc::now(),
+        ) {
+            Ok(context) => context,
+            Err(e) => {
+                let reason = e.to_string();
+                let event = observed::audit_events::audit_authentication(None, false, Some(&reason));
+                let _ = observed::audit_events::record_audit_event(event);
+                
+                return self.encode_error(
+                    Some(message_id),
+                    "UNAUTHENTICATED",
+                    reason.trim_start_matches("UNAUTHENTICATED: "),
+                    "error",
+                );
+            }
+        };
+        
+        let event = observed::audit_events::audit_authentication(Some(&auth_context.actor_id), true, None);
+        let _ = observed::audit_events::record_audit_event(event);
+        auth_context.caller = caller;
+        
+        // Everything past authentication is evaluated against the actor's tenant policy
+        let policy = self.policy.tenant(auth_context.tenant_id.as_deref())?;
         
         let capability = command.get("target")
             .and_then(|t| t.get("capability"))
             .and_then(|c| c.as_str())
             .ok_or("Missing target.capability")?;
         
+        // Actors the policy allows authz.explain may ask for the decision trace on denials
+        let explain = explain_requested(command, &auth_context, policy);
+        
         // 4. AuthZ - Authorize capability
-        match authz::authorize::authorize(
+        let authz_decision = authz::authorize::authorize_with_grants(
             &auth_context,
             capability,
-            &self.roles,
-            &self.capability_requirements,
-        ) {
-            Ok(_) => {
-                // Record successful authorization
-                let event = observed::audit_events::audit_authz(
+            &policy.roles,
+            &policy.capability_requirements,
+            &policy.grants,
+        );
+        
+        if authz_decision.is_allowed() {
+            // Record successful authorization
+            let event = observed::audit_events::audit_authz(
+                &auth_context.actor_id,
+                &auth_context.role,
+                capability,
+                true,
+                None,
+            );
+            let _ = observed::audit_events::record_audit_event(event);
+            
+            // Every use of a break-glass grant is audited with its ID
+            if let Some(grant) = authz_decision.grant_id.as_ref()
+                .and_then(|id| policy.grants.iter().find(|g| &g.id == id))
+            {
+                let event = observed::audit_events::audit_grant_use(
                     &auth_context.actor_id,
                     &auth_context.role,
                     capability,
-                    true,
-                    None,
+                    &grant.id,
+                    &grant.reason,
                 );
                 let _ = observed::audit_events::record_audit_event(event);
             }
-            Err(e) => {
-                // Record denied authorization
-                let event = observed::audit_events::audit_authz(
-                    &auth_context.actor_id,
-                    &auth_context.role,
-                    capability,
-                    false,
-                    Some(&e.to_string()),
-                );
-                let _ = observed::audit_events::record_audit_event(event);
-                
+        } else {
+            // Record denied authorization
+            let event = observed::audit_events::audit_authz(
+                &auth_context.actor_id,
+                &auth_context.role,
+                capability,
+                false,
+                Some(authz_decision.reason()),
+            );
+            let _ = observed::audit_events::record_audit_event(event);
+            
+            return self.encode_denial(message_id, "PERMISSION_DENIED", &authz_decision, explain);
+        }
+        
+        // 5. Routing - Resolve endpoint and capability version
+        // (module_id is canonical from here on: routing, limits, status)
+        let requested_version = command.get("target")
+            .and_then(|t| t.get("version"))
+            .and_then(|v| v.as_str());
+        let resolved = match routing::resolve_endpoint::resolve_endpoint(capability, requested_version, &self.policy.modules) {
+            Ok(resolved) => resolved,
+            Err(e) if e.to_string().starts_with("VERSION_NOT_FOUND: ") => {
                 return self.encode_error(
                     Some(message_id),
-                    "PERMISSION_DENIED",
-                    &e.to_string(),
+                    "VERSION_NOT_FOUND",
+                    e.to_string().trim_start_matches("VERSION_NOT_FOUND: "),
                     "error",
                 );
             }
-        }
-        
-        // 5. Routing - Resolve endpoint
-        let (module_id, endpoint) = routing::resolve_endpoint::resolve_endpoint(capability)
-            .map_err(|e| format!("ROUTING_ERROR: {}", e))?;
+            Err(e) => return Err(format!("ROUTING_ERROR: {}", e).into()),
+        };
+        let routing::resolve_endpoint::ResolvedEndpoint {
+            module_id, endpoints, mut version, versions, persistent_worker, wasm, ..
+        } = resolved;
         
         // 6. Routing - Authorize route
         // Assuming UI -> module route for simplicity
@@ -133,8 +382,8 @@ impl Kernel {
         let from_id = "main_ui";
         let to_type = "module";
         
-        match routing::authorize_route::authorize_route(
-            &self.routing_graph,
+        let route_decision = routing::authorize_route::authorize_route(
+            &policy.routing_graph,
             from_type,
             from_id,
             to_type,
@@ -142,85 +391,270 @@ impl Kernel {
             capability,
             &auth_context,
             None,
-        ) {
-            Ok(_) => {
-                // Record successful routing
-                let event = observed::audit_events::audit_routing(
-                    &auth_context.actor_id,
-                    &auth_context.role,
-                    capability,
-                    from_type,
-                    from_id,
-                    to_type,
-                    &module_id,
-                    true,
-                    None,
-                );
-                let _ = observed::audit_events::record_audit_event(event);
+        );
+        
+        if route_decision.is_allowed() {
+            // Record successful routing
+            let event = observed::audit_events::audit_routing(
+                &auth_context.actor_id,
+                &auth_context.role,
+                capability,
+                from_type,
+                from_id,
+                to_type,
+                &module_id,
+                true,
+                None,
+            );
+            let _ = observed::audit_events::record_audit_event(event);
+        } else {
+            // Record denied routing
+            let event = observed::audit_events::audit_routing(
+                &auth_context.actor_id,
+                &auth_context.role,
+                capability,
+                from_type,
+                from_id,
+                to_type,
+                &module_id,
+                false,
+                Some(route_decision.reason()),
+            );
+            let _ = observed::audit_events::record_audit_event(event);
+            
+            return self.encode_denial(message_id, "ROUTING_DENIED", &route_decision, explain);
+        }
+        
+        // A canary on the route picks the version unless the command pinned one; capabilities
+        // the chosen version lacks (in the manifest already loaded) keep the default.
+        // Shadow copies go only to idempotent commands
+        let mut shadow = None;
+        let canary_route = policy.routing_graph.find_canary(from_type, from_id, to_type, &module_id, capability);
+        if let (Some(route), None) = (canary_route, requested_version) {
+            let canary = route.canary.as_ref().ok_or("Canary route without canary")?;
+            let choice = routing::canary::choose(&route.id, canary, &auth_context.actor_id, &auth_context.role);
+            let exact = |v: &str| routing::capability_versions::parse_version(v)
+                .ok()
+                .filter(|v| versions.contains(v))
+                .map(|v| routing::capability_versions::format_version(&v));
+            
+            if let Some(served) = exact(&choice.serve) {
+                version = served;
             }
-            Err(e) => {
-                // Record denied routing
-                let event = observed::audit_events::audit_routing(
-                    &auth_context.actor_id,
-                    &auth_context.role,
-                    capability,
-                    from_type,
-                    from_id,
-                    to_type,
-                    &module_id,
-                    false,
-                    Some(&e.to_string()),
-                );
-                let _ = observed::audit_events::record_audit_event(event);
-                
-                return self.encode_error(
-                    Some(message_id),
-                    "ROUTING_DENIED",
-                    &e.to_string(),
-                    "error",
-                );
+            if sandbox::retry::is_idempotent(command) {
+                shadow = choice.shadow.as_deref().and_then(exact).map(|v| (route.id.clone(), v));
             }
         }
         
         // 7. Sandbox - Get limits
-        let limits = sandbox::limits::get_module_limits(&module_id, &self.limits_policy);
+        let limits = sandbox::limits::get_module_limits(&module_id, &policy.limits_policy);
+        let limits = sandbox::limits::partition_for_tenant(&limits, auth_context.tenant_id.as_deref());
+        let tenant_state = Some(sandbox::tenant_state::TenantState::prepare(
+            &module_id,
+            auth_context.tenant_id.as_deref(),
+            &limits,
+        )?);
+        
+        // Sandbox backend: named in limits.yaml, else the manifest's runtime.wasm, else by trust level
+        let backend = policy.limits_policy.sandbox
+            .backend_for(&module_id, self.policy.modules.trust_level(&module_id), wasm.is_some());
+        let wasm_endpoint = wasm.filter(|_| backend == sandbox::backend::BackendKind::Wasm)
+            .map(|path| format!("wasm:{}", path.display()));
+        
+        // Syscall filter from the module's seccomp profile, compiled once for every attempt
+        let syscall_filter = policy.limits_policy.seccomp.filter_for(limits.seccomp_profile.as_deref())?;
         
         // 8. Sandbox - Validate input size
         sandbox::limits::check_input_size(input, &limits)?;
         
-        // 9. Sandbox - Spawn module (simulated)
-        let spawn_config = sandbox::spawn::SpawnConfig {
-            module_id: module_id.clone(),
-            endpoint: endpoint.clone(),
-            stdin_data: serde_json::to_string(command)?,
-        };
+        // 9. Sandbox - Retry policy; only idempotent commands are ever sent twice,
+        // and no retry starts past the request deadline
+        let retry_settings = policy.limits_policy.retry.settings_for(capability);
+        let idempotent = sandbox::retry::is_idempotent(command);
+        let max_attempts = if idempotent { retry_settings.max_attempts } else { 1 };
+        let deadline = start_time + sandbox::retry::request_timeout(command, &limits);
+        
+        // The module is told exactly which capability version to run
+        let mut pinned_command = command.clone();
+        pinned_command["target"]["version"] = Value::String(version.clone());
+        let stdin_data = serde_json::to_string(&pinned_command)?;
         
-        let module_output = sandbox::spawn::spawn_module(spawn_config)?;
+        let mut attempts = 0;
+        let (module_output, served_endpoint) = loop {
+            attempts += 1;
+            
+            // 10. Sandbox - Circuit breaker; fails fast while the module keeps failing
+            // Thresholds come from the base limits policy: module health is shared by all tenants
+            let breaker_policy = &self.policy.base.limits_policy.circuit_breaker;
+            let thresholds = breaker_policy.thresholds_for(&module_id);
+            let breaker_key = breaker_policy.breaker_key(&module_id, capability);
+            let breaker_capability = thresholds.per_capability.then_some(capability);
+            if !self.circuit_breakers.get(&breaker_key).is_some_and(|breaker| breaker.is_for(thresholds)) {
+                let breaker = sandbox::circuit_breaker::CircuitBreaker::new(thresholds.clone());
+                self.circuit_breakers.insert(breaker_key.clone(), breaker);
+            }
+            let breaker = self.circuit_breakers.get_mut(&breaker_key)
+                .ok_or("Circuit breaker missing")?;
+            
+            match breaker.admit(std::time::Instant::now()) {
+                Ok(transition) => audit_breaker_transition(&module_id, breaker_capability, transition),
+                Err(retry_after) => {
+                    let message = format!("Circuit breaker for module '{}' is {}", module_id, breaker.state().as_str());
+                    let retry = ipc::encode::RetryAdvice::after(retry_after);
+                    return self.encode_execution_error(message_id, "MODULE_UNAVAILABLE", &message, retry, attempts);
+                }
+            }
+            
+            // 11. Sandbox - Pick an instance in rotation (rebuilt when the manifest's endpoints change)
+            if !self.module_instances.get(&module_id).is_some_and(|pool| pool.is_for(&endpoints)) {
+                let pool = sandbox::instances::InstancePool::from_endpoints(&endpoints)?;
+                self.module_instances.insert(module_id.clone(), pool);
+            }
+            let pool = self.module_instances.get_mut(&module_id)
+                .ok_or("Module instance pool missing")?;
+            
+            // (error code, message, whether the module may have run)
+            let failure = match pool.select() {
+                Some(instance) => {
+                    // 12. Sandbox - Run the module on its backend, or call a warm worker (own network
+                    // namespace, seccomp filter installed before exec)
+                    let spawn_config = sandbox::spawn::SpawnConfig {
+                        module_id: module_id.clone(),
+                        endpoint: wasm_endpoint.clone().unwrap_or_else(|| pool.invoke_endpoint(instance).to_string()),
+                        stdin_data: stdin_data.clone(),
+                        timeout: deadline.saturating_duration_since(std::time::Instant::now()),
+                        max_output_bytes: limits.max_output_bytes,
+                        syscall_filter: syscall_filter.clone(),
+                        network: limits.network.clone(),
+                        tenant_state: tenant_state.clone(),
+                    };
+                    
+                    let endpoint = spawn_config.endpoint.clone();
+                    let module_output = self.invoke_module(
+                        spawn_config,
+                        backend,
+                        persistent_worker,
+                        &limits,
+                        auth_context.tenant_id.as_deref(),
+                    );
+                    let spawn_error = module_output.as_ref().err().map(|e| e.to_string());
+                    let pool = self.module_instances.get_mut(&module_id)
+                        .ok_or("Module instance pool missing")?;
+                    pool.release(instance, spawn_error.as_deref());
+                    observed::module_status::record_instances(&module_id, pool.status(), &mut self.module_statuses);
+                    if let Some(breaker) = self.circuit_breakers.get_mut(&breaker_key) {
+                        let outcome = sandbox::circuit_breaker::CallOutcome::from_error(spawn_error.as_deref());
+                        let transition = breaker.record(outcome, std::time::Instant::now());
+                        audit_breaker_transition(&module_id, breaker_capability, transition);
+                    }
+                    
+                    match module_output {
+                        Ok(output) => break (output, endpoint),
+                        // Limit and security violations are not retried and stay hard errors
+                        Err(e) if !sandbox::retry::is_transient(&e.to_string()) => {
+                            if e.to_string().starts_with("SECURITY_VIOLATION") {
+                                let event = observed::audit_events::audit_security_violation(
+                                    &auth_context.actor_id,
+                                    &auth_context.role,
+                                    &module_id,
+                                    capability,
+                                    &e.to_string(),
+                                );
+                                let _ = observed::audit_events::record_audit_event(event);
+                            }
+                            return Err(e);
+                        }
+                        Err(e) => (sandbox::retry::error_code(&e.to_string()), e.to_string(), true),
+                    }
+                }
+                None => {
+                    // Nothing was called, but the breaker is waiting for an outcome
+                    if let Some(breaker) = self.circuit_breakers.get_mut(&breaker_key) {
+                        let transition = breaker.record(
+                            sandbox::circuit_breaker::CallOutcome::Failure,
+                            std::time::Instant::now(),
+                        );
+                        audit_breaker_transition(&module_id, breaker_capability, transition);
+                    }
+                    ("MODULE_UNAVAILABLE", format!("No healthy instance of module '{}'", module_id), false)
+                }
+            };
+            
+            // Back off and try again while attempts and time remain; otherwise the client
+            // may retry only if a second run cannot have a different effect, and is advised
+            // as many tries as the capability's retry policy gives the kernel
+            let (error_code, message, ran) = failure;
+            let delay = retry_settings.backoff(attempts, sandbox::retry::jitter_random());
+            if attempts >= max_attempts || std::time::Instant::now() + delay >= deadline {
+                let retry = if !ran || idempotent {
+                    ipc::encode::RetryAdvice::up_to(retry_settings.max_attempts)
+                } else {
+                    ipc::encode::RetryAdvice::never()
+                };
+                return self.encode_execution_error(message_id, error_code, &message, retry, attempts);
+            }
+            std::thread::sleep(delay);
+        };
         
-        // 10. Sandbox - Validate output size
+        // 13. Sandbox - Validate output size (process modules were already cut off while streaming)
         sandbox::limits::check_output_size(&module_output, &limits)?;
         
-        // 11. Parse module result
+        // 14. Parse module result
         let result: Value = serde_json::from_str(&module_output)
             .unwrap_or_else(|_| serde_json::json!({
                 "status": "success",
                 "data": {"simulated": true}
             }));
         
-        // 12. Result Gate - Validate shape
+        // 15. Result Gate - Validate shape
         result_gate::validate_shape::validate_result_shape(&result)?;
         
-        // 13. Result Gate - Apply profile (assuming main_ui)
-        let profile = result_gate::redaction::get_profile_for_ui("main_ui", &self.result_profiles)?;
+        // Shadow run: the candidate gets the same command on the instance and runtime that
+        // served it, with a time budget of its own, once this request has been answered. Its
+        // result is only compared; its outcome counts toward neither instance health nor the
+        // circuit breaker
+        if let Some((route_id, shadow_version)) = shadow {
+            let mut shadow_command = command.clone();
+            shadow_command["target"]["version"] = Value::String(shadow_version.clone());
+            
+            self.pending_shadows.push(PendingShadow {
+                route_id,
+                actor_id: auth_context.actor_id.clone(),
+                role: auth_context.role.clone(),
+                capability: capability.to_string(),
+                stable_version: version.clone(),
+                shadow_version,
+                stable_result: result.clone(),
+                spawn_config: sandbox::spawn::SpawnConfig {
+                    module_id: module_id.clone(),
+                    endpoint: served_endpoint,
+                    stdin_data: serde_json::to_string(&shadow_command)?,
+                    timeout: sandbox::retry::request_timeout(command, &limits),
+                    max_output_bytes: limits.max_output_bytes,
+                    syscall_filter: syscall_filter.clone(),
+                    network: limits.network.clone(),
+                    tenant_state: tenant_state.clone(),
+                },
+                backend,
+                persistent_worker,
+                limits: limits.clone(),
+                tenant_id: auth_context.tenant_id.clone(),
+            });
+        }
+        
+        // 16. Result Gate - Apply profile (assuming main_ui)
+        // (the tenant's policy is looked up again: running the module needed the kernel mutably)
+        let policy = self.policy.tenant(auth_context.tenant_id.as_deref())?;
+        let profile = result_gate::redaction::get_profile_for_ui("main_ui", &policy.result_profiles)?;
         let size_limits = result_gate::redaction::get_size_limits(profile);
         
-        // 14. Result Gate - Check size limits
+        // 17. Result Gate - Check size limits
         result_gate::size_limits::check_size_limits(&result, &size_limits)?;
         
-        // 15. Result Gate - Apply redaction
+        // 18. Result Gate - Apply redaction
         let redacted_result = result_gate::redaction::apply_profile(&result, profile)?;
         
-        // 16. Observed - Record execution
+        // 19. Observed - Record execution
         let elapsed_ms = start_time.elapsed().as_millis() as u64;
         
         observed::module_status::record_invocation(
@@ -241,21 +675,23 @@ impl Kernel {
         );
         let _ = observed::audit_events::record_audit_event(event);
         
-        // 17. Observed - Write status
+        // 20. Observed - Write status
         let _ = observed::module_status::write_runtime_status(&self.module_statuses);
         
-        // 18. IPC Encode - Create result envelope
+        // 21. IPC Encode - Create result envelope
         let result_envelope = ipc::encode::encode_result(
             message_id,
             redacted_result,
             Some(elapsed_ms),
+            Some(&version),
+            Some(attempts),
         );
         
-        // 19. IPC Encode - Canonical encoding
+        // 22. IPC Encode - Canonical encoding
         Ok(ipc::encode::encode_canonical(&result_envelope))
     }
     
-    /// Helper to encode error responses
+    /// Helper to encode error responses (never retryable: the same command fails the same way)
     fn encode_error(
         &self,
         correlation_id: Option<&str>,
@@ -268,10 +704,103 @@ impl Kernel {
             error_code,
             message,
             severity,
+            None,
+            ipc::encode::RetryAdvice::never(),
+        );
+        
+        Ok(ipc::encode::encode_canonical(&error_envelope))
+    }
+    
+    /// Helper to encode a failed module call, reporting how many attempts were made
+    fn encode_execution_error(
+        &self,
+        correlation_id: &str,
+        error_code: &str,
+        message: &str,
+        retry: ipc::encode::RetryAdvice,
+        attempts: u32,
+    ) -> Result<String, Box<dyn Error>> {
+        let details = serde_json::json!({
+            "context": {
+                "attempts": attempts
+            }
+        });
+        let error_envelope = ipc::encode::encode_error(
+            Some(correlation_id),
+            error_code,
+            message,
+            "error",
+            Some(details),
+            retry,
         );
         
         Ok(ipc::encode::encode_canonical(&error_envelope))
     }
+    
+    /// Helper to encode a denied Decision, attaching its trace when explain is on
+    fn encode_denial(
+        &self,
+        correlation_id: &str,
+        error_code: &str,
+        decision: &authz::decision::Decision,
+        explain: bool,
+    ) -> Result<String, Box<dyn Error>> {
+        let details = explain.then(|| serde_json::json!({
+            "context": {
+                "decision": decision.to_json()
+            }
+        }));
+        
+        let error_envelope = ipc::encode::encode_error(
+            Some(correlation_id),
+            error_code,
+            decision.reason(),
+            "error",
+            details,
+            ipc::encode::RetryAdvice::never(),
+        );
+        
+        Ok(ipc::encode::encode_canonical(&error_envelope))
+    }
+}
+
+/// Audits a circuit breaker state change, if there was one
+fn audit_breaker_transition(
+    module_id: &str,
+    capability: Option<&str>,
+    transition: Option<sandbox::circuit_breaker::Transition>,
+) {
+    if let Some(transition) = transition {
+        let event = observed::audit_events::audit_circuit_breaker(
+            module_id,
+            capability,
+            transition.from.as_str(),
+            transition.to.as_str(),
+            &transition.reason,
+        );
+        let _ = observed::audit_events::record_audit_event(event);
+    }
+}
+
+/// True if the command sets `options.explain` and the actor's policy allows `EXPLAIN_CAPABILITY`
+/// Checked like any capability, so inherited roles, deny patterns and grants all apply
+fn explain_requested(
+    command: &Value,
+    auth_context: &authz::authorize::AuthContext,
+    policy: &config::policy_set::TenantPolicy,
+) -> bool {
+    let requested = command.get("options")
+        .and_then(|o| o.get("explain"))
+        .and_then(|e| e.as_bool())
+        .unwrap_or(false);
+    
+    requested && authz::authorize::authorize_with_grants(
+        auth_context,
+        authz::decision::EXPLAIN_CAPABILITY,
+        &policy.roles,
+        &policy.capability_requirements,
+        &policy.grants,
+    ).is_allowed()
 }
 
 #[cfg(test)]
@@ -286,4 +815,61 @@ mod lib_tests {
         // Just check it doesn't panic
         assert!(result.is_ok() || result.is_err());
     }
+    
+    #[test]
+    fn test_explain_requires_capability() {
+        use crate::authz::authorize::{AuthContext, CallerAttributes};
+        use crate::authz::tokens;
+        use crate::config::policy_set::{PolicySet, PolicySources};
+        
+        // Repository policy plus a lead role inheriting admin, an auditor role inheriting admin
+        // but denied explain, and a grant of explain to one viewer
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../system/policy");
+        let mut sources = PolicySources::read_dir(&dir).unwrap();
+        sources.insert(tokens::KEYRING_SOURCE, "keys: []\n");
+        sources.insert(tokens::REVOCATION_LIST_SOURCE, "revoked_token_ids: []\n");
+        let role = |name: &str, deny: &str| format!(
+            "  {}:\n    description: \"Test\"\n    inherits: [\"admin\"]\n    scopes: []\n    deny: [{}]\n    rate_limit_per_minute: 1\n    max_request_size_bytes: 1\n\n",
+            name, deny
+        );
+        let access = sources.get("access.yaml").unwrap().replace(
+            "  editor:\n",
+            &format!("{}{}  editor:\n", role("lead", ""), role("auditor", "\"authz.explain\"")),
+        );
+        sources.insert("access.yaml", &access);
+        sources.insert("grants.yaml", r#"
+version: v1.0.0
+grants:
+  - id: explain-for-support
+    actor_id: support-1
+    capabilities: ["authz.explain"]
+    scopes: ["authz:explain"]
+    reason: "Debugging a denial"
+    granted_by: admin-1
+    expires_at: "2999-01-01T00:00:00Z"
+"#);
+        let set = PolicySet::from_sources(&sources).unwrap();
+        
+        let context = |actor_id: &str, role: &str, scopes: &[&str]| AuthContext {
+            actor_id: actor_id.to_string(),
+            actor_type: "user".to_string(),
+            role: role.to_string(),
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+            args: Value::Null,
+            request_time: chrono::Utc::now(),
+            tenant_id: None,
+            caller: CallerAttributes::default(),
+        };
+        let explain = serde_json::json!({"options": {"explain": true}});
+        
+        let admin = context("admin-1", "admin", &["authz:explain"]);
+        assert!(explain_requested(&explain, &admin, &set.base));
+        assert!(!explain_requested(&serde_json::json!({}), &admin, &set.base));
+        // A token without the scope does not qualify, even for an admin
+        assert!(!explain_requested(&explain, &context("admin-1", "admin", &[]), &set.base));
+        assert!(!explain_requested(&explain, &context("editor-1", "editor", &["authz:explain"]), &set.base));
+        assert!(explain_requested(&explain, &context("lead-1", "lead", &["authz:explain"]), &set.base));
+        assert!(!explain_requested(&explain, &context("auditor-1", "auditor", &["authz:explain"]), &set.base));
+        assert!(explain_requested(&explain, &context("support-1", "viewer", &[]), &set.base));
+    }
 }
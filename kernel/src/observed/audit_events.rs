@@ -26,6 +26,12 @@ pub struct AuditMetadata {
     pub to_id: Option<String>,
     pub execution_time_ms: Option<u64>,
     pub error_code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grant_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_policy_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_policy_hash: Option<String>,
 }
 
 /// Records an audit event
@@ -102,6 +108,83 @@ pub fn audit_authz(
     }
 }
 
+/// Creates audit event for actor token verification
+pub fn audit_authentication(
+    actor_id: Option<&str>,
+    verified: bool,
+    reason: Option<&str>,
+) -> AuditEvent {
+    AuditEvent {
+        timestamp: current_timestamp(),
+        event_type: "authentication".to_string(),
+        actor_id: actor_id.unwrap_or("unknown").to_string(),
+        actor_role: String::new(),
+        capability: String::new(),
+        result: if verified { "allowed".to_string() } else { "denied".to_string() },
+        reason: reason.map(|s| s.to_string()),
+        metadata: None,
+    }
+}
+
+/// Creates audit event for a capability allowed through a break-glass grant
+pub fn audit_grant_use(
+    actor_id: &str,
+    actor_role: &str,
+    capability: &str,
+    grant_id: &str,
+    grant_reason: &str,
+) -> AuditEvent {
+    AuditEvent {
+        timestamp: current_timestamp(),
+        event_type: "grant_use".to_string(),
+        actor_id: actor_id.to_string(),
+        actor_role: actor_role.to_string(),
+        capability: capability.to_string(),
+        result: "allowed".to_string(),
+        reason: Some(grant_reason.to_string()),
+        metadata: Some(AuditMetadata {
+            from_type: None,
+            from_id: None,
+            to_type: None,
+            to_id: None,
+            execution_time_ms: None,
+            error_code: None,
+            grant_id: Some(grant_id.to_string()),
+            old_policy_hash: None,
+            new_policy_hash: None,
+        }),
+    }
+}
+
+/// Creates audit event for a policy reload attempt
+pub fn audit_policy_reload(
+    old_hash: &str,
+    new_hash: Option<&str>,
+    success: bool,
+    reason: Option<&str>,
+) -> AuditEvent {
+    AuditEvent {
+        timestamp: current_timestamp(),
+        event_type: "policy_reload".to_string(),
+        actor_id: "kernel".to_string(),
+        actor_role: "system".to_string(),
+        capability: String::new(),
+        result: if success { "success".to_string() } else { "error".to_string() },
+        reason: reason.map(|s| s.to_string()),
+        metadata: Some(AuditMetadata {
+            from_type: None,
+            from_id: None,
+            to_type: None,
+            to_id: None,
+            execution_time_ms: None,
+            error_code: None,
+            grant_id: None,
+            old_policy_hash: Some(old_hash.to_string()),
+            new_policy_hash: new_hash.map(|s| s.to_string()),
+        }),
+    }
+}
+
 /// Creates audit event for routing check
 pub fn audit_routing(
     actor_id: &str,
@@ -129,10 +212,106 @@ pub fn audit_routing(
             to_id: Some(to_id.to_string()),
             execution_time_ms: None,
             error_code: None,
+            grant_id: None,
+            old_policy_hash: None,
+            new_policy_hash: None,
         }),
     }
 }
 
+/// Creates audit event for a circuit breaker state change
+/// `capability` is set only for per-capability breakers
+pub fn audit_circuit_breaker(
+    module_id: &str,
+    capability: Option<&str>,
+    from_state: &str,
+    to_state: &str,
+    reason: &str,
+) -> AuditEvent {
+    AuditEvent {
+        timestamp: current_timestamp(),
+        event_type: "circuit_breaker".to_string(),
+        actor_id: "kernel".to_string(),
+        actor_role: "system".to_string(),
+        capability: capability.unwrap_or_default().to_string(),
+        result: to_state.to_string(),
+        reason: Some(format!("{} -> {}: {}", from_state, to_state, reason)),
+        metadata: Some(AuditMetadata {
+            from_type: None,
+            from_id: None,
+            to_type: Some("module".to_string()),
+            to_id: Some(module_id.to_string()),
+            execution_time_ms: None,
+            error_code: None,
+            grant_id: None,
+            old_policy_hash: None,
+            new_policy_hash: None,
+        }),
+    }
+}
+
+/// Creates audit event for a module the sandbox killed for a security violation
+pub fn audit_security_violation(
+    actor_id: &str,
+    actor_role: &str,
+    module_id: &str,
+    capability: &str,
+    reason: &str,
+) -> AuditEvent {
+    AuditEvent {
+        timestamp: current_timestamp(),
+        event_type: "security_violation".to_string(),
+        actor_id: actor_id.to_string(),
+        actor_role: actor_role.to_string(),
+        capability: capability.to_string(),
+        result: "killed".to_string(),
+        reason: Some(reason.to_string()),
+        metadata: Some(AuditMetadata {
+            from_type: None,
+            from_id: None,
+            to_type: Some("module".to_string()),
+            to_id: Some(module_id.to_string()),
+            execution_time_ms: None,
+            error_code: Some("SECURITY_VIOLATION".to_string()),
+            grant_id: None,
+            old_policy_hash: None,
+            new_policy_hash: None,
+        }),
+    }
+}
+
+/// Creates audit event comparing a shadow (canary candidate) run with the result served
+/// `outcome` is the result paths that differ, or the candidate's error
+pub fn audit_canary_shadow(
+    actor_id: &str,
+    actor_role: &str,
+    capability: &str,
+    route_id: &str,
+    served_version: &str,
+    shadow_version: &str,
+    outcome: Result<&[String], &str>,
+) -> AuditEvent {
+    let (result, detail) = match outcome {
+        Err(error) => ("error", format!("candidate failed: {}", error)),
+        Ok([]) => ("match", "results match".to_string()),
+        Ok(differences) => ("mismatch", format!("results differ at {}", differences.join(", "))),
+    };
+    
+    AuditEvent {
+        timestamp: current_timestamp(),
+        event_type: "canary_shadow".to_string(),
+        actor_id: actor_id.to_string(),
+        actor_role: actor_role.to_string(),
+        capability: capability.to_string(),
+        result: result.to_string(),
+        reason: Some(format!(
+            "route '{}': {} served, {} shadowed: {}",
+            route_id, served_version, shadow_version, detail
+        )),
+        metadata: None,
+    }
+}
+
 /// Creates audit event for execution
 pub fn audit_execution(
     actor_id: &str,
@@ -157,6 +336,9 @@ pub fn audit_execution(
             to_id: None,
             execution_time_ms: Some(execution_time_ms),
             error_code: error_code.map(|s| s.to_string()),
+            grant_id: None,
+            old_policy_hash: None,
+            new_policy_hash: None,
         }),
     }
 }
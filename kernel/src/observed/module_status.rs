@@ -1,6 +1,7 @@
 // Module Status
 // Tracks runtime status of modules (facts-only)
 
+use crate::sandbox::backend::ResourceUsage;
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
 use std::error::Error;
@@ -17,6 +18,25 @@ pub struct ModuleStatus {
     pub avg_execution_time_ms: f64,
     pub last_error: Option<String>,
     pub uptime_seconds: u64,
+    /// Instances of the module and whether they are in rotation
+    #[serde(default)]
+    pub instances: Vec<InstanceStatus>,
+    /// Sandbox backend of the last invocation, and what that invocation used
+    #[serde(default)]
+    pub backend: Option<String>,
+    #[serde(default)]
+    pub last_usage: Option<ResourceUsage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceStatus {
+    pub id: String,
+    pub endpoint: String,
+    pub state: String,  // "healthy", "unhealthy"
+    pub outstanding: u32,
+    pub consecutive_failures: u32,
+    pub last_probe: Option<String>,
+    pub last_error: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -26,15 +46,8 @@ pub struct RuntimeStatus {
     pub modules: HashMap<String, ModuleStatus>,
 }
 
-/// Records module invocation
-pub fn record_invocation(
-    module_id: &str,
-    execution_time_ms: u64,
-    success: bool,
-    error: Option<&str>,
-    statuses: &mut HashMap<String, ModuleStatus>,
-) {
-    let status = statuses.entry(module_id.to_string())
+fn status_entry<'a>(module_id: &str, statuses: &'a mut HashMap<String, ModuleStatus>) -> &'a mut ModuleStatus {
+    statuses.entry(module_id.to_string())
         .or_insert_with(|| ModuleStatus {
             module_id: module_id.to_string(),
             status: "idle".to_string(),
@@ -44,7 +57,21 @@ pub fn record_invocation(
             avg_execution_time_ms: 0.0,
             last_error: None,
             uptime_seconds: 0,
-        });
+            instances: Vec::new(),
+            backend: None,
+            last_usage: None,
+        })
+}
+
+/// Records module invocation
+pub fn record_invocation(
+    module_id: &str,
+    execution_time_ms: u64,
+    success: bool,
+    error: Option<&str>,
+    statuses: &mut HashMap<String, ModuleStatus>,
+) {
+    let status = status_entry(module_id, statuses);
     
     status.invocation_count += 1;
     status.last_invocation = Some(current_timestamp());
@@ -63,6 +90,27 @@ pub fn record_invocation(
         (status.avg_execution_time_ms * (count - 1.0) + execution_time_ms as f64) / count;
 }
 
+/// Replaces the module's instance states
+pub fn record_instances(
+    module_id: &str,
+    instances: Vec<InstanceStatus>,
+    statuses: &mut HashMap<String, ModuleStatus>,
+) {
+    status_entry(module_id, statuses).instances = instances;
+}
+
+/// Records the backend an invocation ran on and its resource usage
+pub fn record_usage(
+    module_id: &str,
+    backend: &str,
+    usage: ResourceUsage,
+    statuses: &mut HashMap<String, ModuleStatus>,
+) {
+    let status = status_entry(module_id, statuses);
+    status.backend = Some(backend.to_string());
+    status.last_usage = Some(usage);
+}
+
 /// Writes runtime status to file
 pub fn write_runtime_status(statuses: &HashMap<String, ModuleStatus>) -> Result<(), Box<dyn Error>> {
     let runtime_status = RuntimeStatus {
@@ -123,4 +171,26 @@ mod tests {
         assert_eq!(status.invocation_count, 2);
         assert_eq!(status.avg_execution_time_ms, 150.0);
     }
+    
+    #[test]
+    fn test_record_instances() {
+        let mut statuses = HashMap::new();
+        let instance = |id: &str, state: &str| InstanceStatus {
+            id: id.to_string(),
+            endpoint: format!("unix:///run/{}.sock", id),
+            state: state.to_string(),
+            outstanding: 0,
+            consecutive_failures: 0,
+            last_probe: None,
+            last_error: None,
+        };
+        
+        record_invocation("storage", 100, true, None, &mut statuses);
+        record_instances("storage", vec![instance("storage-1", "healthy"), instance("storage-2", "unhealthy")], &mut statuses);
+        
+        let status = statuses.get("storage").unwrap();
+        assert_eq!(status.invocation_count, 1);
+        assert_eq!(status.instances.len(), 2);
+        assert_eq!(status.instances[1].state, "unhealthy");
+    }
 }
@@ -0,0 +1,256 @@
+// Capability Patterns
+// The one segment-aware pattern language for capabilities, shared by authz, routing and invariants
+//
+// Patterns are dot-separated; wildcards and alternations always stand for whole segments:
+//   "storage.listings.create"       exact
+//   "storage.listings.*"            `*` matches exactly one segment
+//   "storage.**"                    `**` matches one or more segments
+//   "storage.listings.{get,list}"   `{a,b}` matches any one of the listed segments
+//
+// "storage*", "storage.*x" and "storage.{a,*}" are rejected when policy loads.
+
+use std::error::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Literal(String),
+    OneOf(Vec<String>),
+    /// `*`
+    Any,
+    /// `**`
+    AnyDepth,
+}
+
+impl Segment {
+    /// Whether this single-segment matcher accepts the capability segment (`**` excluded)
+    fn accepts(&self, segment: &str) -> bool {
+        match self {
+            Segment::Literal(literal) => literal == segment,
+            Segment::OneOf(options) => options.iter().any(|o| o == segment),
+            Segment::Any => true,
+            Segment::AnyDepth => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapabilityPattern {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+impl CapabilityPattern {
+    /// Parses and validates a pattern
+    pub fn parse(pattern: &str) -> Result<Self, Box<dyn Error>> {
+        let invalid = |reason: &str| format!("Invalid capability pattern '{}': {}", pattern, reason);
+        
+        let mut segments = Vec::new();
+        for raw in pattern.split('.') {
+            let segment = match raw {
+                "" => return Err(invalid("empty segment").into()),
+                "*" => Segment::Any,
+                "**" => {
+                    if segments.last() == Some(&Segment::AnyDepth) {
+                        return Err(invalid("'**' may not repeat").into());
+                    }
+                    Segment::AnyDepth
+                }
+                _ if raw.starts_with('{') && raw.ends_with('}') => {
+                    let mut options: Vec<String> = Vec::new();
+                    for option in raw[1..raw.len() - 1].split(',') {
+                        if option.is_empty() || option.contains(is_special) {
+                            return Err(invalid("alternatives must be plain, non-empty segments").into());
+                        }
+                        if !options.iter().any(|o| o == option) {
+                            options.push(option.to_string());
+                        }
+                    }
+                    Segment::OneOf(options)
+                }
+                _ if raw.contains(is_special) => {
+                    return Err(invalid("'*', '**' and '{a,b}' must be whole segments").into());
+                }
+                _ => Segment::Literal(raw.to_string()),
+            };
+            segments.push(segment);
+        }
+        
+        Ok(CapabilityPattern {
+            source: pattern.to_string(),
+            segments,
+        })
+    }
+    
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+    
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+    
+    /// Matches a capability segment by segment; capabilities with empty segments never match
+    pub fn matches(&self, capability: &str) -> bool {
+        let segments: Vec<&str> = capability.split('.').collect();
+        if segments.iter().any(|s| s.is_empty()) {
+            return false;
+        }
+        
+        match_segments(&self.segments, &segments)
+    }
+    
+    /// True if some capability matches both patterns
+    pub fn overlaps(&self, other: &CapabilityPattern) -> bool {
+        let a = expand_any_depth(&self.segments);
+        let b = expand_any_depth(&other.segments);
+        let mut memo = vec![vec![None; b.len() + 1]; a.len() + 1];
+        overlap(&a, &b, 0, 0, &mut memo)
+    }
+}
+
+fn is_special(c: char) -> bool {
+    matches!(c, '*' | '{' | '}' | ',')
+}
+
+fn match_segments(pattern: &[Segment], capability: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => capability.is_empty(),
+        Some((Segment::AnyDepth, rest)) => {
+            (1..=capability.len()).any(|taken| match_segments(rest, &capability[taken..]))
+        }
+        Some((segment, rest)) => match capability.split_first() {
+            Some((first, remaining)) => segment.accepts(first) && match_segments(rest, remaining),
+            None => false,
+        },
+    }
+}
+
+/// Pattern element for overlap checks: `**` becomes one `*` followed by zero-or-more `*`
+#[derive(Clone, Copy)]
+enum Element<'a> {
+    One(&'a Segment),
+    Repeat,
+}
+
+fn expand_any_depth(segments: &[Segment]) -> Vec<Element<'_>> {
+    const ANY: Segment = Segment::Any;
+    
+    let mut elements = Vec::new();
+    for segment in segments {
+        if *segment == Segment::AnyDepth {
+            elements.push(Element::One(&ANY));
+            elements.push(Element::Repeat);
+        } else {
+            elements.push(Element::One(segment));
+        }
+    }
+    elements
+}
+
+fn overlap(a: &[Element], b: &[Element], i: usize, j: usize, memo: &mut [Vec<Option<bool>>]) -> bool {
+    if let Some(known) = memo[i][j] {
+        return known;
+    }
+    
+    let result = match (a.get(i), b.get(j)) {
+        (None, None) => true,
+        (Some(Element::Repeat), _) => {
+            overlap(a, b, i + 1, j, memo) || (j < b.len() && overlap(a, b, i, j + 1, memo))
+        }
+        (_, Some(Element::Repeat)) => {
+            overlap(a, b, i, j + 1, memo) || (i < a.len() && overlap(a, b, i + 1, j, memo))
+        }
+        (Some(Element::One(x)), Some(Element::One(y))) => {
+            segments_intersect(x, y) && overlap(a, b, i + 1, j + 1, memo)
+        }
+        _ => false,
+    };
+    
+    memo[i][j] = Some(result);
+    result
+}
+
+fn segments_intersect(a: &Segment, b: &Segment) -> bool {
+    match (a, b) {
+        (Segment::Any, _) | (_, Segment::Any) => true,
+        (Segment::Literal(x), other) | (other, Segment::Literal(x)) => other.accepts(x),
+        (Segment::OneOf(xs), Segment::OneOf(ys)) => xs.iter().any(|x| ys.contains(x)),
+        _ => false,
+    }
+}
+
+/// Checks that a pattern is well formed
+pub fn validate_pattern(pattern: &str) -> Result<(), Box<dyn Error>> {
+    CapabilityPattern::parse(pattern).map(|_| ())
+}
+
+/// Matches a single pattern against a capability; invalid patterns match nothing
+pub fn pattern_matches(pattern: &str, capability: &str) -> bool {
+    CapabilityPattern::parse(pattern)
+        .map(|p| p.matches(capability))
+        .unwrap_or(false)
+}
+
+/// True if some capability could match both patterns; invalid patterns overlap nothing
+pub fn patterns_overlap(a: &str, b: &str) -> bool {
+    match (CapabilityPattern::parse(a), CapabilityPattern::parse(b)) {
+        (Ok(a), Ok(b)) => a.overlaps(&b),
+        _ => false,
+    }
+}
+
+/// True if the string uses any pattern syntax (as opposed to naming one capability)
+pub fn is_pattern(value: &str) -> bool {
+    value.contains(is_special)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_single_segment_wildcard() {
+        assert!(pattern_matches("storage.listings.*", "storage.listings.create"));
+        assert!(!pattern_matches("storage.listings.*", "storage.listings"));
+        assert!(!pattern_matches("storage.*", "storage.listings.create"));
+        assert!(!pattern_matches("storage.*", "storage."));
+        assert!(pattern_matches("storage.*.create", "storage.listings.create"));
+    }
+    
+    #[test]
+    fn test_any_depth_wildcard() {
+        assert!(pattern_matches("storage.**", "storage.listings"));
+        assert!(pattern_matches("storage.**", "storage.listings.create"));
+        assert!(!pattern_matches("storage.**", "storage"));
+        assert!(!pattern_matches("storage.**", "storage_admin.purge"));
+        assert!(pattern_matches("**.delete", "storage.listings.delete"));
+        assert!(pattern_matches("storage.**.create", "storage.a.b.create"));
+    }
+    
+    #[test]
+    fn test_alternation() {
+        assert!(pattern_matches("storage.listings.{get,list}", "storage.listings.list"));
+        assert!(!pattern_matches("storage.listings.{get,list}", "storage.listings.delete"));
+        assert!(pattern_matches("{storage,pricing}.**", "pricing.calculate"));
+    }
+    
+    #[test]
+    fn test_invalid_patterns() {
+        for pattern in ["storage*", "storage.*x", "storage..create", "", "storage.{a,*}", "storage.{}", "a.**.**", "st{a,b}"] {
+            assert!(validate_pattern(pattern).is_err(), "{} should be invalid", pattern);
+        }
+    }
+    
+    #[test]
+    fn test_overlap() {
+        assert!(patterns_overlap("storage.**", "storage.listings.*"));
+        assert!(patterns_overlap("storage.listings.*", "storage.listings.create"));
+        assert!(patterns_overlap("*.listings.create", "storage.*.create"));
+        assert!(patterns_overlap("storage.{get,list}", "storage.{list,put}"));
+        assert!(!patterns_overlap("storage.{get,list}", "storage.{put,delete}"));
+        assert!(!patterns_overlap("storage.*", "storage.listings.create"));
+        assert!(!patterns_overlap("storage.**", "pricing.**"));
+        assert!(patterns_overlap("**.delete", "storage.**"));
+        assert!(!patterns_overlap("storage.**", "storage"));
+    }
+}
@@ -0,0 +1,175 @@
+// Capability Trie
+// Segment trie over dot-separated capability patterns, compiled once at policy load
+//
+// Patterns use the shared language in primitives::capability_pattern
+// (`*` one segment, `**` one or more, `{a,b}` alternation).
+
+use super::capability_pattern::{CapabilityPattern, Segment};
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+
+#[derive(Debug, Clone)]
+pub struct CapabilityTrie<T> {
+    root: TrieNode,
+    /// Inserted values; nodes refer to them by position
+    values: Vec<T>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct TrieNode {
+    children: HashMap<String, TrieNode>,
+    /// Continuation after a `*` segment
+    any: Option<Box<TrieNode>>,
+    /// Continuation after a `**` segment
+    any_depth: Option<Box<TrieNode>>,
+    /// Values whose pattern ends at this node
+    values: Vec<usize>,
+}
+
+impl TrieNode {
+    fn insert(&mut self, segments: &[Segment], value: usize) {
+        let (first, rest) = match segments.split_first() {
+            Some(split) => split,
+            None => {
+                self.values.push(value);
+                return;
+            }
+        };
+        
+        match first {
+            Segment::Literal(literal) => self.children.entry(literal.clone()).or_default().insert(rest, value),
+            Segment::OneOf(options) => {
+                for option in options {
+                    self.children.entry(option.clone()).or_default().insert(rest, value);
+                }
+            }
+            Segment::Any => self.any.get_or_insert_with(Default::default).insert(rest, value),
+            Segment::AnyDepth => self.any_depth.get_or_insert_with(Default::default).insert(rest, value),
+        }
+    }
+    
+    fn collect(&self, capability: &[&str], found: &mut BTreeSet<usize>) {
+        let (first, rest) = match capability.split_first() {
+            Some(split) => split,
+            None => {
+                found.extend(self.values.iter().copied());
+                return;
+            }
+        };
+        
+        if let Some(child) = self.children.get(*first) {
+            child.collect(rest, found);
+        }
+        if let Some(any) = &self.any {
+            any.collect(rest, found);
+        }
+        if let Some(any_depth) = &self.any_depth {
+            for taken in 1..=capability.len() {
+                any_depth.collect(&capability[taken..], found);
+            }
+        }
+    }
+}
+
+impl<T> CapabilityTrie<T> {
+    pub fn new() -> Self {
+        CapabilityTrie {
+            root: TrieNode::default(),
+            values: Vec::new(),
+        }
+    }
+    
+    /// Number of patterns inserted
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+    
+    /// Inserts a pattern; fails if the pattern is invalid
+    pub fn insert(&mut self, pattern: &str, value: T) -> Result<(), Box<dyn Error>> {
+        let pattern = CapabilityPattern::parse(pattern)?;
+        
+        self.root.insert(pattern.segments(), self.values.len());
+        self.values.push(value);
+        Ok(())
+    }
+    
+    /// Returns every value whose pattern matches the capability, in insertion order
+    /// Cost depends on the capability's depth, not on the number of patterns
+    pub fn matches(&self, capability: &str) -> Vec<&T> {
+        let segments: Vec<&str> = capability.split('.').collect();
+        if segments.iter().any(|s| s.is_empty()) {
+            return Vec::new();
+        }
+        
+        let mut found = BTreeSet::new();
+        self.root.collect(&segments, &mut found);
+        found.into_iter().map(|position| &self.values[position]).collect()
+    }
+    
+    /// True if any pattern matches the capability
+    pub fn contains_match(&self, capability: &str) -> bool {
+        !self.matches(capability).is_empty()
+    }
+}
+
+impl<T> Default for CapabilityTrie<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_exact_and_wildcard_matches() {
+        let mut trie = CapabilityTrie::new();
+        trie.insert("storage.listings.create", 1).unwrap();
+        trie.insert("storage.**", 2).unwrap();
+        trie.insert("pricing.calculate", 3).unwrap();
+        trie.insert("storage.*", 4).unwrap();
+        
+        assert_eq!(trie.matches("storage.listings.create"), vec![&1, &2]);
+        assert_eq!(trie.matches("storage.imports"), vec![&2, &4]);
+        assert_eq!(trie.matches("pricing.calculate"), vec![&3]);
+        assert!(trie.matches("pricing.other").is_empty());
+        assert_eq!(trie.len(), 4);
+    }
+    
+    #[test]
+    fn test_wildcard_is_segment_aware() {
+        let mut trie = CapabilityTrie::new();
+        trie.insert("storage.*", ()).unwrap();
+        
+        assert!(trie.contains_match("storage.listings"));
+        assert!(!trie.contains_match("storage"));
+        assert!(!trie.contains_match("storage."));
+        assert!(!trie.contains_match("storage.listings.create"));
+        assert!(!trie.contains_match("storage_admin.purge"));
+    }
+    
+    #[test]
+    fn test_alternation_and_any_depth() {
+        let mut trie = CapabilityTrie::new();
+        trie.insert("storage.listings.{get,list}", "read").unwrap();
+        trie.insert("**.delete", "delete").unwrap();
+        
+        assert_eq!(trie.matches("storage.listings.list"), vec![&"read"]);
+        assert_eq!(trie.matches("storage.listings.delete"), vec![&"delete"]);
+        assert!(trie.matches("storage.listings.create").is_empty());
+    }
+    
+    #[test]
+    fn test_invalid_patterns_rejected() {
+        let mut trie = CapabilityTrie::new();
+        assert!(trie.insert("storage*", ()).is_err());
+        assert!(trie.insert("storage.*x", ()).is_err());
+        assert!(trie.insert("storage..create", ()).is_err());
+        assert!(trie.is_empty());
+    }
+}
@@ -105,6 +105,25 @@ pub fn validate_id_format(id: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// Validate a tenant ID
+/// Tenant IDs become directory names (policy trees, module state), so only
+/// lowercase alphanumerics, '-' and '_' are allowed, starting with a letter or digit
+pub fn validate_tenant_id(tenant_id: &str) -> Result<(), String> {
+    if tenant_id.is_empty() || tenant_id.len() > 63 {
+        return Err("Tenant ID must be 1-63 characters".to_string());
+    }
+    
+    if !tenant_id.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit()) {
+        return Err("Tenant ID must start with a lowercase letter or digit".to_string());
+    }
+    
+    if !tenant_id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_') {
+        return Err("Tenant ID must be lowercase alphanumeric with '-' or '_'".to_string());
+    }
+    
+    Ok(())
+}
+
 /// Entity-specific ID generators
 pub mod entities {
     use super::*;
@@ -235,4 +254,14 @@ mod tests {
         assert_eq!(id1, id2);
         assert!(id1.starts_with(prefixes::IMPORT));
     }
+
+    #[test]
+    fn test_validate_tenant_id() {
+        assert!(validate_tenant_id("dealer-a").is_ok());
+        assert!(validate_tenant_id("dealer_42").is_ok());
+        assert!(validate_tenant_id("").is_err());
+        assert!(validate_tenant_id("../etc").is_err());
+        assert!(validate_tenant_id("Dealer").is_err());
+        assert!(validate_tenant_id("-dealer").is_err());
+    }
 }
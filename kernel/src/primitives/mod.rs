@@ -1,7 +1,9 @@
 // Kernel Primitives
-// Pure functions for deterministic operations (stable sort, hashing, ID generation)
+// Pure functions for deterministic operations (stable sort, hashing, ID generation, pattern tries)
 // No I/O, no side effects - must be reproducible
 
 pub mod stable_sort;
 pub mod hash;
 pub mod ids;
+pub mod capability_pattern;
+pub mod capability_trie;
@@ -40,7 +40,12 @@ pub fn load_result_profiles() -> Result<ResultProfilesPolicy, Box<dyn Error>> {
     let content = fs::read_to_string(policy_path)
         .map_err(|e| format!("Failed to read result profiles policy: {}", e))?;
     
-    let policy: ResultProfilesPolicy = serde_yaml::from_str(&content)
+    parse_result_profiles(&content)
+}
+
+/// Parses result profiles policy content
+pub fn parse_result_profiles(content: &str) -> Result<ResultProfilesPolicy, Box<dyn Error>> {
+    let policy: ResultProfilesPolicy = serde_yaml::from_str(content)
         .map_err(|e| format!("Failed to parse result profiles policy: {}", e))?;
     
     Ok(policy)
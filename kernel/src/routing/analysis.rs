@@ -0,0 +1,498 @@
+// Routing Analysis
+// Shape of the routing graph: per-UI reachability, chain cycles and dead ends,
+// disabled routes still referenced by chains, and internal routes a UI can reach
+//
+// Errors (chain cycles, internal routes reachable from a UI) fail policy validation;
+// warnings are reported only. Exports the graph as Graphviz DOT or Mermaid.
+
+use super::graph::{Route, RoutingGraph};
+use crate::authz::access_analysis::TableRow;
+use crate::primitives::capability_pattern;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    pub severity: Severity,
+    /// chain_cycle | chain_dead_end | disabled_referenced_route | internal_route_exposed
+    pub kind: String,
+    pub subject: String,
+    pub detail: String,
+}
+
+/// A node reachable from a UI, with the first route path found (fewest hops)
+#[derive(Debug, Clone, Serialize)]
+pub struct ReachabilityRow {
+    pub ui: String,
+    pub target: String,
+    pub hops: usize,
+    pub via: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoutingAnalysis {
+    pub reachability: Vec<ReachabilityRow>,
+    pub findings: Vec<Finding>,
+}
+
+impl RoutingAnalysis {
+    pub fn errors(&self) -> impl Iterator<Item = &Finding> {
+        self.findings.iter().filter(|f| f.severity == Severity::Error)
+    }
+    
+    pub fn warnings(&self) -> impl Iterator<Item = &Finding> {
+        self.findings.iter().filter(|f| f.severity == Severity::Warning)
+    }
+    
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+}
+
+impl TableRow for Finding {
+    fn headers() -> Vec<&'static str> {
+        vec!["SEVERITY", "KIND", "SUBJECT", "DETAIL"]
+    }
+    
+    fn cells(&self) -> Vec<String> {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        vec![severity.to_string(), self.kind.clone(), self.subject.clone(), self.detail.clone()]
+    }
+}
+
+impl TableRow for ReachabilityRow {
+    fn headers() -> Vec<&'static str> {
+        vec!["UI", "TARGET", "HOPS", "VIA"]
+    }
+    
+    fn cells(&self) -> Vec<String> {
+        vec![self.ui.clone(), self.target.clone(), self.hops.to_string(), self.via.join(" -> ")]
+    }
+}
+
+/// Runs every analysis over the graph; findings are sorted errors first
+pub fn analyze(graph: &RoutingGraph) -> RoutingAnalysis {
+    let mut findings = Vec::new();
+    findings.extend(chain_cycles(graph));
+    findings.extend(exposed_internal_routes(graph));
+    findings.extend(chain_dead_ends(graph));
+    findings.extend(disabled_referenced_routes(graph));
+    
+    findings.sort_by(|a, b| {
+        (a.severity != Severity::Error, &a.kind, &a.subject, &a.detail)
+            .cmp(&(b.severity != Severity::Error, &b.kind, &b.subject, &b.detail))
+    });
+    
+    RoutingAnalysis {
+        reachability: reachability(graph),
+        findings,
+    }
+}
+
+fn node_name(r#type: &str, id: &str) -> String {
+    format!("{}:{}", r#type, id)
+}
+
+/// Breadth-first search over enabled routes from every UI node
+fn reachability(graph: &RoutingGraph) -> Vec<ReachabilityRow> {
+    let mut adjacency: BTreeMap<String, Vec<(&Route, String)>> = BTreeMap::new();
+    for route in graph.model.routes.iter().filter(|r| r.enabled) {
+        adjacency.entry(node_name(&route.from.r#type, &route.from.id))
+            .or_default()
+            .push((route, node_name(&route.to.r#type, &route.to.id)));
+    }
+    
+    let uis: BTreeSet<String> = graph.model.routes.iter()
+        .filter(|r| r.enabled && r.from.r#type == "ui")
+        .map(|r| node_name(&r.from.r#type, &r.from.id))
+        .collect();
+    
+    let mut rows = Vec::new();
+    for ui in uis {
+        let mut paths: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut queue = VecDeque::from([(ui.clone(), Vec::new())]);
+        
+        while let Some((node, via)) = queue.pop_front() {
+            for (route, target) in adjacency.get(&node).into_iter().flatten() {
+                if *target == ui || paths.contains_key(target) {
+                    continue;
+                }
+                
+                let mut path = via.clone();
+                path.push(route.id.clone());
+                paths.insert(target.clone(), path.clone());
+                queue.push_back((target.clone(), path));
+            }
+        }
+        
+        for (target, via) in paths {
+            rows.push(ReachabilityRow {
+                ui: ui.clone(),
+                target,
+                hops: via.len(),
+                via,
+            });
+        }
+    }
+    
+    rows
+}
+
+/// Cycles in `capability_chains`, each reported once starting from its smallest capability
+fn chain_cycles(graph: &RoutingGraph) -> Vec<Finding> {
+    let chains: BTreeMap<&str, Vec<&str>> = graph.model.capability_chains.iter()
+        .map(|(parent, children)| (parent.as_str(), children.iter().map(|c| c.as_str()).collect()))
+        .collect();
+    
+    let mut cycles: BTreeSet<Vec<String>> = BTreeSet::new();
+    let mut finished: BTreeSet<&str> = BTreeSet::new();
+    
+    for start in chains.keys() {
+        let mut stack: Vec<&str> = Vec::new();
+        find_cycles(start, &chains, &mut stack, &mut finished, &mut cycles);
+    }
+    
+    cycles.into_iter()
+        .map(|cycle| {
+            let mut path = cycle.clone();
+            path.push(cycle[0].clone());
+            Finding {
+                severity: Severity::Error,
+                kind: "chain_cycle".to_string(),
+                subject: cycle[0].clone(),
+                detail: path.join(" -> "),
+            }
+        })
+        .collect()
+}
+
+fn find_cycles<'a>(
+    node: &'a str,
+    chains: &BTreeMap<&'a str, Vec<&'a str>>,
+    stack: &mut Vec<&'a str>,
+    finished: &mut BTreeSet<&'a str>,
+    cycles: &mut BTreeSet<Vec<String>>,
+) {
+    if let Some(position) = stack.iter().position(|n| *n == node) {
+        let mut cycle: Vec<String> = stack[position..].iter().map(|n| n.to_string()).collect();
+        let smallest = (0..cycle.len()).min_by_key(|&i| &cycle[i]).unwrap_or(0);
+        cycle.rotate_left(smallest);
+        cycles.insert(cycle);
+        return;
+    }
+    if finished.contains(node) {
+        return;
+    }
+    
+    stack.push(node);
+    for child in chains.get(node).into_iter().flatten() {
+        find_cycles(child, chains, stack, finished, cycles);
+    }
+    stack.pop();
+    finished.insert(node);
+}
+
+/// Chain links that no enabled route carries: the child can never actually be invoked
+fn chain_dead_ends(graph: &RoutingGraph) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    
+    for (parent, children) in &graph.model.capability_chains {
+        for child in children {
+            let carried = graph.model.routes.iter().any(|route| {
+                route.enabled
+                    && route.from.capability.as_deref() == Some(parent.as_str())
+                    && graph.capability_matches(route, child)
+            });
+            
+            if !carried {
+                findings.push(Finding {
+                    severity: Severity::Warning,
+                    kind: "chain_dead_end".to_string(),
+                    subject: parent.clone(),
+                    detail: format!("'{}' -> '{}' has no enabled route", parent, child),
+                });
+            }
+        }
+    }
+    
+    findings
+}
+
+/// Disabled routes whose source or capabilities are named in `capability_chains`
+fn disabled_referenced_routes(graph: &RoutingGraph) -> Vec<Finding> {
+    let referenced: BTreeSet<&str> = graph.model.capability_chains.iter()
+        .flat_map(|(parent, children)| std::iter::once(parent).chain(children.iter()))
+        .map(|c| c.as_str())
+        .collect();
+    
+    graph.model.routes.iter()
+        .filter(|route| !route.enabled)
+        .filter_map(|route| {
+            let mut hits: Vec<&str> = referenced.iter()
+                .copied()
+                .filter(|c| {
+                    route.from.capability.as_deref() == Some(*c)
+                        || route.allowed_capabilities.as_ref().is_some_and(|_| graph.capability_matches(route, c))
+                })
+                .collect();
+            hits.dedup();
+            
+            (!hits.is_empty()).then(|| Finding {
+                severity: Severity::Warning,
+                kind: "disabled_referenced_route".to_string(),
+                subject: route.id.clone(),
+                detail: format!("disabled but referenced by capability_chains: {}", hits.join(", ")),
+            })
+        })
+        .collect()
+}
+
+/// Internal routes starting at a UI, or whose capabilities a UI route also allows
+/// on the same target (and does not deny), checked with the kernel's own route lookup
+fn exposed_internal_routes(graph: &RoutingGraph) -> Vec<Finding> {
+    let ui_routes: Vec<&Route> = graph.model.routes.iter()
+        .filter(|r| r.enabled && !r.internal && r.from.r#type == "ui")
+        .collect();
+    
+    let mut findings = Vec::new();
+    for route in graph.model.routes.iter().filter(|r| r.enabled && r.internal) {
+        if route.from.r#type == "ui" {
+            findings.push(Finding {
+                severity: Severity::Error,
+                kind: "internal_route_exposed".to_string(),
+                subject: route.id.clone(),
+                detail: format!("internal route starts at ui:{}", route.from.id),
+            });
+            continue;
+        }
+        
+        let capabilities = route.allowed_capabilities.iter()
+            .flatten()
+            .filter(|c| !capability_pattern::is_pattern(c));
+        
+        for capability in capabilities {
+            let exposing = ui_routes.iter()
+                .filter(|ui| ui.to.r#type == route.to.r#type && ui.to.id == route.to.id)
+                .find(|ui| {
+                    let (from_type, from_id) = (&ui.from.r#type, &ui.from.id);
+                    let (to_type, to_id) = (&ui.to.r#type, &ui.to.id);
+                    graph.find_routes(from_type, from_id, to_type, to_id, capability).iter().any(|r| r.id == ui.id)
+                        && graph.find_denying_route(from_type, from_id, to_type, to_id, capability).is_none()
+                });
+            
+            if let Some(ui) = exposing {
+                findings.push(Finding {
+                    severity: Severity::Error,
+                    kind: "internal_route_exposed".to_string(),
+                    subject: route.id.clone(),
+                    detail: format!(
+                        "'{}' reachable from ui:{} via route '{}'",
+                        capability, ui.from.id, ui.id
+                    ),
+                });
+            }
+        }
+    }
+    
+    findings
+}
+
+fn route_label(route: &Route) -> String {
+    let capabilities = route.allowed_capabilities.as_ref()
+        .map(|c| c.join(", "))
+        .unwrap_or_else(|| "*".to_string());
+    format!("{}: {}", route.id, capabilities)
+}
+
+/// Sorted node names of every route endpoint
+fn graph_nodes(graph: &RoutingGraph) -> Vec<(String, String)> {
+    let nodes: BTreeSet<(String, String)> = graph.model.routes.iter()
+        .flat_map(|r| [(r.from.r#type.clone(), r.from.id.clone()), (r.to.r#type.clone(), r.to.id.clone())])
+        .collect();
+    nodes.into_iter().collect()
+}
+
+/// Graphviz DOT: UIs are boxes, internal routes bold, disabled routes dashed grey
+pub fn to_dot(graph: &RoutingGraph) -> String {
+    let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut out = String::from("digraph routing {\n    rankdir=LR;\n");
+    
+    for (r#type, id) in graph_nodes(graph) {
+        let shape = if r#type == "ui" { "box" } else { "ellipse" };
+        let _ = writeln!(out, "    \"{}\" [shape={}];", escape(&node_name(&r#type, &id)), shape);
+    }
+    
+    for route in &graph.model.routes {
+        let mut styles = Vec::new();
+        if route.internal {
+            styles.push("bold");
+        }
+        if !route.enabled {
+            styles.push("dashed");
+        }
+        
+        let mut attributes = vec![format!("label=\"{}\"", escape(&route_label(route)))];
+        if !styles.is_empty() {
+            attributes.push(format!("style=\"{}\"", styles.join(",")));
+        }
+        if !route.enabled {
+            attributes.push("color=grey".to_string());
+        }
+        
+        let _ = writeln!(
+            out,
+            "    \"{}\" -> \"{}\" [{}];",
+            escape(&node_name(&route.from.r#type, &route.from.id)),
+            escape(&node_name(&route.to.r#type, &route.to.id)),
+            attributes.join(", ")
+        );
+    }
+    
+    out.push_str("}\n");
+    out
+}
+
+/// Mermaid flowchart: internal routes use thick links, disabled routes dotted links
+pub fn to_mermaid(graph: &RoutingGraph) -> String {
+    let escape = |s: &str| s.replace('"', "#quot;");
+    let nodes = graph_nodes(graph);
+    let index: BTreeMap<String, usize> = nodes.iter()
+        .enumerate()
+        .map(|(i, (t, id))| (node_name(t, id), i))
+        .collect();
+    
+    let mut out = String::from("flowchart LR\n");
+    for (i, (r#type, id)) in nodes.iter().enumerate() {
+        let name = escape(&node_name(r#type, id));
+        if r#type == "ui" {
+            let _ = writeln!(out, "    n{}[\"{}\"]", i, name);
+        } else {
+            let _ = writeln!(out, "    n{}([\"{}\"])", i, name);
+        }
+    }
+    
+    for route in &graph.model.routes {
+        let from = index[&node_name(&route.from.r#type, &route.from.id)];
+        let to = index[&node_name(&route.to.r#type, &route.to.id)];
+        let link = if !route.enabled {
+            "-.->"
+        } else if route.internal {
+            "==>"
+        } else {
+            "-->"
+        };
+        let _ = writeln!(out, "    n{} {}|\"{}\"| n{}", from, link, escape(&route_label(route)), to);
+    }
+    
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const POLICY: &str = r#"
+version: v1.0.0
+policy: deny_by_default
+routes:
+  - id: ui-to-storage
+    from: { type: ui, id: main_ui }
+    to: { type: module, id: storage }
+    allowed_capabilities: ["storage.listings.*", "import.run"]
+    enabled: true
+  - id: storage-to-parser
+    from: { type: module, id: storage }
+    to: { type: module, id: parser }
+    allowed_capabilities: ["parser.parse"]
+    enabled: true
+  - id: import-to-upsert
+    from: { type: module, id: storage, capability: import.run }
+    to: { type: module, id: storage }
+    allowed_capabilities: ["storage.listings.upsert_batch"]
+    enabled: true
+    internal: true
+  - id: import-to-register
+    from: { type: module, id: storage, capability: import.run }
+    to: { type: module, id: storage }
+    allowed_capabilities: ["storage.imports.register"]
+    enabled: false
+    internal: true
+capability_chains:
+  "import.run": ["storage.listings.upsert_batch", "storage.imports.register"]
+  "a.one": ["a.two"]
+  "a.two": ["a.one"]
+"#;
+    
+    fn kinds(analysis: &RoutingAnalysis) -> Vec<(&str, &str)> {
+        analysis.findings.iter().map(|f| (f.kind.as_str(), f.subject.as_str())).collect()
+    }
+    
+    #[test]
+    fn test_analysis_findings() {
+        let graph = RoutingGraph::parse(POLICY).unwrap();
+        let analysis = analyze(&graph);
+        
+        assert!(analysis.has_errors());
+        assert_eq!(kinds(&analysis), vec![
+            ("chain_cycle", "a.one"),
+            ("internal_route_exposed", "import-to-upsert"),
+            ("chain_dead_end", "a.one"),
+            ("chain_dead_end", "a.two"),
+            ("chain_dead_end", "import.run"),
+            ("disabled_referenced_route", "import-to-register"),
+        ]);
+        assert_eq!(analysis.findings[0].detail, "a.one -> a.two -> a.one");
+    }
+    
+    #[test]
+    fn test_deny_on_ui_route_hides_internal_capability() {
+        let policy = POLICY.replace(
+            "allowed_capabilities: [\"storage.listings.*\", \"import.run\"]",
+            "allowed_capabilities: [\"storage.listings.*\", \"import.run\"]\n    deny: [\"storage.listings.upsert_batch\"]",
+        );
+        let graph = RoutingGraph::parse(&policy).unwrap();
+        
+        assert!(!kinds(&analyze(&graph)).contains(&("internal_route_exposed", "import-to-upsert")));
+    }
+    
+    #[test]
+    fn test_reachability_per_ui() {
+        let graph = RoutingGraph::parse(POLICY).unwrap();
+        let rows = analyze(&graph).reachability;
+        
+        let summary: Vec<(&str, usize, String)> = rows.iter()
+            .map(|r| (r.target.as_str(), r.hops, r.via.join(",")))
+            .collect();
+        assert_eq!(summary, vec![
+            ("module:parser", 2, "ui-to-storage,storage-to-parser".to_string()),
+            ("module:storage", 1, "ui-to-storage".to_string()),
+        ]);
+    }
+    
+    #[test]
+    fn test_exports() {
+        let graph = RoutingGraph::parse(POLICY).unwrap();
+        
+        let dot = to_dot(&graph);
+        assert!(dot.starts_with("digraph routing {"));
+        assert!(dot.contains("\"ui:main_ui\" [shape=box];"));
+        assert!(dot.contains("\"module:storage\" -> \"module:storage\" [label=\"import-to-register: storage.imports.register\", style=\"bold,dashed\", color=grey];"));
+        
+        let mermaid = to_mermaid(&graph);
+        assert!(mermaid.starts_with("flowchart LR\n"));
+        assert!(mermaid.contains("n2[\"ui:main_ui\"]"));
+        assert!(mermaid.contains("n1 ==>|\"import-to-upsert: storage.listings.upsert_batch\"| n1"));
+        assert!(mermaid.contains("n1 -.->|"));
+    }
+}
@@ -3,9 +3,10 @@
 
 use super::graph::{Route, RoutingGraph};
 use crate::authz::authorize::AuthContext;
-use std::error::Error;
+use crate::authz::decision::Decision;
 
 /// Authorizes a route: checks if the edge exists in the allowlist and command is allowed
+/// Returns a Decision tracing every candidate route and condition checked
 pub fn authorize_route(
     graph: &RoutingGraph,
     from_type: &str,
@@ -15,61 +16,157 @@ pub fn authorize_route(
     capability: &str,
     auth_context: &AuthContext,
     parent_capability: Option<&str>,
-) -> Result<(), Box<dyn Error>> {
+) -> Decision {
+    let mut decision = Decision::new();
+    let edge = format!("{}:{} -> {}:{}", from_type, from_id, to_type, to_id);
+    
+    // Explicit deny on the edge takes precedence over any allowing route
+    if let Some(route) = graph.find_denying_route(from_type, from_id, to_type, to_id, capability) {
+        let deny_rule = format!("route[{}].deny", route.id);
+        decision.fail(&deny_rule, format!("'{}' denied on {}", capability, edge));
+        return decision.deny(deny_rule, format!(
+            "ROUTING_DENIED: Capability '{}' explicitly denied by route '{}'",
+            capability, route.id
+        ));
+    }
+    
     // Find matching routes
     let matching_routes = graph.find_routes(from_type, from_id, to_type, to_id, capability);
     
     if matching_routes.is_empty() {
-        return Err(format!(
+        decision.fail("route_lookup", format!("no enabled route {} allows '{}'", edge, capability));
+        return decision.deny("route_lookup", format!(
             "ROUTING_DENIED: No route found from {}:{} to {}:{} for capability '{}'",
             from_type, from_id, to_type, to_id, capability
-        ).into());
+        ));
     }
     
+    decision.pass("route_lookup", format!(
+        "{} candidate route(s) {} allow '{}'",
+        matching_routes.len(), edge, capability
+    ));
+    
     // Check if any route allows this request
     for route in matching_routes {
-        if check_route_conditions(route, auth_context).is_ok() {
+        if check_route_conditions(route, auth_context, &mut decision).is_ok() {
             // If this is a chained call, verify the chain is allowed
             if let Some(parent_cap) = parent_capability {
+                let chain_rule = format!("capability_chains[{}]", parent_cap);
                 if !graph.is_chain_allowed(parent_cap, capability) {
-                    return Err(format!(
+                    decision.fail(&chain_rule, format!("'{}' not an allowed child", capability));
+                    return decision.deny(chain_rule, format!(
                         "ROUTING_DENIED: Capability chain '{}' -> '{}' not allowed",
                         parent_cap, capability
-                    ).into());
+                    ));
                 }
+                decision.pass(&chain_rule, format!("'{}' is an allowed child", capability));
             }
             
-            return Ok(());
+            return decision.allow();
         }
     }
     
-    Err("ROUTING_DENIED: Route conditions not satisfied".into())
+    let deciding_rule = decision.checks.last()
+        .map(|c| c.rule.clone())
+        .unwrap_or_else(|| "route_conditions".to_string());
+    decision.deny(deciding_rule, "ROUTING_DENIED: Route conditions not satisfied")
 }
 
-/// Checks if route conditions are satisfied
-fn check_route_conditions(route: &Route, auth_context: &AuthContext) -> Result<(), Box<dyn Error>> {
+/// Checks if route conditions are satisfied, recording each in `decision`
+fn check_route_conditions(route: &Route, auth_context: &AuthContext, decision: &mut Decision) -> Result<(), String> {
+    let rule = format!("route[{}]", route.id);
+    
+    // Tenant-owned nodes are only reachable by their tenant unless the route opts into crossing
+    let tenant_rule = format!("{}.tenant", rule);
+    for owner in [&route.from.tenant, &route.to.tenant].into_iter().flatten() {
+        let actor_tenant = auth_context.tenant_id.as_deref();
+        if actor_tenant == Some(owner.as_str()) {
+            decision.pass(&tenant_rule, format!("actor tenant matches '{}'", owner));
+        } else if route.cross_tenant {
+            decision.pass(&tenant_rule, format!("route allows cross-tenant access to '{}'", owner));
+        } else {
+            decision.fail(&tenant_rule, format!("node owned by tenant '{}', actor tenant {:?}", owner, actor_tenant));
+            return Err(format!(
+                "ROUTING_DENIED: Cross-tenant route '{}' not allowed",
+                route.id
+            ));
+        }
+    }
+    
     if let Some(conditions) = &route.conditions {
         // Check role requirement
         if let Some(allowed_roles) = &conditions.allowed_roles {
+            let roles_rule = format!("{}.allowed_roles", rule);
             if !allowed_roles.contains(&auth_context.role) {
+                decision.fail(&roles_rule, format!("role '{}' not in {:?}", auth_context.role, allowed_roles));
                 return Err(format!(
                     "ROUTING_DENIED: Role '{}' not allowed for route '{}'",
                     auth_context.role, route.id
-                ).into());
+                ));
+            }
+            decision.pass(&roles_rule, format!("role '{}' in {:?}", auth_context.role, allowed_roles));
+        }
+        
+        // Check actor type
+        if let Some(actor_types) = &conditions.actor_types {
+            let type_rule = format!("{}.actor_types", rule);
+            if !actor_types.contains(&auth_context.actor_type) {
+                decision.fail(&type_rule, format!("actor type '{}' not in {:?}", auth_context.actor_type, actor_types));
+                return Err(format!(
+                    "ROUTING_DENIED: Actor type '{}' not allowed for route '{}'",
+                    auth_context.actor_type, route.id
+                ));
+            }
+            decision.pass(&type_rule, format!("actor type '{}' in {:?}", auth_context.actor_type, actor_types));
+        }
+        
+        // Check time windows (any window may match)
+        if let Some(windows) = &conditions.time_windows {
+            let window_rule = format!("{}.time_windows", rule);
+            let now = auth_context.request_time.format("%a %H:%M UTC");
+            if !windows.iter().any(|w| w.contains(auth_context.request_time)) {
+                decision.fail(&window_rule, format!("{} outside all windows", now));
+                return Err(format!(
+                    "ROUTING_DENIED: Route '{}' not available at this time",
+                    route.id
+                ));
+            }
+            decision.pass(&window_rule, format!("{} inside a window", now));
+        }
+        
+        // Check transport-set caller attributes
+        if let Some(peer_uids) = conditions.caller.as_ref().and_then(|c| c.peer_uids.as_ref()) {
+            let uid_rule = format!("{}.caller.peer_uids", rule);
+            match auth_context.caller.peer_uid {
+                Some(uid) if peer_uids.contains(&uid) => {
+                    decision.pass(&uid_rule, format!("peer uid {} in {:?}", uid, peer_uids));
+                }
+                peer_uid => {
+                    decision.fail(&uid_rule, format!("peer uid {:?} not in {:?}", peer_uid, peer_uids));
+                    return Err(format!(
+                        "ROUTING_DENIED: Caller not allowed for route '{}'",
+                        route.id
+                    ));
+                }
             }
         }
         
         // Check scope requirements
         if let Some(required_scopes) = &conditions.required_scopes {
             for required_scope in required_scopes {
+                let scope_rule = format!("{}.required_scopes[{}]", rule, required_scope);
                 if !auth_context.scopes.contains(required_scope) {
+                    decision.fail(&scope_rule, "scope not held by actor");
                     return Err(format!(
                         "ROUTING_DENIED: Missing required scope '{}' for route '{}'",
                         required_scope, route.id
-                    ).into());
+                    ));
                 }
+                decision.pass(&scope_rule, "scope held by actor");
             }
         }
+    } else {
+        decision.pass(&rule, "route has no conditions");
     }
     
     Ok(())
@@ -83,37 +180,48 @@ mod tests {
     
     #[test]
     fn test_authorize_route_success() {
-        let graph = RoutingGraph {
-            routes: vec![
+        let graph = RoutingGraph::new(
+            vec![
                 Route {
                     id: "test-route".to_string(),
                     from: RouteNode {
                         r#type: "ui".to_string(),
                         id: "main_ui".to_string(),
                         capability: None,
+                        tenant: None,
                     },
                     to: RouteNode {
                         r#type: "module".to_string(),
                         id: "storage".to_string(),
                         capability: None,
+                        tenant: None,
                     },
                     allowed_capabilities: Some(vec!["storage.listings.create".to_string()]),
+                    deny: None,
                     conditions: Some(RouteConditions {
                         required_scopes: Some(vec!["storage:write".to_string()]),
                         allowed_roles: Some(vec!["admin".to_string()]),
+                        ..Default::default()
                     }),
                     enabled: true,
                     internal: false,
+                    cross_tenant: false,
+                    canary: None,
+                    metadata: None,
                 }
             ],
-            capability_chains: HashMap::new(),
-        };
+            HashMap::new(),
+        ).unwrap();
         
         let auth_context = AuthContext {
             actor_id: "user-123".to_string(),
             actor_type: "user".to_string(),
             role: "admin".to_string(),
             scopes: vec!["storage:write".to_string()],
+            args: serde_json::Value::Null,
+            request_time: chrono::Utc::now(),
+            tenant_id: None,
+            caller: Default::default(),
         };
         
         let result = authorize_route(
@@ -127,21 +235,23 @@ mod tests {
             None,
         );
         
-        assert!(result.is_ok());
+        assert!(result.is_allowed());
+        assert_eq!(result.deciding_rule.as_deref(), Some("route[test-route].required_scopes[storage:write]"));
     }
     
     #[test]
     fn test_authorize_route_no_route() {
-        let graph = RoutingGraph {
-            routes: vec![],
-            capability_chains: HashMap::new(),
-        };
+        let graph = RoutingGraph::new(vec![], HashMap::new()).unwrap();
         
         let auth_context = AuthContext {
             actor_id: "user-123".to_string(),
             actor_type: "user".to_string(),
             role: "admin".to_string(),
             scopes: vec![],
+            args: serde_json::Value::Null,
+            request_time: chrono::Utc::now(),
+            tenant_id: None,
+            caller: Default::default(),
         };
         
         let result = authorize_route(
@@ -155,6 +265,188 @@ mod tests {
             None,
         );
         
-        assert!(result.is_err());
+        assert!(!result.is_allowed());
+        assert_eq!(result.deciding_rule.as_deref(), Some("route_lookup"));
+    }
+    
+    #[test]
+    fn test_route_deny_overrides_allow() {
+        let node = |t: &str, id: &str| RouteNode { r#type: t.to_string(), id: id.to_string(), capability: None, tenant: None };
+        let graph = RoutingGraph::new(
+            vec![
+                Route {
+                    id: "ui-to-storage".to_string(),
+                    from: node("ui", "main_ui"),
+                    to: node("module", "storage"),
+                    allowed_capabilities: Some(vec!["storage.**".to_string()]),
+                    deny: None,
+                    conditions: None,
+                    enabled: true,
+                    internal: false,
+                    cross_tenant: false,
+                    canary: None,
+                    metadata: None,
+                },
+                Route {
+                    id: "ui-no-delete".to_string(),
+                    from: node("ui", "main_ui"),
+                    to: node("module", "storage"),
+                    allowed_capabilities: Some(vec![]),
+                    deny: Some(vec!["storage.listings.delete".to_string()]),
+                    conditions: None,
+                    enabled: true,
+                    internal: false,
+                    cross_tenant: false,
+                    canary: None,
+                    metadata: None,
+                },
+            ],
+            HashMap::new(),
+        ).unwrap();
+        
+        let auth_context = AuthContext {
+            actor_id: "user-123".to_string(),
+            actor_type: "user".to_string(),
+            role: "admin".to_string(),
+            scopes: vec![],
+            args: serde_json::Value::Null,
+            request_time: chrono::Utc::now(),
+            tenant_id: None,
+            caller: Default::default(),
+        };
+        
+        let check = |capability: &str| authorize_route(
+            &graph, "ui", "main_ui", "module", "storage", capability, &auth_context, None,
+        );
+        
+        assert!(check("storage.listings.create").is_allowed());
+        let denied = check("storage.listings.delete");
+        assert!(!denied.is_allowed());
+        assert_eq!(denied.deciding_rule.as_deref(), Some("route[ui-no-delete].deny"));
+    }
+    
+    #[test]
+    fn test_cross_tenant_route_denied_by_default() {
+        let node = |t: &str, id: &str, tenant: Option<&str>| RouteNode {
+            r#type: t.to_string(),
+            id: id.to_string(),
+            capability: None,
+            tenant: tenant.map(|t| t.to_string()),
+        };
+        let route = |cross_tenant: bool| Route {
+            id: "ui-to-dealer-a-storage".to_string(),
+            from: node("ui", "main_ui", None),
+            to: node("module", "storage", Some("dealer-a")),
+            allowed_capabilities: None,
+            deny: None,
+            conditions: None,
+            enabled: true,
+            internal: false,
+            cross_tenant,
+            canary: None,
+            metadata: None,
+        };
+        let context = |tenant_id: Option<&str>| AuthContext {
+            actor_id: "user-123".to_string(),
+            actor_type: "user".to_string(),
+            role: "admin".to_string(),
+            scopes: vec![],
+            args: serde_json::Value::Null,
+            request_time: chrono::Utc::now(),
+            tenant_id: tenant_id.map(|t| t.to_string()),
+            caller: Default::default(),
+        };
+        let check = |cross_tenant: bool, tenant_id: Option<&str>| {
+            let graph = RoutingGraph::new(vec![route(cross_tenant)], HashMap::new()).unwrap();
+            authorize_route(&graph, "ui", "main_ui", "module", "storage", "storage.listings.read", &context(tenant_id), None)
+        };
+        
+        assert!(check(false, Some("dealer-a")).is_allowed());
+        
+        let denied = check(false, Some("dealer-b"));
+        assert!(!denied.is_allowed());
+        assert_eq!(denied.deciding_rule.as_deref(), Some("route[ui-to-dealer-a-storage].tenant"));
+        assert!(!check(false, None).is_allowed());
+        
+        assert!(check(true, Some("dealer-b")).is_allowed());
+    }
+    
+    #[test]
+    fn test_actor_type_time_window_and_caller_conditions() {
+        use super::super::graph::{CallerConditions, TimeWindow};
+        use crate::authz::authorize::CallerAttributes;
+        use chrono::TimeZone;
+        
+        let graph = RoutingGraph::new(
+            vec![Route {
+                id: "service-imports".to_string(),
+                from: RouteNode { r#type: "ui".to_string(), id: "main_ui".to_string(), capability: None, tenant: None },
+                to: RouteNode { r#type: "module".to_string(), id: "import".to_string(), capability: None, tenant: None },
+                allowed_capabilities: Some(vec!["import.run".to_string()]),
+                deny: None,
+                conditions: Some(RouteConditions {
+                    actor_types: Some(vec!["service".to_string()]),
+                    time_windows: Some(vec![TimeWindow {
+                        days: Some(vec!["mon".to_string(), "tue".to_string(), "wed".to_string(), "thu".to_string(), "fri".to_string()]),
+                        start: "09:00".to_string(),
+                        end: "18:00".to_string(),
+                    }]),
+                    caller: Some(CallerConditions { peer_uids: Some(vec![1000]) }),
+                    ..Default::default()
+                }),
+                enabled: true,
+                internal: false,
+                cross_tenant: false,
+                canary: None,
+                metadata: None,
+            }],
+            HashMap::new(),
+        ).unwrap();
+        
+        let monday_10 = chrono::Utc.with_ymd_and_hms(2026, 1, 12, 10, 0, 0).unwrap();
+        let check = |actor_type: &str, request_time, peer_uid| {
+            let context = AuthContext {
+                actor_id: "svc-importer".to_string(),
+                actor_type: actor_type.to_string(),
+                role: "admin".to_string(),
+                scopes: vec![],
+                args: serde_json::Value::Null,
+                request_time,
+                tenant_id: None,
+                caller: CallerAttributes { peer_uid },
+            };
+            authorize_route(&graph, "ui", "main_ui", "module", "import", "import.run", &context, None)
+        };
+        
+        assert!(check("service", monday_10, Some(1000)).is_allowed());
+        assert_eq!(
+            check("user", monday_10, Some(1000)).deciding_rule.as_deref(),
+            Some("route[service-imports].actor_types")
+        );
+        assert_eq!(
+            check("service", monday_10 + chrono::Duration::days(5), Some(1000)).deciding_rule.as_deref(),
+            Some("route[service-imports].time_windows")
+        );
+        assert_eq!(
+            check("service", monday_10, Some(0)).deciding_rule.as_deref(),
+            Some("route[service-imports].caller.peer_uids")
+        );
+        assert!(!check("service", monday_10, None).is_allowed());
+    }
+    
+    #[test]
+    fn test_invalid_route_conditions_rejected_at_load() {
+        let result = RoutingGraph::parse(r#"
+version: v1.0.0
+policy: deny_by_default
+routes:
+  - id: bad
+    from: { type: ui, id: main_ui }
+    to: { type: module, id: storage }
+    conditions:
+      actor_types: [robot]
+    enabled: true
+"#);
+        assert!(result.err().unwrap().to_string().contains("unknown actor type 'robot'"));
     }
 }
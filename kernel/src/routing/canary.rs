@@ -0,0 +1,148 @@
+// Canary Routing
+// Picks the stable or candidate module version for a route with a canary, and compares shadow results
+//
+// The choice is sticky: an actor's bucket is a hash of the route ID and actor ID, so the
+// same actor keeps getting the same version, and raising `weight_percent` only adds actors.
+
+use super::model::{Canary, CanaryMode};
+use crate::primitives::hash::hash_string;
+use serde_json::Value;
+
+/// Versions a request runs on
+#[derive(Debug, Clone, PartialEq)]
+pub struct CanaryChoice {
+    /// Version that serves the request
+    pub serve: String,
+    /// Version the request is mirrored to; its result is discarded
+    pub shadow: Option<String>,
+    /// Why the actor got the candidate (or not)
+    pub reason: String,
+}
+
+/// Chooses the version for an actor on the canary's route
+pub fn choose(route_id: &str, canary: &Canary, actor_id: &str, role: &str) -> CanaryChoice {
+    let (candidate, reason) = if canary.actors.iter().any(|a| a == actor_id) {
+        (true, format!("actor '{}' pinned to candidate", actor_id))
+    } else if canary.roles.iter().any(|r| r == role) {
+        (true, format!("role '{}' pinned to candidate", role))
+    } else {
+        let bucket = bucket(route_id, actor_id);
+        let candidate = bucket < canary.weight_percent;
+        let comparison = if candidate { "<" } else { ">=" };
+        (candidate, format!("bucket {} {} weight {}%", bucket, comparison, canary.weight_percent))
+    };
+    
+    match (canary.mode, candidate) {
+        (CanaryMode::Split, true) => CanaryChoice { serve: canary.candidate.clone(), shadow: None, reason },
+        (CanaryMode::Shadow, true) => CanaryChoice {
+            serve: canary.stable.clone(),
+            shadow: Some(canary.candidate.clone()),
+            reason,
+        },
+        (_, false) => CanaryChoice { serve: canary.stable.clone(), shadow: None, reason },
+    }
+}
+
+/// Sticky bucket in 0..100 for an actor on a route
+pub fn bucket(route_id: &str, actor_id: &str) -> u32 {
+    let hash = hash_string(&format!("{}:{}", route_id, actor_id));
+    u32::from_str_radix(&hash[..8], 16).unwrap_or(0) % 100
+}
+
+/// Paths at which two results differ ("data.items[2].price"), at most `limit` of them
+pub fn differences(primary: &Value, shadow: &Value, limit: usize) -> Vec<String> {
+    let mut found = Vec::new();
+    collect_differences("", primary, shadow, limit, &mut found);
+    found
+}
+
+fn collect_differences(path: &str, primary: &Value, shadow: &Value, limit: usize, found: &mut Vec<String>) {
+    if found.len() >= limit {
+        return;
+    }
+    
+    match (primary, shadow) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys().filter(|k| !a.contains_key(*k))).collect();
+            keys.sort();
+            for key in keys {
+                let child = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                collect_differences(&child, a.get(key).unwrap_or(&Value::Null), b.get(key).unwrap_or(&Value::Null), limit, found);
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for index in 0..a.len().max(b.len()) {
+                let child = format!("{}[{}]", path, index);
+                collect_differences(&child, a.get(index).unwrap_or(&Value::Null), b.get(index).unwrap_or(&Value::Null), limit, found);
+            }
+        }
+        _ if primary != shadow => found.push(if path.is_empty() { "$".to_string() } else { path.to_string() }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    fn canary(weight_percent: u32, mode: CanaryMode) -> Canary {
+        Canary {
+            stable: "v1.4.0".to_string(),
+            candidate: "v2.0.0".to_string(),
+            weight_percent,
+            actors: vec!["user-qa-001".to_string()],
+            roles: vec!["tester".to_string()],
+            mode,
+        }
+    }
+    
+    #[test]
+    fn test_pinned_actors_and_roles() {
+        let split = canary(0, CanaryMode::Split);
+        assert_eq!(choose("ui-to-pricing", &split, "user-qa-001", "viewer").serve, "v2.0.0");
+        assert_eq!(choose("ui-to-pricing", &split, "user-123", "tester").serve, "v2.0.0");
+        assert_eq!(choose("ui-to-pricing", &split, "user-123", "viewer").serve, "v1.4.0");
+    }
+    
+    #[test]
+    fn test_weight_is_sticky_and_monotonic() {
+        let actors: Vec<String> = (0..1000).map(|i| format!("user-{}", i)).collect();
+        let on_candidate = |weight: u32| -> Vec<&String> {
+            actors.iter()
+                .filter(|actor| choose("ui-to-pricing", &canary(weight, CanaryMode::Split), actor, "viewer").serve == "v2.0.0")
+                .collect()
+        };
+        
+        let ten = on_candidate(10);
+        assert_eq!(ten, on_candidate(10));
+        assert!((50..150).contains(&ten.len()), "{} of 1000 on candidate", ten.len());
+        
+        let fifty = on_candidate(50);
+        assert!(ten.iter().all(|actor| fifty.contains(actor)));
+        assert_eq!(on_candidate(100).len(), 1000);
+    }
+    
+    #[test]
+    fn test_shadow_serves_stable() {
+        let shadow = canary(0, CanaryMode::Shadow);
+        let choice = choose("ui-to-pricing", &shadow, "user-qa-001", "viewer");
+        assert_eq!(choice.serve, "v1.4.0");
+        assert_eq!(choice.shadow.as_deref(), Some("v2.0.0"));
+        
+        assert_eq!(choose("ui-to-pricing", &shadow, "user-123", "viewer").shadow, None);
+    }
+    
+    #[test]
+    fn test_differences() {
+        let primary = json!({"data": {"price": 100, "items": [1, 2], "currency": "EUR"}});
+        let shadow = json!({"data": {"price": 105, "items": [1, 2, 3], "currency": "EUR", "tax": 5}});
+        
+        assert_eq!(
+            differences(&primary, &shadow, 10),
+            vec!["data.items[2]", "data.price", "data.tax"]
+        );
+        assert_eq!(differences(&primary, &shadow, 1).len(), 1);
+        assert!(differences(&primary, &primary, 10).is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+// Capability Versions
+// Semver selection among the versions of a capability a module serves side by side
+//
+// Versions are written with or without a leading `v` ("v1.2.0", "1.2.0").
+// Constraints use Cargo syntax: "v1.2.0" / "^1.2" (compatible), "~1.2" (patch-level),
+// "=1.2.0" (exact), ">=1.0.0, <2.0.0" (range). Without a constraint the latest
+// stable version is chosen; pre-releases are only chosen when a constraint names them.
+
+use semver::{Version, VersionReq};
+use std::error::Error;
+
+/// Parses a version, with or without a leading `v`
+pub fn parse_version(value: &str) -> Result<Version, Box<dyn Error>> {
+    Version::parse(value.trim().trim_start_matches('v'))
+        .map_err(|e| format!("Invalid version '{}': {}", value, e).into())
+}
+
+/// Parses a version constraint; a `v` directly before a number is ignored
+pub fn parse_constraint(value: &str) -> Result<VersionReq, Box<dyn Error>> {
+    let mut normalized = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        let before_digit = chars.peek().is_some_and(|next| next.is_ascii_digit());
+        if c == 'v' && before_digit {
+            continue;
+        }
+        normalized.push(c);
+    }
+    
+    VersionReq::parse(&normalized)
+        .map_err(|e| format!("Invalid version constraint '{}': {}", value, e).into())
+}
+
+/// Canonical "vMAJOR.MINOR.PATCH[-pre]" form used in envelopes
+pub fn format_version(version: &Version) -> String {
+    format!("v{}", version)
+}
+
+/// Highest version matching the constraint, or the highest stable version without one
+pub fn select<T>(candidates: Vec<(Version, T)>, constraint: Option<&VersionReq>) -> Option<(Version, T)> {
+    candidates.into_iter()
+        .filter(|(version, _)| match constraint {
+            Some(constraint) => constraint.matches(version),
+            None => version.pre.is_empty(),
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn candidates() -> Vec<(Version, &'static str)> {
+        ["v1.0.0", "v1.2.3", "v1.3.0", "v2.0.0-beta.1", "v2.0.0-beta.2"]
+            .iter()
+            .map(|v| (parse_version(v).unwrap(), *v))
+            .collect()
+    }
+    
+    fn pick(constraint: Option<&str>) -> Option<&'static str> {
+        let constraint = constraint.map(|c| parse_constraint(c).unwrap());
+        select(candidates(), constraint.as_ref()).map(|(_, v)| v)
+    }
+    
+    #[test]
+    fn test_default_is_latest_stable() {
+        assert_eq!(pick(None), Some("v1.3.0"));
+    }
+    
+    #[test]
+    fn test_constraints() {
+        assert_eq!(pick(Some("v1.0.0")), Some("v1.3.0"));
+        assert_eq!(pick(Some("=v1.2.3")), Some("v1.2.3"));
+        assert_eq!(pick(Some("~1.2")), Some("v1.2.3"));
+        assert_eq!(pick(Some(">=1.0.0, <1.3.0")), Some("v1.2.3"));
+        assert_eq!(pick(Some("^2.0.0-beta.1")), Some("v2.0.0-beta.2"));
+        assert_eq!(pick(Some("^3")), None);
+    }
+    
+    #[test]
+    fn test_parse() {
+        assert_eq!(format_version(&parse_version("v1.2.0").unwrap()), "v1.2.0");
+        assert_eq!(format_version(&parse_version("1.2.0-rc.1").unwrap()), "v1.2.0-rc.1");
+        assert!(parse_version("v1.2").is_err());
+        assert!(parse_constraint("latest").is_err());
+    }
+}
@@ -1,47 +1,81 @@
 // Routing Graph
-// Loads and manages routing allowlist from policy
+// Compiles the routing model into the allowlist index authorize_route consults
 
-use serde::Deserialize;
-use std::collections::HashMap;
+use crate::config::module_registry::ModuleRegistry;
+use crate::primitives::capability_pattern;
+use crate::primitives::capability_trie::CapabilityTrie;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error;
 use std::fs;
 
-#[derive(Debug, Clone, Deserialize)]
-pub struct Route {
-    pub id: String,
-    pub from: RouteNode,
-    pub to: RouteNode,
-    pub allowed_capabilities: Option<Vec<String>>,
-    pub conditions: Option<RouteConditions>,
-    pub enabled: bool,
-    #[serde(default)]
-    pub internal: bool,
-}
+pub use super::model::{CallerConditions, Canary, CanaryMode, Route, RouteConditions, RouteNode, RoutingModel, TimeWindow};
 
-#[derive(Debug, Clone, Deserialize)]
-pub struct RouteNode {
-    pub r#type: String,
-    pub id: String,
-    pub capability: Option<String>,
+/// A validated RoutingModel plus its lookup index; the only input to authorize_route
+pub struct RoutingGraph {
+    pub model: RoutingModel,
+    index: RouteIndex,
 }
 
-#[derive(Debug, Clone, Deserialize)]
-pub struct RouteConditions {
-    pub required_scopes: Option<Vec<String>>,
-    pub allowed_roles: Option<Vec<String>>,
+/// (from_type, from_id, to_type, to_id)
+type EdgeKey = (String, String, String, String);
+
+/// Routes and chains compiled once at load time
+#[derive(Default)]
+struct RouteIndex {
+    edges: HashMap<EdgeKey, EdgeRoutes>,
+    chains: HashMap<String, HashSet<String>>,
 }
 
-#[derive(Debug, Deserialize)]
-struct RoutingPolicy {
-    version: String,
-    policy: String,
-    routes: Vec<Route>,
-    capability_chains: Option<HashMap<String, Vec<String>>>,
+/// Enabled routes sharing one edge, as positions in `RoutingModel.routes`
+#[derive(Default)]
+struct EdgeRoutes {
+    /// Routes restricted by `allowed_capabilities`
+    restricted: CapabilityTrie<usize>,
+    /// Routes without `allowed_capabilities` (match any capability)
+    unrestricted: Vec<usize>,
+    /// Routes by their `deny` patterns
+    denied: CapabilityTrie<usize>,
 }
 
-pub struct RoutingGraph {
-    pub routes: Vec<Route>,
-    pub capability_chains: HashMap<String, Vec<String>>,
+impl RouteIndex {
+    fn compile(routes: &[Route], capability_chains: &BTreeMap<String, Vec<String>>) -> Result<Self, Box<dyn Error>> {
+        let mut edges: HashMap<EdgeKey, EdgeRoutes> = HashMap::new();
+        
+        for (position, route) in routes.iter().enumerate() {
+            if !route.enabled {
+                continue;
+            }
+            
+            let key = (
+                route.from.r#type.clone(),
+                route.from.id.clone(),
+                route.to.r#type.clone(),
+                route.to.id.clone(),
+            );
+            let entry = edges.entry(key).or_default();
+            
+            match &route.allowed_capabilities {
+                Some(allowed) => {
+                    for pattern in allowed {
+                        entry.restricted.insert(pattern, position)
+                            .map_err(|e| format!("Route '{}': {}", route.id, e))?;
+                    }
+                }
+                None => entry.unrestricted.push(position),
+            }
+            
+            for pattern in route.deny.iter().flatten() {
+                entry.denied.insert(pattern, position)
+                    .map_err(|e| format!("Route '{}' deny: {}", route.id, e))?;
+            }
+        }
+        
+        let chains = capability_chains.iter()
+            .map(|(parent, children)| (parent.clone(), children.iter().cloned().collect()))
+            .collect();
+        
+        Ok(RouteIndex { edges, chains })
+    }
 }
 
 impl RoutingGraph {
@@ -51,21 +85,36 @@ impl RoutingGraph {
         let content = fs::read_to_string(policy_path)
             .map_err(|e| format!("Failed to read routing policy: {}", e))?;
         
-        let policy: RoutingPolicy = serde_yaml::from_str(&content)
-            .map_err(|e| format!("Failed to parse routing policy: {}", e))?;
-        
-        // Verify deny-by-default policy
-        if policy.policy != "deny_by_default" {
-            return Err("Routing policy must be deny_by_default".into());
-        }
+        RoutingGraph::parse(&content)
+    }
+    
+    /// Parses routing policy in either source format and compiles the graph
+    pub fn parse(content: &str) -> Result<Self, Box<dyn Error>> {
+        RoutingGraph::from_model(RoutingModel::parse(content)?)
+    }
+    
+    /// Like `parse`, with module nodes rewritten to canonical IDs; unknown modules fail
+    pub fn parse_resolved(content: &str, modules: &ModuleRegistry) -> Result<Self, Box<dyn Error>> {
+        let mut model = RoutingModel::parse(content)?;
+        model.resolve_modules(modules)?;
+        RoutingGraph::from_model(model)
+    }
+    
+    /// Builds a graph from routes and chains under a deny-by-default model
+    pub fn new(routes: Vec<Route>, capability_chains: impl IntoIterator<Item = (String, Vec<String>)>) -> Result<Self, Box<dyn Error>> {
+        RoutingGraph::from_model(RoutingModel::new(routes, capability_chains))
+    }
+    
+    /// Validates the model and compiles its route and chain indexes
+    pub fn from_model(model: RoutingModel) -> Result<Self, Box<dyn Error>> {
+        model.validate()?;
+        let index = RouteIndex::compile(&model.routes, &model.capability_chains)?;
         
-        Ok(RoutingGraph {
-            routes: policy.routes,
-            capability_chains: policy.capability_chains.unwrap_or_default(),
-        })
+        Ok(RoutingGraph { model, index })
     }
     
-    /// Finds routes that match the given from/to criteria
+    /// Finds routes that match the given from/to criteria, in policy order
+    /// Looks up the edge by hash and the capability in that edge's trie
     pub fn find_routes(
         &self,
         from_type: &str,
@@ -74,34 +123,68 @@ impl RoutingGraph {
         to_id: &str,
         capability: &str,
     ) -> Vec<&Route> {
-        self.routes.iter()
-            .filter(|r| {
-                r.enabled &&
-                r.from.r#type == from_type &&
-                r.from.id == from_id &&
-                r.to.r#type == to_type &&
-                r.to.id == to_id &&
-                self.capability_matches(r, capability)
-            })
-            .collect()
+        let key = (
+            from_type.to_string(),
+            from_id.to_string(),
+            to_type.to_string(),
+            to_id.to_string(),
+        );
+        
+        let edge = match self.index.edges.get(&key) {
+            Some(edge) => edge,
+            None => return Vec::new(),
+        };
+        
+        let mut positions: Vec<usize> = edge.restricted.matches(capability)
+            .into_iter()
+            .copied()
+            .chain(edge.unrestricted.iter().copied())
+            .collect();
+        positions.sort_unstable();
+        positions.dedup();
+        
+        positions.into_iter().map(|p| &self.model.routes[p]).collect()
     }
     
-    /// Checks if a capability matches a route's allowed capabilities
-    fn capability_matches(&self, route: &Route, capability: &str) -> bool {
+    /// The first route (in policy order) carrying the capability on the edge that has a canary
+    pub fn find_canary(
+        &self,
+        from_type: &str,
+        from_id: &str,
+        to_type: &str,
+        to_id: &str,
+        capability: &str,
+    ) -> Option<&Route> {
+        self.find_routes(from_type, from_id, to_type, to_id, capability)
+            .into_iter()
+            .find(|route| route.canary.is_some())
+    }
+    
+    /// Finds the first enabled route on the edge whose `deny` matches the capability
+    pub fn find_denying_route(
+        &self,
+        from_type: &str,
+        from_id: &str,
+        to_type: &str,
+        to_id: &str,
+        capability: &str,
+    ) -> Option<&Route> {
+        let key = (
+            from_type.to_string(),
+            from_id.to_string(),
+            to_type.to_string(),
+            to_id.to_string(),
+        );
+        
+        let edge = self.index.edges.get(&key)?;
+        let position = edge.denied.matches(capability).into_iter().min()?;
+        Some(&self.model.routes[*position])
+    }
+    
+    /// Checks if a capability matches a route's allowed capabilities (single route, no index)
+    pub fn capability_matches(&self, route: &Route, capability: &str) -> bool {
         if let Some(allowed) = &route.allowed_capabilities {
-            for pattern in allowed {
-                if pattern == capability {
-                    return true;
-                }
-                // Wildcard matching: "storage.listings.*" matches "storage.listings.create"
-                if pattern.ends_with("*") {
-                    let prefix = &pattern[..pattern.len() - 1];
-                    if capability.starts_with(prefix) {
-                        return true;
-                    }
-                }
-            }
-            false
+            allowed.iter().any(|pattern| capability_pattern::pattern_matches(pattern, capability))
         } else {
             // No restrictions = matches any capability
             true
@@ -110,11 +193,9 @@ impl RoutingGraph {
     
     /// Checks if a capability chain is allowed
     pub fn is_chain_allowed(&self, parent_capability: &str, child_capability: &str) -> bool {
-        if let Some(allowed_children) = self.capability_chains.get(parent_capability) {
-            allowed_children.contains(&child_capability.to_string())
-        } else {
-            false
-        }
+        self.index.chains.get(parent_capability)
+            .map(|children| children.contains(child_capability))
+            .unwrap_or(false)
     }
 }
 
@@ -124,31 +205,74 @@ mod tests {
     
     #[test]
     fn test_capability_pattern_matching() {
-        let graph = RoutingGraph {
-            routes: vec![
+        let graph = RoutingGraph::new(
+            vec![
                 Route {
                     id: "test-route".to_string(),
                     from: RouteNode {
                         r#type: "ui".to_string(),
                         id: "main_ui".to_string(),
                         capability: None,
+                        tenant: None,
                     },
                     to: RouteNode {
                         r#type: "module".to_string(),
                         id: "storage".to_string(),
                         capability: None,
+                        tenant: None,
                     },
                     allowed_capabilities: Some(vec!["storage.listings.*".to_string()]),
+                    deny: None,
                     conditions: None,
                     enabled: true,
                     internal: false,
+                    cross_tenant: false,
+                    canary: None,
+                    metadata: None,
                 }
             ],
-            capability_chains: HashMap::new(),
+            HashMap::new(),
+        ).unwrap();
+        
+        assert!(graph.capability_matches(&graph.model.routes[0], "storage.listings.create"));
+        assert!(graph.capability_matches(&graph.model.routes[0], "storage.listings.get"));
+        assert!(!graph.capability_matches(&graph.model.routes[0], "storage.imports.register"));
+    }
+    
+    #[test]
+    fn test_find_routes_uses_index() {
+        let route = |id: &str, to: &str, allowed: Option<Vec<&str>>, enabled: bool| Route {
+            id: id.to_string(),
+            from: RouteNode { r#type: "ui".to_string(), id: "main_ui".to_string(), capability: None, tenant: None },
+            to: RouteNode { r#type: "module".to_string(), id: to.to_string(), capability: None, tenant: None },
+            allowed_capabilities: allowed.map(|a| a.iter().map(|s| s.to_string()).collect()),
+            deny: None,
+            conditions: None,
+            enabled,
+            internal: false,
+            cross_tenant: false,
+            canary: None,
+            metadata: None,
         };
         
-        assert!(graph.capability_matches(&graph.routes[0], "storage.listings.create"));
-        assert!(graph.capability_matches(&graph.routes[0], "storage.listings.get"));
-        assert!(!graph.capability_matches(&graph.routes[0], "storage.imports.register"));
+        let mut chains = HashMap::new();
+        chains.insert("import.run".to_string(), vec!["storage.imports.register".to_string()]);
+        
+        let graph = RoutingGraph::new(vec![
+            route("a", "storage", Some(vec!["storage.listings.*"]), true),
+            route("b", "storage", None, true),
+            route("c", "storage", Some(vec!["storage.listings.create"]), false),
+            route("d", "pricing", Some(vec!["pricing.*"]), true),
+        ], chains).unwrap();
+        
+        let ids = |routes: Vec<&Route>| routes.iter().map(|r| r.id.clone()).collect::<Vec<_>>();
+        
+        assert_eq!(ids(graph.find_routes("ui", "main_ui", "module", "storage", "storage.listings.create")), vec!["a", "b"]);
+        assert_eq!(ids(graph.find_routes("ui", "main_ui", "module", "storage", "import.run")), vec!["b"]);
+        assert!(graph.find_routes("ui", "other_ui", "module", "storage", "storage.listings.create").is_empty());
+        assert!(graph.find_routes("ui", "main_ui", "module", "pricing", "storage.listings.create").is_empty());
+        
+        assert!(graph.is_chain_allowed("import.run", "storage.imports.register"));
+        assert!(!graph.is_chain_allowed("import.run", "storage.listings.delete"));
     }
 }
@@ -2,5 +2,9 @@
 // Request routing and allowlist management
 
 pub mod graph;
+pub mod model;
 pub mod resolve_endpoint;
+pub mod capability_versions;
+pub mod canary;
 pub mod authorize_route;
+pub mod analysis;
@@ -0,0 +1,644 @@
+// Routing Model
+// The one typed routing policy; every loader produces it and RoutingGraph is compiled from it
+//
+// Two source formats describe the same model and convert into each other without loss:
+//   routes  nested `from`/`to` nodes, as in system/policy/routing.yaml
+//   edges   flat `from_type`/`from_id`/`to_type`/`to_id` records under `edges:`
+// Whatever the source, the model is checked against shared/contracts/v1/routing.schema.yaml.
+// Unknown keys are refused rather than dropped, so nothing is lost silently in a conversion.
+
+use crate::config::module_registry::ModuleRegistry;
+use crate::primitives::capability_pattern;
+use crate::routing::capability_versions;
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::sync::OnceLock;
+
+const ROUTING_SCHEMA: &str = include_str!("../../../shared/contracts/v1/routing.schema.yaml");
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Route {
+    pub id: String,
+    pub from: RouteNode,
+    pub to: RouteNode,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_capabilities: Option<Vec<String>>,
+    /// Capability patterns refused on this edge, even if another route on it allows them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deny: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conditions: Option<RouteConditions>,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub internal: bool,
+    /// Allows the route between nodes owned by different tenants
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub cross_tenant: bool,
+    /// Splits the route's traffic between two versions of the target module
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canary: Option<Canary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<RouteMetadata>,
+}
+
+/// Descriptive route fields; carried through conversions, not enforced by the graph
+/// Whether the route is in use is `Route::enabled`, so there is no `metadata.enabled`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteNode {
+    pub r#type: String,
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capability: Option<String>,
+    /// Tenant that owns this node; None means shared across tenants
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteConditions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_scopes: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_roles: Option<Vec<String>>,
+    /// Actor types allowed on the route (user, service, system)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor_types: Option<Vec<String>>,
+    /// Route is usable only inside one of these windows (UTC)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_windows: Option<Vec<TimeWindow>>,
+    /// Requirements on attributes set by the transport
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caller: Option<CallerConditions>,
+}
+
+/// Daily time window; `days` refers to the day the window opens
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimeWindow {
+    /// Weekdays (mon..sun); every day if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub days: Option<Vec<String>>,
+    /// "HH:MM", inclusive
+    pub start: String,
+    /// "HH:MM", exclusive; earlier than `start` means the window runs past midnight
+    pub end: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CallerConditions {
+    /// Local socket peer uids allowed on the route
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peer_uids: Option<Vec<u32>>,
+}
+
+/// Traffic split between the stable and a candidate version of a route's target module
+/// Actors named in `actors` or holding one of `roles` always get the candidate;
+/// of the rest, a sticky `weight_percent` share (by actor ID) does
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Canary {
+    /// Version everyone else runs ("v1.4.0")
+    pub stable: String,
+    pub candidate: String,
+    #[serde(default)]
+    pub weight_percent: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actors: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    #[serde(default)]
+    pub mode: CanaryMode,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CanaryMode {
+    /// Candidate actors are served by the candidate
+    #[default]
+    Split,
+    /// Everyone is served by stable; candidate actors' commands are also run on the
+    /// candidate, whose result is discarded and compared
+    Shadow,
+}
+
+/// Global restrictions; carried through conversions, not enforced by the graph
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Restrictions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_chain_depth: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked_capabilities: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_networks: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoutingFormat {
+    /// Nested `routes:` (system/policy/routing.yaml)
+    Routes,
+    /// Flat `edges:`
+    Edges,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoutingModel {
+    pub version: String,
+    pub policy: String,
+    pub routes: Vec<Route>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub capability_chains: BTreeMap<String, Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restrictions: Option<Restrictions>,
+}
+
+/// Source document in the edges format
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EdgesDocument {
+    version: String,
+    #[serde(default = "deny_by_default")]
+    policy: String,
+    edges: Vec<Edge>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    capability_chains: BTreeMap<String, Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    restrictions: Option<Restrictions>,
+}
+
+/// A route with its endpoints flattened; `id` is derived from the endpoints if omitted
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Edge {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    from_type: String,
+    from_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    from_capability: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    from_tenant: Option<String>,
+    to_type: String,
+    to_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    to_capability: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    to_tenant: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    allowed_capabilities: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deny: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    conditions: Option<RouteConditions>,
+    #[serde(default = "enabled_by_default")]
+    enabled: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    internal: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    cross_tenant: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    canary: Option<Canary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metadata: Option<RouteMetadata>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+fn deny_by_default() -> String {
+    "deny_by_default".to_string()
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+impl From<&Route> for Edge {
+    fn from(route: &Route) -> Self {
+        Edge {
+            id: Some(route.id.clone()),
+            from_type: route.from.r#type.clone(),
+            from_id: route.from.id.clone(),
+            from_capability: route.from.capability.clone(),
+            from_tenant: route.from.tenant.clone(),
+            to_type: route.to.r#type.clone(),
+            to_id: route.to.id.clone(),
+            to_capability: route.to.capability.clone(),
+            to_tenant: route.to.tenant.clone(),
+            allowed_capabilities: route.allowed_capabilities.clone(),
+            deny: route.deny.clone(),
+            conditions: route.conditions.clone(),
+            enabled: route.enabled,
+            internal: route.internal,
+            cross_tenant: route.cross_tenant,
+            canary: route.canary.clone(),
+            metadata: route.metadata.clone(),
+        }
+    }
+}
+
+impl From<Edge> for Route {
+    fn from(edge: Edge) -> Self {
+        let id = edge.id.unwrap_or_else(|| {
+            format!("{}-to-{}", edge.from_id, edge.to_id)
+                .to_lowercase()
+                .replace(|c: char| !c.is_ascii_alphanumeric(), "-")
+        });
+        
+        Route {
+            id,
+            from: RouteNode {
+                r#type: edge.from_type,
+                id: edge.from_id,
+                capability: edge.from_capability,
+                tenant: edge.from_tenant,
+            },
+            to: RouteNode {
+                r#type: edge.to_type,
+                id: edge.to_id,
+                capability: edge.to_capability,
+                tenant: edge.to_tenant,
+            },
+            allowed_capabilities: edge.allowed_capabilities,
+            deny: edge.deny,
+            conditions: edge.conditions,
+            enabled: edge.enabled,
+            internal: edge.internal,
+            cross_tenant: edge.cross_tenant,
+            canary: edge.canary,
+            metadata: edge.metadata,
+        }
+    }
+}
+
+impl RoutingFormat {
+    /// Edges if the document has a top-level `edges` key, routes otherwise
+    pub fn detect(content: &str) -> Result<Self, Box<dyn Error>> {
+        let document: serde_yaml::Value = serde_yaml::from_str(content)
+            .map_err(|e| format!("Failed to parse routing policy: {}", e))?;
+        
+        Ok(if document.get("edges").is_some() {
+            RoutingFormat::Edges
+        } else {
+            RoutingFormat::Routes
+        })
+    }
+}
+
+impl RoutingModel {
+    /// Deny-by-default model at the current contract version
+    pub fn new(routes: Vec<Route>, capability_chains: impl IntoIterator<Item = (String, Vec<String>)>) -> Self {
+        RoutingModel {
+            version: "v1.0.0".to_string(),
+            policy: deny_by_default(),
+            routes,
+            capability_chains: capability_chains.into_iter().collect(),
+            restrictions: None,
+        }
+    }
+    
+    /// Parses either source format and validates the result
+    pub fn parse(content: &str) -> Result<Self, Box<dyn Error>> {
+        let format = RoutingFormat::detect(content)?;
+        RoutingModel::parse_as(content, format)
+    }
+    
+    /// Parses one source format and validates the result
+    pub fn parse_as(content: &str, format: RoutingFormat) -> Result<Self, Box<dyn Error>> {
+        let model = match format {
+            RoutingFormat::Routes => serde_yaml::from_str(content)
+                .map_err(|e| format!("Failed to parse routing policy: {}", e))?,
+            RoutingFormat::Edges => {
+                let document: EdgesDocument = serde_yaml::from_str(content)
+                    .map_err(|e| format!("Failed to parse routing edges: {}", e))?;
+                RoutingModel {
+                    version: document.version,
+                    policy: document.policy,
+                    routes: document.edges.into_iter().map(Route::from).collect(),
+                    capability_chains: document.capability_chains,
+                    restrictions: document.restrictions,
+                }
+            }
+        };
+        
+        model.validate()?;
+        Ok(model)
+    }
+    
+    /// Serializes the model in the given source format
+    pub fn to_yaml(&self, format: RoutingFormat) -> Result<String, Box<dyn Error>> {
+        let yaml = match format {
+            RoutingFormat::Routes => serde_yaml::to_string(self)?,
+            RoutingFormat::Edges => serde_yaml::to_string(&EdgesDocument {
+                version: self.version.clone(),
+                policy: self.policy.clone(),
+                edges: self.routes.iter().map(Edge::from).collect(),
+                capability_chains: self.capability_chains.clone(),
+                restrictions: self.restrictions.clone(),
+            })?,
+        };
+        
+        Ok(yaml)
+    }
+    
+    /// Rewrites every module node to its canonical ID; unknown modules fail
+    pub fn resolve_modules(&mut self, modules: &ModuleRegistry) -> Result<(), Box<dyn Error>> {
+        for route in &mut self.routes {
+            for node in [&mut route.from, &mut route.to] {
+                if node.r#type == "module" {
+                    node.id = modules.resolve(&node.id)
+                        .map_err(|e| format!("Route '{}': {}", route.id, e))?
+                        .to_string();
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Checks the model against routing.schema.yaml, then the rules the kernel adds on top
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        let instance = serde_json::to_value(self)?;
+        let errors: Vec<String> = schema_validator()?.iter_errors(&instance)
+            .map(|e| format!("{}: {}", e.instance_path(), e))
+            .collect();
+        if !errors.is_empty() {
+            return Err(format!("Routing policy violates routing.schema.yaml: {}", errors.join("; ")).into());
+        }
+        
+        // The schema tolerates allow_by_default; the kernel does not
+        if self.policy != "deny_by_default" {
+            return Err("Routing policy must be deny_by_default".into());
+        }
+        
+        let mut ids = HashSet::new();
+        for route in &self.routes {
+            if !ids.insert(route.id.as_str()) {
+                return Err(format!("Duplicate route id '{}'", route.id).into());
+            }
+            
+            let patterns = route.allowed_capabilities.iter().flatten()
+                .chain(route.deny.iter().flatten())
+                .chain(route.from.capability.iter());
+            for pattern in patterns {
+                capability_pattern::validate_pattern(pattern)
+                    .map_err(|e| format!("Route '{}': {}", route.id, e))?;
+            }
+            
+            if let Some(conditions) = &route.conditions {
+                conditions.validate()
+                    .map_err(|e| format!("Route '{}' conditions: {}", route.id, e))?;
+            }
+            
+            if let Some(canary) = &route.canary {
+                if route.to.r#type != "module" {
+                    return Err(format!("Route '{}': canary needs a module target", route.id).into());
+                }
+                canary.validate()
+                    .map_err(|e| format!("Route '{}' canary: {}", route.id, e))?;
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+/// Compiled once; the schema is embedded so validation never depends on the working directory
+fn schema_validator() -> Result<&'static jsonschema::Validator, Box<dyn Error>> {
+    static VALIDATOR: OnceLock<Result<jsonschema::Validator, String>> = OnceLock::new();
+    
+    VALIDATOR.get_or_init(|| {
+        let schema: serde_json::Value = serde_yaml::from_str(ROUTING_SCHEMA)
+            .map_err(|e| format!("Invalid routing.schema.yaml: {}", e))?;
+        jsonschema::validator_for(&schema)
+            .map_err(|e| format!("Invalid routing.schema.yaml: {}", e))
+    })
+    .as_ref()
+    .map_err(|e| e.clone().into())
+}
+
+const ACTOR_TYPES: [&str; 3] = ["user", "service", "system"];
+const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+impl RouteConditions {
+    fn validate(&self) -> Result<(), String> {
+        for actor_type in self.actor_types.iter().flatten() {
+            if !ACTOR_TYPES.contains(&actor_type.as_str()) {
+                return Err(format!("unknown actor type '{}'", actor_type));
+            }
+        }
+        
+        for window in self.time_windows.iter().flatten() {
+            window.validate()?;
+        }
+        
+        Ok(())
+    }
+}
+
+impl Canary {
+    fn validate(&self) -> Result<(), String> {
+        let stable = capability_versions::parse_version(&self.stable).map_err(|e| e.to_string())?;
+        let candidate = capability_versions::parse_version(&self.candidate).map_err(|e| e.to_string())?;
+        if stable == candidate {
+            return Err(format!("stable and candidate are both {}", self.stable));
+        }
+        if self.weight_percent > 100 {
+            return Err(format!("weight_percent {} is over 100", self.weight_percent));
+        }
+        Ok(())
+    }
+}
+
+impl TimeWindow {
+    fn validate(&self) -> Result<(), String> {
+        parse_hhmm(&self.start)?;
+        parse_hhmm(&self.end)?;
+        
+        for day in self.days.iter().flatten() {
+            if !WEEKDAYS.contains(&day.as_str()) {
+                return Err(format!("unknown weekday '{}'", day));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Whether `at` falls inside the window
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        let (start, end) = match (parse_hhmm(&self.start), parse_hhmm(&self.end)) {
+            (Ok(start), Ok(end)) => (start, end),
+            _ => return false,
+        };
+        let minute = at.hour() * 60 + at.minute();
+        let weekday = at.weekday();
+        
+        // (inside, weekday the window opened on)
+        let (inside, opened) = if start <= end {
+            (minute >= start && minute < end, weekday)
+        } else if minute >= start {
+            (true, weekday)
+        } else {
+            (minute < end, weekday.pred())
+        };
+        
+        let opened = WEEKDAYS[opened.num_days_from_monday() as usize];
+        inside && self.days.as_ref().is_none_or(|days| days.iter().any(|d| d == opened))
+    }
+}
+
+/// Parses "HH:MM" into minutes since midnight
+fn parse_hhmm(value: &str) -> Result<u32, String> {
+    let invalid = || format!("invalid time '{}', expected HH:MM", value);
+    let (hours, minutes) = value.split_once(':').ok_or_else(invalid)?;
+    if hours.len() != 2 || minutes.len() != 2 {
+        return Err(invalid());
+    }
+    
+    let hours: u32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u32 = minutes.parse().map_err(|_| invalid())?;
+    if hours > 23 || minutes > 59 {
+        return Err(invalid());
+    }
+    
+    Ok(hours * 60 + minutes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn system_routing() -> String {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../system/policy/routing.yaml");
+        std::fs::read_to_string(path).unwrap()
+    }
+    
+    #[test]
+    fn test_round_trip_between_formats() {
+        let model = RoutingModel::parse(&system_routing()).unwrap();
+        
+        let edges = model.to_yaml(RoutingFormat::Edges).unwrap();
+        assert_eq!(RoutingFormat::detect(&edges).unwrap(), RoutingFormat::Edges);
+        let from_edges = RoutingModel::parse(&edges).unwrap();
+        assert_eq!(from_edges, model);
+        
+        let routes = from_edges.to_yaml(RoutingFormat::Routes).unwrap();
+        assert_eq!(RoutingModel::parse(&routes).unwrap(), model);
+        assert_eq!(from_edges.to_yaml(RoutingFormat::Edges).unwrap(), edges);
+    }
+    
+    #[test]
+    fn test_edges_format_defaults() {
+        let model = RoutingModel::parse(r#"
+version: v1.0.0
+edges:
+  - from_type: ui
+    from_id: main_ui
+    to_type: module
+    to_id: storage-module
+    allowed_capabilities: ["storage.listings.*"]
+    conditions:
+      time_windows:
+        - { days: [mon], start: "09:00", end: "17:00" }
+"#).unwrap();
+        
+        assert_eq!(model.policy, "deny_by_default");
+        let route = &model.routes[0];
+        assert_eq!(route.id, "main-ui-to-storage-module");
+        assert!(route.enabled);
+        assert_eq!(route.to.id, "storage-module");
+        assert_eq!(route.conditions.as_ref().unwrap().time_windows.as_ref().unwrap()[0].start, "09:00");
+    }
+    
+    #[test]
+    fn test_schema_violations_rejected() {
+        let routes = |route: &str| format!("version: v1.0.0\npolicy: deny_by_default\nroutes:\n{}", route);
+        let edge = "  - id: ok\n    from: {type: ui, id: main_ui}\n    to: {type: module, id: storage}\n";
+        
+        assert!(RoutingModel::parse(&routes(edge)).is_ok());
+        
+        let invalid = [
+            routes(&edge.replace("id: ok", "id: Not_Ok")),
+            routes(&edge.replace("{type: module", "{type: ui")),
+            routes(&format!("{}    allowed_capabilities: [\"storage*\"]\n", edge)),
+            routes(&format!("{}{}", edge, edge)),
+            routes(edge).replace("v1.0.0", "v2.0.0"),
+            routes(edge).replace("deny_by_default", "allow_by_default"),
+            format!("{}unknown_key: true\n", routes(edge)),
+            "version: v1.0.0\nedges:\n  - {from_type: invalid, from_id: x, to_type: module, to_id: y}\n".to_string(),
+        ];
+        for content in &invalid {
+            assert!(RoutingModel::parse(content).is_err(), "should be rejected:\n{}", content);
+        }
+        
+        let error = RoutingModel::parse(&invalid[0]).unwrap_err().to_string();
+        assert!(error.contains("routing.schema.yaml") && error.contains("/routes/0/id"), "{}", error);
+    }
+    
+    #[test]
+    fn test_canary_validation() {
+        let route = |canary: &str| format!(
+            "version: v1.0.0\npolicy: deny_by_default\nroutes:\n  - id: ui-to-pricing\n    from: {{type: ui, id: main_ui}}\n    to: {{type: module, id: pricing}}\n    canary: {}\n",
+            canary
+        );
+        
+        let model = RoutingModel::parse(&route("{stable: v1.4.0, candidate: v2.0.0, weight_percent: 10, mode: shadow}")).unwrap();
+        let canary = model.routes[0].canary.as_ref().unwrap();
+        assert_eq!((canary.weight_percent, canary.mode), (10, CanaryMode::Shadow));
+        
+        for invalid in [
+            "{stable: v1.4.0, candidate: v1.4.0}",
+            "{stable: v1.4.0, candidate: v2.0.0, weight_percent: 101}",
+            "{stable: v1.4.0, candidate: latest}",
+            "{stable: v1.4.0, candidate: v2.0.0, mode: blue_green}",
+        ] {
+            assert!(RoutingModel::parse(&route(invalid)).is_err(), "should be rejected: {}", invalid);
+        }
+    }
+    
+    #[test]
+    fn test_time_window() {
+        use chrono::TimeZone;
+        
+        let at = |day: u32, hour: u32| Utc.with_ymd_and_hms(2026, 1, day, hour, 0, 0).unwrap(); // Jan 12 = Monday
+        let window = |days: Option<Vec<&str>>, start: &str, end: &str| TimeWindow {
+            days: days.map(|d| d.iter().map(|s| s.to_string()).collect()),
+            start: start.to_string(),
+            end: end.to_string(),
+        };
+        
+        let business = window(Some(vec!["mon", "tue", "wed", "thu", "fri"]), "09:00", "18:00");
+        assert!(business.contains(at(12, 9)));
+        assert!(!business.contains(at(12, 18)));
+        assert!(!business.contains(at(17, 10))); // Saturday
+        
+        // Overnight window opened on Friday still holds early Saturday
+        let night = window(Some(vec!["fri"]), "22:00", "02:00");
+        assert!(night.contains(at(16, 23)));
+        assert!(night.contains(at(17, 1)));
+        assert!(!night.contains(at(16, 1)));
+        
+        assert!(window(None, "24:00", "02:00").validate().is_err());
+        assert!(window(Some(vec!["monday"]), "09:00", "10:00").validate().is_err());
+    }
+}
@@ -1,8 +1,12 @@
 // Resolve Endpoint
-// Determines which module should handle a capability
+// Determines which module, and which version of the capability, should handle a request
 
+use crate::config::module_registry::ModuleRegistry;
+use crate::routing::capability_versions;
+use crate::sandbox::instances::{HealthCheck, InstanceEndpoint, Selection};
 use std::error::Error;
 use std::fs;
+use std::path::{Component, Path, PathBuf};
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
@@ -10,41 +14,190 @@ pub struct ModuleManifest {
     pub module: ModuleInfo,
     pub capabilities: Vec<CapabilityDef>,
     pub endpoints: Endpoints,
+    #[serde(default)]
+    pub runtime: Runtime,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Runtime {
+    /// The module can stay running between requests and speak framed IPC (see sandbox::workers)
+    #[serde(default)]
+    pub persistent_worker: bool,
+    /// WASI module, relative to the module directory, run in-process (see sandbox::wasm)
+    pub wasm: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ModuleInfo {
     pub id: String,
     pub name: String,
+    /// Version of capabilities that declare none
+    pub version: Option<String>,
 }
 
+/// One version of a capability; several entries may share an `id` with different versions
 #[derive(Debug, Deserialize)]
 pub struct CapabilityDef {
     pub id: String,
     pub handler: String,
+    pub version: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct Endpoints {
     pub invoke: String,
     pub health: String,
+    /// Several instances of the module; `invoke`/`health` describe the only one if empty
+    #[serde(default)]
+    pub instances: Vec<InstanceEndpoint>,
+    #[serde(default)]
+    pub selection: Selection,
+    /// Probe interval and failure threshold; instances are not health-checked without it
+    pub health_check: Option<HealthCheck>,
 }
 
-/// Resolves a capability to its module endpoint
-pub fn resolve_endpoint(capability: &str) -> Result<(String, String), Box<dyn Error>> {
+/// A capability resolved to one module and one of its versions
+#[derive(Debug, Clone)]
+pub struct ResolvedEndpoint {
+    /// Canonical module ID
+    pub module_id: String,
+    pub endpoints: Endpoints,
+    /// Capability version that will run ("v1.2.0")
+    pub version: String,
+    /// Every version of the capability the manifest declares, e.g. for a canary to choose from
+    pub versions: Vec<semver::Version>,
+    pub handler: String,
+    /// Runs on a warm worker instead of a process per request
+    pub persistent_worker: bool,
+    /// WASM module run in-process instead of invoking the endpoint
+    pub wasm: Option<PathBuf>,
+}
+
+/// Resolves a capability to its module's canonical ID, endpoints and capability version
+/// `version_constraint` is the command's `target.version`; see capability_versions for syntax
+pub fn resolve_endpoint(
+    capability: &str,
+    version_constraint: Option<&str>,
+    modules: &ModuleRegistry,
+) -> Result<ResolvedEndpoint, Box<dyn Error>> {
     // Extract module from capability
     // e.g., "storage.listings.create" -> module could be "storage"
     let module_id = extract_module_from_capability(capability)?;
+    let module_id = modules.resolve(&module_id)?.to_string();
+    
+    // Load module manifest; it was checked when policy loaded, but is read again for every request
+    let module_dir = modules.module_dir(&module_id);
+    let manifest = load_module_manifest(&module_id, &module_dir)?;
+    check_manifest_identity(&module_id, &manifest, modules)?;
+    
+    // Verify capability is provided by this module, in a version the caller accepts
+    let (version, cap_def) = select_capability_version(&manifest, capability, version_constraint)
+        .map_err(|e| format!("{} (module '{}')", e, module_id))?;
+    let version = capability_versions::format_version(&version);
+    let versions = declared_versions(&manifest, capability)?.into_iter().map(|(v, _)| v).collect();
+    let handler = cap_def.handler.clone();
+    let wasm = manifest.runtime.wasm.as_deref()
+        .map(|file| wasm_path(&module_dir, file))
+        .transpose()
+        .map_err(|e| format!("Manifest for module '{}': {}", module_id, e))?;
     
-    // Load module manifest
-    let manifest = load_module_manifest(&module_id)?;
+    Ok(ResolvedEndpoint {
+        module_id,
+        endpoints: manifest.endpoints,
+        version,
+        versions,
+        handler,
+        persistent_worker: manifest.runtime.persistent_worker,
+        wasm,
+    })
+}
+
+/// Picks the manifest entry for the capability that best satisfies the constraint
+fn select_capability_version<'a>(
+    manifest: &'a ModuleManifest,
+    capability: &str,
+    version_constraint: Option<&str>,
+) -> Result<(semver::Version, &'a CapabilityDef), Box<dyn Error>> {
+    let candidates = declared_versions(manifest, capability)?;
+    let constraint = version_constraint.map(capability_versions::parse_constraint).transpose()?;
+    capability_versions::select(candidates, constraint.as_ref()).ok_or_else(|| {
+        match version_constraint {
+            Some(requested) => format!("VERSION_NOT_FOUND: No version of '{}' matches '{}'", capability, requested),
+            None => format!("VERSION_NOT_FOUND: No stable version of '{}'", capability),
+        }
+        .into()
+    })
+}
+
+/// Manifest entries for the capability with their versions; fails on none or a duplicate
+fn declared_versions<'a>(
+    manifest: &'a ModuleManifest,
+    capability: &str,
+) -> Result<Vec<(semver::Version, &'a CapabilityDef)>, Box<dyn Error>> {
+    let mut candidates: Vec<(semver::Version, &CapabilityDef)> = Vec::new();
+    for def in manifest.capabilities.iter().filter(|c| c.id == capability) {
+        let declared = def.version.as_ref()
+            .or(manifest.module.version.as_ref())
+            .ok_or_else(|| format!("Capability '{}' declares no version", capability))?;
+        let version = capability_versions::parse_version(declared)?;
+        
+        if candidates.iter().any(|(existing, _)| *existing == version) {
+            return Err(format!("Capability '{}' declares version {} twice", capability, declared).into());
+        }
+        candidates.push((version, def));
+    }
+    
+    if candidates.is_empty() {
+        return Err(format!("Capability '{}' not found", capability).into());
+    }
+    
+    Ok(candidates)
+}
+
+/// Checks every installed module against the registry; run when policy loads
+/// Each module directory must be named by a canonical ID and its manifest must declare that
+/// module. Without a modules directory there is nothing installed to check
+pub fn check_manifests(modules: &ModuleRegistry) -> Result<(), Box<dyn Error>> {
+    let dir = modules.modules_dir();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(format!("Failed to read modules directory '{}': {}", dir.display(), e).into()),
+    };
+    
+    for entry in entries {
+        let path = entry?.path();
+        if !path.join("manifest.yaml").is_file() {
+            continue;
+        }
+        let name = path.file_name()
+            .and_then(|name| name.to_str())
+            .ok_or("Invalid module directory name")?;
+        let module_id = modules.resolve(name)
+            .map_err(|e| format!("Module directory '{}': {}", name, e))?;
+        if module_id != name {
+            return Err(format!("Module directory '{}' must be named by its canonical ID '{}'", name, module_id).into());
+        }
+        
+        let manifest = load_module_manifest(module_id, &path)?;
+        check_manifest_identity(module_id, &manifest, modules)?;
+    }
     
-    // Verify capability is provided by this module
-    let cap_def = manifest.capabilities.iter()
-        .find(|c| c.id == capability)
-        .ok_or_else(|| format!("Capability '{}' not found in module '{}'", capability, module_id))?;
+    Ok(())
+}
+
+/// The manifest must declare (an alias of) the module it was loaded for
+fn check_manifest_identity(module_id: &str, manifest: &ModuleManifest, modules: &ModuleRegistry) -> Result<(), Box<dyn Error>> {
+    let declared = modules.resolve(&manifest.module.id)
+        .map_err(|e| format!("Manifest for module '{}': {}", module_id, e))?;
+    if declared != module_id {
+        return Err(format!(
+            "Manifest for module '{}' declares module '{}' ('{}')",
+            module_id, manifest.module.id, declared
+        ).into());
+    }
     
-    Ok((module_id, manifest.endpoints.invoke))
+    Ok(())
 }
 
 /// Extracts likely module ID from capability name
@@ -68,12 +221,18 @@ fn extract_module_from_capability(capability: &str) -> Result<String, Box<dyn Er
     Err(format!("Cannot determine module for capability: {}", capability).into())
 }
 
+/// `runtime.wasm` must stay inside the module directory
+fn wasm_path(module_dir: &Path, file: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let relative = Path::new(file);
+    if !relative.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+        return Err(format!("runtime.wasm '{}' must be a relative path without '..'", file).into());
+    }
+    Ok(module_dir.join(relative))
+}
+
 /// Loads a module manifest
-fn load_module_manifest(module_id: &str) -> Result<ModuleManifest, Box<dyn Error>> {
-    let manifest_path = format!(
-        "/home/runner/work/cabinet/cabinet/extensions/modules/{}/manifest.yaml",
-        module_id
-    );
+fn load_module_manifest(module_id: &str, module_dir: &Path) -> Result<ModuleManifest, Box<dyn Error>> {
+    let manifest_path = module_dir.join("manifest.yaml");
     
     let content = fs::read_to_string(&manifest_path)
         .map_err(|e| format!("Failed to read manifest for module '{}': {}", module_id, e))?;
@@ -94,4 +253,124 @@ mod tests {
         assert_eq!(extract_module_from_capability("import.run").unwrap(), "storage");
         assert_eq!(extract_module_from_capability("pricing.calculate").unwrap(), "pricing");
     }
+    
+    #[test]
+    fn test_manifest_identity_resolved_through_registry() {
+        use crate::config::module_registry::{ModuleIdentity, TrustLevel};
+        
+        let module = |id: &str, aliases: Vec<String>| ModuleIdentity { id: id.to_string(), aliases, trust_level: TrustLevel::Untrusted };
+        let modules = ModuleRegistry::new(vec![
+            module("storage", vec!["storage-module".to_string()]),
+            module("pricing", vec![]),
+        ]).unwrap();
+        let manifest = |id: &str| ModuleManifest {
+            module: ModuleInfo { id: id.to_string(), name: "Test".to_string(), version: None },
+            capabilities: vec![],
+            endpoints: Endpoints {
+                invoke: "invoke".to_string(),
+                health: "health".to_string(),
+                instances: vec![],
+                selection: Selection::default(),
+                health_check: None,
+            },
+            runtime: Runtime::default(),
+        };
+        
+        assert!(check_manifest_identity("storage", &manifest("storage-module"), &modules).is_ok());
+        assert!(check_manifest_identity("storage", &manifest("pricing"), &modules).is_err());
+        assert!(check_manifest_identity("storage", &manifest("car-storage"), &modules).is_err());
+    }
+    
+    #[test]
+    fn test_check_manifests() {
+        use crate::config::module_registry::parse_module_registry;
+        
+        let registry = |dir: &Path| parse_module_registry(&format!(
+            "modules:\n  - {{ id: storage, aliases: [storage-module] }}\n  - {{ id: pricing }}\nmodules_dir: {}\n",
+            dir.display()
+        )).unwrap();
+        
+        // The repository's own modules match its registry
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        let mut content = std::fs::read_to_string(root.join("system/policy/modules.yaml")).unwrap();
+        content.push_str(&format!("modules_dir: {}\n", root.join("extensions/modules").display()));
+        assert!(check_manifests(&parse_module_registry(&content).unwrap()).is_ok());
+        
+        let dir = std::env::temp_dir().join(format!("cabinet-manifests-{}", std::process::id()));
+        let install = |name: &str, declared: &str| {
+            std::fs::create_dir_all(dir.join(name)).unwrap();
+            std::fs::write(
+                dir.join(name).join("manifest.yaml"),
+                format!("module: {{ id: {}, name: Test }}\ncapabilities: []\nendpoints: {{ invoke: x, health: x }}\n", declared),
+            ).unwrap();
+        };
+        
+        install("storage", "storage-module");
+        assert!(check_manifests(&registry(&dir)).is_ok());
+        
+        install("pricing", "storage-module");
+        let error = check_manifests(&registry(&dir)).unwrap_err().to_string();
+        assert!(error.contains("Manifest for module 'pricing' declares module 'storage-module'"), "{}", error);
+        
+        std::fs::remove_dir_all(dir.join("pricing")).unwrap();
+        install("storage-module", "storage-module");
+        let error = check_manifests(&registry(&dir)).unwrap_err().to_string();
+        assert!(error.contains("must be named by its canonical ID 'storage'"), "{}", error);
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(check_manifests(&registry(&dir)).is_ok());
+    }
+    
+    #[test]
+    fn test_runtime_persistent_worker() {
+        let manifest: ModuleManifest = serde_yaml::from_str(r#"
+module: { id: storage-module, name: Storage, version: v1.0.0 }
+capabilities: []
+endpoints: { invoke: "exec:/opt/storage/worker", health: "exec:/opt/storage/worker" }
+runtime: { language: php, language_version: "8.2", persistent_worker: true }
+"#).unwrap();
+        assert!(manifest.runtime.persistent_worker);
+        
+        let manifest: ModuleManifest = serde_yaml::from_str(r#"
+module: { id: storage-module, name: Storage, version: v1.0.0 }
+capabilities: []
+endpoints: { invoke: "exec:/opt/storage/run", health: "exec:/opt/storage/run" }
+"#).unwrap();
+        assert!(!manifest.runtime.persistent_worker);
+    }
+    
+    #[test]
+    fn test_runtime_wasm_path() {
+        let dir = Path::new("/modules/pricing");
+        assert_eq!(wasm_path(dir, "build/pricing.wasm").unwrap(), dir.join("build/pricing.wasm"));
+        assert!(wasm_path(dir, "/opt/pricing.wasm").is_err());
+        assert!(wasm_path(dir, "../storage/storage.wasm").is_err());
+        assert_eq!(wasm_path(dir, "./pricing.wasm").unwrap(), dir.join("pricing.wasm"));
+    }
+    
+    #[test]
+    fn test_capability_versions_side_by_side() {
+        let manifest: ModuleManifest = serde_yaml::from_str(r#"
+module: { id: storage-module, name: Storage, version: v1.0.0 }
+capabilities:
+  - { id: storage.listings.list, handler: handlers/list.handler }
+  - { id: storage.listings.list, handler: handlers/list_v2.handler, version: v2.1.0 }
+  - { id: storage.listings.list, handler: handlers/list_v3.handler, version: v3.0.0-beta.1 }
+  - { id: storage.listings.get, handler: handlers/get.handler }
+endpoints: { invoke: "unix:///run/storage.sock", health: "unix:///run/storage.sock" }
+"#).unwrap();
+        let pick = |constraint: Option<&str>| select_capability_version(&manifest, "storage.listings.list", constraint)
+            .map(|(version, def)| (version.to_string(), def.handler.clone()));
+        
+        assert_eq!(pick(None).unwrap(), ("2.1.0".to_string(), "handlers/list_v2.handler".to_string()));
+        assert_eq!(pick(Some("v1.0.0")).unwrap().1, "handlers/list.handler");
+        assert_eq!(pick(Some("^3.0.0-beta")).unwrap().0, "3.0.0-beta.1");
+        assert!(pick(Some("v4.0.0")).unwrap_err().to_string().starts_with("VERSION_NOT_FOUND:"));
+        assert!(pick(Some("latest")).is_err());
+        
+        // Capabilities without a version take the module's
+        let (version, _) = select_capability_version(&manifest, "storage.listings.get", None).unwrap();
+        assert_eq!(version.to_string(), "1.0.0");
+        assert!(select_capability_version(&manifest, "storage.listings.delete", None).is_err());
+    }
 }
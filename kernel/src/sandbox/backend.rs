@@ -0,0 +1,365 @@
+// Sandbox Backends
+// How one module invocation is run and isolated, chosen per module
+//
+//   process         plain child process with memory and CPU rlimits, for trusted modules
+//   jailed_process  the same, in its own network namespace, with its seccomp profile
+//   wasm            the manifest's runtime.wasm, in-process (see sandbox::wasm)
+//
+// A module's backend is, in order: its entry in the `sandbox.modules` section of limits.yaml;
+// wasm if its manifest declares runtime.wasm; the backend of its trust_level (modules.yaml)
+// in `sandbox.trust_levels`. FakeBackend, with scripted replies, is not a kind policy can
+// pick: tests install it in place of a real one with `Kernel::set_backend`.
+
+use crate::config::module_registry::{ModuleRegistry, TrustLevel};
+use crate::sandbox::limits::{LimitsPolicy, ModuleLimits};
+use crate::sandbox::spawn::{output_exceeded, ProcessBackend, SpawnConfig};
+use crate::sandbox::wasm::WasmRuntime;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendKind {
+    Process,
+    JailedProcess,
+    Wasm,
+}
+
+impl BackendKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackendKind::Process => "process",
+            BackendKind::JailedProcess => "jailed_process",
+            BackendKind::Wasm => "wasm",
+        }
+    }
+    
+    /// Runs modules as child processes (which persistent workers need)
+    pub fn is_process(&self) -> bool {
+        matches!(self, BackendKind::Process | BackendKind::JailedProcess)
+    }
+}
+
+/// Starts module invocations in one kind of sandbox
+pub trait SandboxBackend {
+    /// Starts one invocation of the module; its input is sent separately
+    fn spawn<'a>(
+        &'a mut self,
+        config: &'a SpawnConfig,
+        limits: &'a ModuleLimits,
+    ) -> Result<Box<dyn SandboxedModule + 'a>, Box<dyn Error>>;
+    
+    /// Takes up a new limits policy; called at startup and on every reload
+    fn configure(&mut self, _policy: &LimitsPolicy) {}
+}
+
+/// One invocation of a module inside its sandbox
+pub trait SandboxedModule {
+    /// Sends the module its input and closes its stdin
+    fn write_input(&mut self, input: &[u8]) -> Result<(), Box<dyn Error>>;
+    
+    /// Waits for the module to finish and returns its stdout, within the timeout and output cap
+    fn read_output(&mut self) -> Result<String, Box<dyn Error>>;
+    
+    /// Stops the module if it is still running
+    fn kill(&mut self);
+    
+    /// Resources used so far; complete once the module has finished or been killed
+    fn usage(&self) -> ResourceUsage;
+}
+
+/// What an invocation used; each backend reports what it can measure
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    pub wall_ms: u64,
+    pub cpu_ms: Option<u64>,
+    pub peak_memory_bytes: Option<u64>,
+    /// WASM fuel burned
+    pub fuel: Option<u64>,
+}
+
+/// Runs an invocation to the end: `config.stdin_data` in, stdout out
+/// A module that fails is killed before its usage is read
+pub fn run(mut module: Box<dyn SandboxedModule + '_>, config: &SpawnConfig) -> (Result<String, Box<dyn Error>>, ResourceUsage) {
+    let output = module.write_input(config.stdin_data.as_bytes())
+        .and_then(|_| module.read_output());
+    if output.is_err() {
+        module.kill();
+    }
+    
+    (output, module.usage())
+}
+
+/// `sandbox` section of limits.yaml
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct BackendPolicy {
+    pub trust_levels: TrustLevelBackends,
+    /// Backend per module (any module reference), over its manifest and trust level
+    pub modules: HashMap<String, BackendKind>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct TrustLevelBackends {
+    pub trusted: BackendKind,
+    pub untrusted: BackendKind,
+}
+
+impl Default for TrustLevelBackends {
+    fn default() -> Self {
+        TrustLevelBackends {
+            trusted: BackendKind::Process,
+            untrusted: BackendKind::JailedProcess,
+        }
+    }
+}
+
+impl BackendPolicy {
+    /// Re-keys `modules` by canonical module ID
+    pub fn resolve_modules(&mut self, modules: &ModuleRegistry) -> Result<(), Box<dyn Error>> {
+        self.modules = modules.rekey(std::mem::take(&mut self.modules), "Limits policy: sandbox")?;
+        Ok(())
+    }
+    
+    /// Backend for a module (by canonical ID)
+    pub fn backend_for(&self, module_id: &str, trust_level: TrustLevel, has_wasm: bool) -> BackendKind {
+        if let Some(kind) = self.modules.get(module_id) {
+            return *kind;
+        }
+        if has_wasm {
+            return BackendKind::Wasm;
+        }
+        match trust_level {
+            TrustLevel::Trusted => self.trust_levels.trusted,
+            TrustLevel::Untrusted => self.trust_levels.untrusted,
+        }
+    }
+}
+
+/// One backend of each kind
+pub struct Backends {
+    backends: HashMap<BackendKind, Box<dyn SandboxBackend>>,
+}
+
+impl Backends {
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        let mut backends: HashMap<BackendKind, Box<dyn SandboxBackend>> = HashMap::new();
+        backends.insert(BackendKind::Process, Box::new(ProcessBackend::plain()));
+        backends.insert(BackendKind::JailedProcess, Box::new(ProcessBackend::jailed()));
+        backends.insert(BackendKind::Wasm, Box::new(WasmRuntime::new()?));
+        
+        Ok(Backends { backends })
+    }
+    
+    /// Replaces the backend of a kind, e.g. with a scripted FakeBackend
+    pub fn set(&mut self, kind: BackendKind, backend: Box<dyn SandboxBackend>) {
+        self.backends.insert(kind, backend);
+    }
+    
+    pub fn get_mut(&mut self, kind: BackendKind) -> Result<&mut dyn SandboxBackend, Box<dyn Error>> {
+        match self.backends.get_mut(&kind) {
+            Some(backend) => Ok(backend.as_mut()),
+            None => Err(format!("No {} sandbox backend", kind.as_str()).into()),
+        }
+    }
+    
+    /// Runs one invocation on the backend of `kind`
+    pub fn invoke(
+        &mut self,
+        kind: BackendKind,
+        config: &SpawnConfig,
+        limits: &ModuleLimits,
+    ) -> (Result<String, Box<dyn Error>>, ResourceUsage) {
+        let started = match self.get_mut(kind).and_then(|backend| backend.spawn(config, limits)) {
+            Ok(module) => module,
+            Err(e) => return (Err(e), ResourceUsage::default()),
+        };
+        run(started, config)
+    }
+    
+    pub fn configure(&mut self, policy: &LimitsPolicy) {
+        for backend in self.backends.values_mut() {
+            backend.configure(policy);
+        }
+    }
+}
+
+/// A module invocation, as the fake backend saw it
+#[derive(Debug, Clone, PartialEq)]
+pub struct FakeCall {
+    pub module_id: String,
+    pub endpoint: String,
+    pub input: String,
+    pub timeout: Duration,
+}
+
+/// In-memory backend: answers each module with its scripted reply and records every call
+/// A reply scripted for `<module>@<version>` answers only commands pinned to that version
+#[derive(Default)]
+pub struct FakeBackend {
+    replies: HashMap<String, Result<String, String>>,
+    calls: Arc<Mutex<Vec<FakeCall>>>,
+}
+
+impl FakeBackend {
+    /// Module (by canonical ID) writes `output` and exits successfully
+    pub fn reply(mut self, module_id: &str, output: &str) -> Self {
+        self.replies.insert(module_id.to_string(), Ok(output.to_string()));
+        self
+    }
+    
+    /// Module fails with `error` (e.g. "TIMEOUT: ...", to exercise retries)
+    pub fn fail(mut self, module_id: &str, error: &str) -> Self {
+        self.replies.insert(module_id.to_string(), Err(error.to_string()));
+        self
+    }
+    
+    /// Calls made so far, shared with the backend once it is handed over
+    pub fn calls(&self) -> Arc<Mutex<Vec<FakeCall>>> {
+        Arc::clone(&self.calls)
+    }
+}
+
+impl SandboxBackend for FakeBackend {
+    fn spawn<'a>(
+        &'a mut self,
+        config: &'a SpawnConfig,
+        _limits: &'a ModuleLimits,
+    ) -> Result<Box<dyn SandboxedModule + 'a>, Box<dyn Error>> {
+        let version = serde_json::from_str::<serde_json::Value>(&config.stdin_data).ok()
+            .and_then(|command| command["target"]["version"].as_str().map(str::to_string));
+        let reply = version.and_then(|version| self.replies.get(&format!("{}@{}", config.module_id, version)))
+            .or_else(|| self.replies.get(&config.module_id))
+            .cloned()
+            .unwrap_or_else(|| Err(format!("Fake backend has no reply for module '{}'", config.module_id)));
+        let mut module = CannedReply::new(config, reply);
+        module.calls = Some(Arc::clone(&self.calls));
+        Ok(Box::new(module))
+    }
+}
+
+/// An invocation whose outcome is known up front (fake backend, simulated endpoints)
+pub struct CannedReply<'a> {
+    config: &'a SpawnConfig,
+    reply: Result<String, String>,
+    calls: Option<Arc<Mutex<Vec<FakeCall>>>>,
+}
+
+impl<'a> CannedReply<'a> {
+    pub fn new(config: &'a SpawnConfig, reply: Result<String, String>) -> Self {
+        CannedReply { config, reply, calls: None }
+    }
+}
+
+impl SandboxedModule for CannedReply<'_> {
+    fn write_input(&mut self, input: &[u8]) -> Result<(), Box<dyn Error>> {
+        if let Some(calls) = &self.calls {
+            calls.lock().map_err(|_| "Fake backend calls poisoned")?.push(FakeCall {
+                module_id: self.config.module_id.clone(),
+                endpoint: self.config.endpoint.clone(),
+                input: String::from_utf8_lossy(input).into_owned(),
+                timeout: self.config.timeout,
+            });
+        }
+        Ok(())
+    }
+    
+    fn read_output(&mut self) -> Result<String, Box<dyn Error>> {
+        let output = self.reply.clone()?;
+        if output.len() as u64 > self.config.max_output_bytes {
+            return Err(output_exceeded(self.config, "stdout"));
+        }
+        Ok(output)
+    }
+    
+    fn kill(&mut self) {}
+    
+    fn usage(&self) -> ResourceUsage {
+        ResourceUsage::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    
+    fn limits() -> ModuleLimits {
+        serde_yaml::from_str(
+            "{ timeout_ms: 5000, max_memory_mb: 64, max_cpu_percent: 50, max_output_bytes: 64, max_input_bytes: 64 }",
+        ).unwrap()
+    }
+    
+    #[test]
+    fn test_backend_selection() {
+        let policy: BackendPolicy = serde_yaml::from_str(r#"
+trust_levels: { trusted: process }
+modules: { pricing: wasm, storage: process }
+"#).unwrap();
+        
+        assert_eq!(policy.backend_for("pricing", TrustLevel::Trusted, false), BackendKind::Wasm);
+        assert_eq!(policy.backend_for("storage", TrustLevel::Untrusted, true), BackendKind::Process);
+        assert_eq!(policy.backend_for("automation", TrustLevel::Untrusted, true), BackendKind::Wasm);
+        assert_eq!(policy.backend_for("automation", TrustLevel::Trusted, false), BackendKind::Process);
+        assert_eq!(policy.backend_for("automation", TrustLevel::Untrusted, false), BackendKind::JailedProcess);
+        assert!(serde_yaml::from_str::<BackendPolicy>("modules: { pricing: docker }").is_err());
+        // Scripted replies cannot stand in for a module from policy
+        assert!(serde_yaml::from_str::<BackendPolicy>("modules: { pricing: fake }").is_err());
+    }
+    
+    #[test]
+    fn test_fake_backend() {
+        let mut fake = FakeBackend::default()
+            .reply("pricing", r#"{"status":"success","data":{}}"#)
+            .fail("pricing@v2.0.0", "pricing v2 is broken")
+            .fail("storage", "TIMEOUT: storage is slow");
+        let calls = fake.calls();
+        let limits = limits();
+        
+        let pricing = SpawnConfig::for_test("pricing", "exec:/opt/pricing/run");
+        let (output, usage) = run(fake.spawn(&pricing, &limits).unwrap(), &pricing);
+        assert_eq!(output.unwrap(), r#"{"status":"success","data":{}}"#);
+        assert_eq!(usage, ResourceUsage::default());
+        
+        let storage = SpawnConfig::for_test("storage", "exec:/opt/storage/run");
+        assert!(run(fake.spawn(&storage, &limits).unwrap(), &storage).0.unwrap_err().to_string().starts_with("TIMEOUT"));
+        let automation = SpawnConfig::for_test("automation", "exec:/opt/automation/run");
+        assert!(run(fake.spawn(&automation, &limits).unwrap(), &automation).0.is_err());
+        
+        // Commands pinned to a version with its own reply get that one
+        let mut pinned = SpawnConfig::for_test("pricing", "exec:/opt/pricing/run");
+        pinned.stdin_data = r#"{"target":{"version":"v2.0.0"}}"#.to_string();
+        assert_eq!(run(fake.spawn(&pinned, &limits).unwrap(), &pinned).0.unwrap_err().to_string(), "pricing v2 is broken");
+        
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.len(), 4);
+        assert_eq!(calls[0].input, "{}");
+        assert_eq!(calls[1].module_id, "storage");
+    }
+    
+    #[test]
+    fn test_process_backend_reports_usage() {
+        let mut backends = Backends::new().unwrap();
+        let limits = limits();
+        let echo = SpawnConfig::for_test("pricing", "exec:/bin/cat");
+        
+        for kind in [BackendKind::Process, BackendKind::JailedProcess] {
+            let backend = backends.get_mut(kind).unwrap();
+            let (output, usage) = run(backend.spawn(&echo, &limits).unwrap(), &echo);
+            assert_eq!(output.unwrap(), echo.stdin_data);
+            assert!(usage.cpu_ms.is_some() && usage.peak_memory_bytes.is_some_and(|bytes| bytes > 0), "{:?}", usage);
+        }
+        
+        // Killed on timeout; usage is still collected
+        let mut sleeper = SpawnConfig::for_test("pricing", "exec:/bin/sleep 5");
+        sleeper.timeout = Duration::from_millis(50);
+        let backend = backends.get_mut(BackendKind::Process).unwrap();
+        let (output, usage) = run(backend.spawn(&sleeper, &limits).unwrap(), &sleeper);
+        assert!(output.unwrap_err().to_string().starts_with("TIMEOUT"));
+        assert!(usage.cpu_ms.is_some());
+    }
+}
@@ -0,0 +1,376 @@
+// Circuit Breaker
+// Stops sending requests to a module that keeps failing
+//
+// Closed: calls go through; the outcomes of the last `window` calls are kept.
+// Open: calls fail fast with MODULE_UNAVAILABLE until `open_ms` has passed.
+// Half-open: one trial call at a time; `half_open_calls` successes close the breaker,
+// any failure opens it again.
+
+use crate::config::module_registry::ModuleRegistry;
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+/// `circuit_breaker` section of limits.yaml
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CircuitBreakerPolicy {
+    #[serde(default)]
+    pub defaults: BreakerThresholds,
+    /// Per-module thresholds (replace the defaults for that module)
+    #[serde(default)]
+    pub modules: HashMap<String, BreakerThresholds>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct BreakerThresholds {
+    /// Recent calls the error rate is computed over
+    pub window: u32,
+    /// Calls needed in the window before the error rate can open the breaker
+    pub min_calls: u32,
+    /// Error rate (percent of the window) that opens the breaker
+    pub failure_rate_percent: u32,
+    /// Consecutive timeouts that open the breaker regardless of the error rate
+    pub consecutive_timeouts: u32,
+    /// How long the breaker stays open before a trial call is let through
+    pub open_ms: u64,
+    /// Successful trial calls needed to close the breaker again
+    pub half_open_calls: u32,
+    /// Keep a separate breaker per capability of the module
+    pub per_capability: bool,
+}
+
+impl Default for BreakerThresholds {
+    fn default() -> Self {
+        BreakerThresholds {
+            window: 20,
+            min_calls: 5,
+            failure_rate_percent: 50,
+            consecutive_timeouts: 3,
+            open_ms: 30000,
+            half_open_calls: 1,
+            per_capability: false,
+        }
+    }
+}
+
+impl CircuitBreakerPolicy {
+    /// Rejects thresholds under which the error rate would open the breaker on successes
+    /// (failure_rate_percent 0), or never (over 100, or min_calls above the window)
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        validate_thresholds("defaults", &self.defaults)?;
+        for (module, thresholds) in &self.modules {
+            validate_thresholds(module, thresholds)?;
+        }
+        Ok(())
+    }
+    
+    /// Re-keys `modules` by canonical module ID
+    pub fn resolve_modules(&mut self, modules: &ModuleRegistry) -> Result<(), Box<dyn Error>> {
+        self.modules = modules.rekey(std::mem::take(&mut self.modules), "Limits policy: circuit_breaker")?;
+        Ok(())
+    }
+    
+    /// Thresholds for a module (by canonical ID)
+    pub fn thresholds_for(&self, module_id: &str) -> &BreakerThresholds {
+        self.modules.get(module_id).unwrap_or(&self.defaults)
+    }
+    
+    /// Key of the breaker guarding a call: the module, or module/capability
+    pub fn breaker_key(&self, module_id: &str, capability: &str) -> String {
+        if self.thresholds_for(module_id).per_capability {
+            format!("{}/{}", module_id, capability)
+        } else {
+            module_id.to_string()
+        }
+    }
+}
+
+fn validate_thresholds(name: &str, thresholds: &BreakerThresholds) -> Result<(), Box<dyn Error>> {
+    if !(1..=100).contains(&thresholds.failure_rate_percent) {
+        return Err(format!(
+            "Limits policy: circuit_breaker '{}': failure_rate_percent must be between 1 and 100",
+            name
+        ).into());
+    }
+    if thresholds.window == 0 {
+        return Err(format!("Limits policy: circuit_breaker '{}': window must be at least 1", name).into());
+    }
+    if thresholds.min_calls > thresholds.window {
+        return Err(format!("Limits policy: circuit_breaker '{}': min_calls must be at most window", name).into());
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl BreakerState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BreakerState::Closed => "closed",
+            BreakerState::Open => "open",
+            BreakerState::HalfOpen => "half_open",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallOutcome {
+    Success,
+    Failure,
+    Timeout,
+}
+
+impl CallOutcome {
+    /// Classifies a module call by its error, if any
+    pub fn from_error(error: Option<&str>) -> Self {
+        match error {
+            None => CallOutcome::Success,
+            Some(e) if e.starts_with("TIMEOUT") => CallOutcome::Timeout,
+            Some(_) => CallOutcome::Failure,
+        }
+    }
+}
+
+/// A state change, to be audited
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    pub from: BreakerState,
+    pub to: BreakerState,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    thresholds: BreakerThresholds,
+    state: BreakerState,
+    /// Last `window` calls while closed; true = failed
+    recent: VecDeque<bool>,
+    consecutive_timeouts: u32,
+    opened_at: Option<Instant>,
+    trial_in_flight: bool,
+    trial_successes: u32,
+}
+
+impl CircuitBreaker {
+    pub fn new(thresholds: BreakerThresholds) -> Self {
+        CircuitBreaker {
+            thresholds,
+            state: BreakerState::Closed,
+            recent: VecDeque::new(),
+            consecutive_timeouts: 0,
+            opened_at: None,
+            trial_in_flight: false,
+            trial_successes: 0,
+        }
+    }
+    
+    /// True if the breaker was built from these thresholds
+    pub fn is_for(&self, thresholds: &BreakerThresholds) -> bool {
+        self.thresholds == *thresholds
+    }
+    
+    pub fn state(&self) -> BreakerState {
+        self.state
+    }
+    
+    /// Decides whether a call may go through
+    /// Err carries how long until the breaker will let a trial call through
+    pub fn admit(&mut self, now: Instant) -> Result<Option<Transition>, Duration> {
+        match self.state {
+            BreakerState::Closed => Ok(None),
+            BreakerState::Open => {
+                let reopen_at = self.opened_at.unwrap_or(now) + Duration::from_millis(self.thresholds.open_ms);
+                if now < reopen_at {
+                    return Err(reopen_at - now);
+                }
+                
+                self.trial_in_flight = true;
+                self.trial_successes = 0;
+                Ok(Some(self.transition(BreakerState::HalfOpen, format!(
+                    "open for {} ms, letting a trial call through",
+                    self.thresholds.open_ms
+                ))))
+            }
+            BreakerState::HalfOpen if self.trial_in_flight => Err(Duration::ZERO),
+            BreakerState::HalfOpen => {
+                self.trial_in_flight = true;
+                Ok(None)
+            }
+        }
+    }
+    
+    /// Records the outcome of an admitted call
+    pub fn record(&mut self, outcome: CallOutcome, now: Instant) -> Option<Transition> {
+        match self.state {
+            BreakerState::Closed => self.record_closed(outcome, now),
+            BreakerState::HalfOpen => {
+                self.trial_in_flight = false;
+                if outcome != CallOutcome::Success {
+                    return Some(self.open(now, "trial call failed".to_string()));
+                }
+                
+                self.trial_successes += 1;
+                if self.trial_successes < self.thresholds.half_open_calls {
+                    return None;
+                }
+                
+                self.recent.clear();
+                self.consecutive_timeouts = 0;
+                Some(self.transition(BreakerState::Closed, format!(
+                    "{} trial call(s) succeeded",
+                    self.trial_successes
+                )))
+            }
+            // A call admitted before the breaker opened
+            BreakerState::Open => None,
+        }
+    }
+    
+    fn record_closed(&mut self, outcome: CallOutcome, now: Instant) -> Option<Transition> {
+        self.recent.push_back(outcome != CallOutcome::Success);
+        while self.recent.len() > self.thresholds.window.max(1) as usize {
+            self.recent.pop_front();
+        }
+        
+        if outcome == CallOutcome::Timeout {
+            self.consecutive_timeouts += 1;
+        } else {
+            self.consecutive_timeouts = 0;
+        }
+        
+        if self.thresholds.consecutive_timeouts > 0
+            && self.consecutive_timeouts >= self.thresholds.consecutive_timeouts
+        {
+            let reason = format!("{} consecutive timeouts", self.consecutive_timeouts);
+            return Some(self.open(now, reason));
+        }
+        
+        let calls = self.recent.len() as u32;
+        let failures = self.recent.iter().filter(|failed| **failed).count() as u32;
+        if calls >= self.thresholds.min_calls.max(1)
+            && failures * 100 >= self.thresholds.failure_rate_percent * calls
+        {
+            let reason = format!("{} of the last {} calls failed", failures, calls);
+            return Some(self.open(now, reason));
+        }
+        
+        None
+    }
+    
+    fn open(&mut self, now: Instant, reason: String) -> Transition {
+        self.opened_at = Some(now);
+        self.trial_in_flight = false;
+        self.trial_successes = 0;
+        self.transition(BreakerState::Open, reason)
+    }
+    
+    fn transition(&mut self, to: BreakerState, reason: String) -> Transition {
+        let from = std::mem::replace(&mut self.state, to);
+        Transition { from, to, reason }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn thresholds() -> BreakerThresholds {
+        BreakerThresholds {
+            window: 4,
+            min_calls: 4,
+            failure_rate_percent: 50,
+            consecutive_timeouts: 2,
+            open_ms: 1000,
+            half_open_calls: 1,
+            per_capability: false,
+        }
+    }
+    
+    #[test]
+    fn test_opens_on_error_rate() {
+        let mut breaker = CircuitBreaker::new(thresholds());
+        let now = Instant::now();
+        
+        assert_eq!(breaker.record(CallOutcome::Failure, now), None);
+        assert_eq!(breaker.record(CallOutcome::Success, now), None);
+        assert_eq!(breaker.record(CallOutcome::Success, now), None);
+        
+        let transition = breaker.record(CallOutcome::Failure, now).unwrap();
+        assert_eq!((transition.from, transition.to), (BreakerState::Closed, BreakerState::Open));
+        assert_eq!(transition.reason, "2 of the last 4 calls failed");
+        assert!(breaker.admit(now + Duration::from_millis(10)).is_err());
+    }
+    
+    #[test]
+    fn test_opens_on_consecutive_timeouts() {
+        let mut breaker = CircuitBreaker::new(thresholds());
+        let now = Instant::now();
+        
+        breaker.record(CallOutcome::from_error(Some("TIMEOUT: 30000 ms")), now);
+        let transition = breaker.record(CallOutcome::Timeout, now).unwrap();
+        assert_eq!(transition.to, BreakerState::Open);
+        assert_eq!(transition.reason, "2 consecutive timeouts");
+    }
+    
+    #[test]
+    fn test_half_open_trial() {
+        let mut breaker = CircuitBreaker::new(thresholds());
+        let now = Instant::now();
+        breaker.record(CallOutcome::Timeout, now);
+        breaker.record(CallOutcome::Timeout, now);
+        
+        assert_eq!(breaker.admit(now + Duration::from_millis(400)), Err(Duration::from_millis(600)));
+        
+        // After open_ms one trial call goes through; others wait for its outcome
+        let later = now + Duration::from_millis(1000);
+        let transition = breaker.admit(later).unwrap().unwrap();
+        assert_eq!(transition.to, BreakerState::HalfOpen);
+        assert!(breaker.admit(later).is_err());
+        
+        // A failed trial opens it again
+        assert_eq!(breaker.record(CallOutcome::Failure, later).unwrap().to, BreakerState::Open);
+        
+        let latest = later + Duration::from_millis(1000);
+        breaker.admit(latest).unwrap();
+        let transition = breaker.record(CallOutcome::Success, latest).unwrap();
+        assert_eq!((transition.from, transition.to), (BreakerState::HalfOpen, BreakerState::Closed));
+        assert_eq!(breaker.admit(latest), Ok(None));
+    }
+    
+    #[test]
+    fn test_policy_thresholds_and_keys() {
+        let policy: CircuitBreakerPolicy = serde_yaml::from_str(r#"
+defaults:
+  failure_rate_percent: 40
+modules:
+  pricing:
+    per_capability: true
+"#).unwrap();
+        
+        assert_eq!(policy.thresholds_for("storage").failure_rate_percent, 40);
+        assert_eq!(policy.thresholds_for("pricing").failure_rate_percent, 50);
+        assert_eq!(policy.breaker_key("storage", "storage.listings.get"), "storage");
+        assert_eq!(policy.breaker_key("pricing", "pricing.quote"), "pricing/pricing.quote");
+    }
+    
+    #[test]
+    fn test_policy_validation() {
+        let parse = |yaml: &str| serde_yaml::from_str::<CircuitBreakerPolicy>(yaml).unwrap().validate();
+        
+        parse("defaults: { failure_rate_percent: 100 }").unwrap();
+        let error = parse("defaults: { failure_rate_percent: 0 }").unwrap_err();
+        assert!(error.to_string().contains("'defaults': failure_rate_percent must be between 1 and 100"), "{}", error);
+        let error = parse("modules: { pricing: { failure_rate_percent: 101 } }").unwrap_err();
+        assert!(error.to_string().contains("'pricing': failure_rate_percent"), "{}", error);
+        assert!(parse("defaults: { window: 0, min_calls: 0 }").is_err());
+        assert!(parse("defaults: { window: 4, min_calls: 5 }").is_err());
+    }
+}
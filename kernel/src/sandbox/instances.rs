@@ -0,0 +1,393 @@
+// Module Instances
+// Several instances of one module, picked per request and health-checked on an interval
+//
+// A manifest may list `endpoints.instances` (processes or local sockets); without it the
+// module has one instance built from `endpoints.invoke` / `endpoints.health`.
+// With `endpoints.health_check` set, an instance leaves rotation after
+// `unhealthy_threshold` consecutive failures (health probes or invocations) and returns
+// on its next successful probe. Without it every instance stays in rotation.
+
+use crate::observed::module_status::InstanceStatus;
+use crate::routing::resolve_endpoint::Endpoints;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::error::Error;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::os::unix::net::UnixStream;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Selection {
+    /// Fewest in-flight requests; ties go round-robin
+    #[default]
+    LeastOutstanding,
+    RoundRobin,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct InstanceEndpoint {
+    pub id: String,
+    pub invoke: String,
+    /// Probed on the health-check interval; instances without one are re-admitted each round
+    pub health: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct HealthCheck {
+    pub interval_ms: u64,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Consecutive failures before an instance leaves rotation
+    #[serde(default = "default_unhealthy_threshold")]
+    pub unhealthy_threshold: u32,
+}
+
+fn default_timeout_ms() -> u64 {
+    1000
+}
+
+fn default_unhealthy_threshold() -> u32 {
+    3
+}
+
+/// Checks one health endpoint
+pub trait HealthProbe {
+    fn check(&self, endpoint: &str, timeout: Duration) -> Result<(), String>;
+}
+
+/// Healthy if the endpoint accepts a connection
+/// `unix:///path` connects to a local socket; `http://`, `https://` and `tcp://` to host:port
+pub struct ConnectProbe;
+
+impl HealthProbe for ConnectProbe {
+    fn check(&self, endpoint: &str, timeout: Duration) -> Result<(), String> {
+        if let Some(path) = endpoint.strip_prefix("unix://") {
+            return UnixStream::connect(path).map(|_| ()).map_err(|e| e.to_string());
+        }
+        
+        let (rest, default_port) = if let Some(rest) = endpoint.strip_prefix("http://") {
+            (rest, 80)
+        } else if let Some(rest) = endpoint.strip_prefix("https://") {
+            (rest, 443)
+        } else if let Some(rest) = endpoint.strip_prefix("tcp://") {
+            (rest, 0)
+        } else {
+            return Err(format!("unsupported health endpoint '{}'", endpoint));
+        };
+        
+        let authority = rest.split(['/', '?']).next().unwrap_or_default();
+        let address = if authority.contains(':') {
+            authority.to_string()
+        } else {
+            format!("{}:{}", authority, default_port)
+        };
+        
+        let target = address.to_socket_addrs()
+            .map_err(|e| e.to_string())?
+            .next()
+            .ok_or_else(|| format!("no address for '{}'", address))?;
+        TcpStream::connect_timeout(&target, timeout).map(|_| ()).map_err(|e| e.to_string())
+    }
+}
+
+struct Instance {
+    endpoint: InstanceEndpoint,
+    healthy: bool,
+    outstanding: u32,
+    consecutive_failures: u32,
+    last_error: Option<String>,
+    last_probe: Option<String>,
+}
+
+/// Instances of one module and their rotation state
+pub struct InstancePool {
+    selection: Selection,
+    health_check: Option<HealthCheck>,
+    instances: Vec<Instance>,
+    /// Next instance to consider first (round-robin, and ties under least-outstanding)
+    cursor: usize,
+    last_probe: Option<Instant>,
+}
+
+impl Endpoints {
+    /// Declared instances, or the single instance described by `invoke` / `health`
+    pub fn instance_endpoints(&self) -> Vec<InstanceEndpoint> {
+        if !self.instances.is_empty() {
+            return self.instances.clone();
+        }
+        
+        vec![InstanceEndpoint {
+            id: "default".to_string(),
+            invoke: self.invoke.clone(),
+            health: Some(self.health.clone()),
+        }]
+    }
+}
+
+impl InstancePool {
+    /// Builds the pool for a manifest's endpoints; instance IDs must be unique
+    pub fn from_endpoints(endpoints: &Endpoints) -> Result<Self, Box<dyn Error>> {
+        let mut seen = HashSet::new();
+        let mut instances = Vec::new();
+        
+        for endpoint in endpoints.instance_endpoints() {
+            if !seen.insert(endpoint.id.clone()) {
+                return Err(format!("Duplicate module instance id: {}", endpoint.id).into());
+            }
+            instances.push(Instance {
+                endpoint,
+                healthy: true,
+                outstanding: 0,
+                consecutive_failures: 0,
+                last_error: None,
+                last_probe: None,
+            });
+        }
+        
+        Ok(InstancePool {
+            selection: endpoints.selection,
+            health_check: endpoints.health_check.clone(),
+            instances,
+            cursor: 0,
+            last_probe: None,
+        })
+    }
+    
+    /// True if the pool was built from these endpoints (false once the manifest changes)
+    pub fn is_for(&self, endpoints: &Endpoints) -> bool {
+        self.selection == endpoints.selection
+            && self.health_check == endpoints.health_check
+            && self.instances.iter().map(|i| &i.endpoint).eq(endpoints.instance_endpoints().iter())
+    }
+    
+    /// Picks a healthy instance and counts the request against it
+    /// None if every instance is out of rotation
+    pub fn select(&mut self) -> Option<usize> {
+        let count = self.instances.len();
+        let mut candidates = (0..count)
+            .map(|offset| (self.cursor + offset) % count)
+            .filter(|&index| self.instances[index].healthy);
+        
+        let chosen = match self.selection {
+            Selection::RoundRobin => candidates.next(),
+            // min_by_key keeps the first minimum, i.e. the one nearest the cursor
+            Selection::LeastOutstanding => candidates.min_by_key(|&index| self.instances[index].outstanding),
+        }?;
+        
+        self.cursor = (chosen + 1) % count;
+        self.instances[chosen].outstanding += 1;
+        Some(chosen)
+    }
+    
+    pub fn invoke_endpoint(&self, index: usize) -> &str {
+        &self.instances[index].endpoint.invoke
+    }
+    
+    pub fn instance_id(&self, index: usize) -> &str {
+        &self.instances[index].endpoint.id
+    }
+    
+    /// Ends a request started by `select`; failed invocations count toward the threshold
+    pub fn release(&mut self, index: usize, error: Option<&str>) {
+        let instance = &mut self.instances[index];
+        instance.outstanding = instance.outstanding.saturating_sub(1);
+        
+        match error {
+            None => instance.consecutive_failures = 0,
+            Some(error) => self.record_failure(index, error),
+        }
+    }
+    
+    /// True once the health-check interval has passed since the last round
+    pub fn probe_due(&self, now: Instant) -> bool {
+        match (&self.health_check, self.last_probe) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(check), Some(last)) => now.duration_since(last) >= Duration::from_millis(check.interval_ms),
+        }
+    }
+    
+    /// Probes every instance; failing ones leave rotation at the threshold, passing ones return
+    pub fn probe(&mut self, probe: &dyn HealthProbe, now: Instant) {
+        let timeout = match &self.health_check {
+            Some(check) => Duration::from_millis(check.timeout_ms),
+            None => return,
+        };
+        self.last_probe = Some(now);
+        
+        for index in 0..self.instances.len() {
+            let result = match &self.instances[index].endpoint.health {
+                Some(health) => probe.check(health, timeout),
+                None => Ok(()),
+            };
+            self.instances[index].last_probe = Some(chrono::Utc::now().to_rfc3339());
+            
+            match result {
+                Ok(()) => {
+                    let instance = &mut self.instances[index];
+                    instance.healthy = true;
+                    instance.consecutive_failures = 0;
+                    instance.last_error = None;
+                }
+                Err(error) => self.record_failure(index, &error),
+            }
+        }
+    }
+    
+    fn record_failure(&mut self, index: usize, error: &str) {
+        let threshold = match &self.health_check {
+            Some(check) => check.unhealthy_threshold,
+            None => return,
+        };
+        
+        let instance = &mut self.instances[index];
+        instance.consecutive_failures += 1;
+        instance.last_error = Some(error.to_string());
+        if instance.consecutive_failures >= threshold {
+            instance.healthy = false;
+        }
+    }
+    
+    /// Instance state for ModuleStatus
+    pub fn status(&self) -> Vec<InstanceStatus> {
+        self.instances.iter()
+            .map(|instance| InstanceStatus {
+                id: instance.endpoint.id.clone(),
+                endpoint: instance.endpoint.invoke.clone(),
+                state: if instance.healthy { "healthy" } else { "unhealthy" }.to_string(),
+                outstanding: instance.outstanding,
+                consecutive_failures: instance.consecutive_failures,
+                last_probe: instance.last_probe.clone(),
+                last_error: instance.last_error.clone(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    
+    /// Fails every endpoint in the set
+    struct FakeProbe(RefCell<HashSet<String>>);
+    
+    impl HealthProbe for FakeProbe {
+        fn check(&self, endpoint: &str, _timeout: Duration) -> Result<(), String> {
+            if self.0.borrow().contains(endpoint) {
+                Err("connection refused".to_string())
+            } else {
+                Ok(())
+            }
+        }
+    }
+    
+    fn endpoints(count: usize, selection: Selection, health_check: Option<HealthCheck>) -> Endpoints {
+        Endpoints {
+            invoke: "unix:///run/m.sock".to_string(),
+            health: "unix:///run/m.sock".to_string(),
+            instances: (0..count)
+                .map(|i| InstanceEndpoint {
+                    id: format!("m-{}", i),
+                    invoke: format!("unix:///run/m-{}.sock", i),
+                    health: Some(format!("health-{}", i)),
+                })
+                .collect(),
+            selection,
+            health_check,
+        }
+    }
+    
+    fn check(threshold: u32) -> Option<HealthCheck> {
+        Some(HealthCheck { interval_ms: 1000, timeout_ms: 100, unhealthy_threshold: threshold })
+    }
+    
+    #[test]
+    fn test_single_instance_without_declared_instances() {
+        let mut pool = InstancePool::from_endpoints(&endpoints(0, Selection::default(), None)).unwrap();
+        
+        let index = pool.select().unwrap();
+        assert_eq!(pool.instance_id(index), "default");
+        assert_eq!(pool.invoke_endpoint(index), "unix:///run/m.sock");
+        assert!(!pool.probe_due(Instant::now()));
+    }
+    
+    #[test]
+    fn test_round_robin() {
+        let mut pool = InstancePool::from_endpoints(&endpoints(3, Selection::RoundRobin, None)).unwrap();
+        
+        let picks: Vec<usize> = (0..4).map(|_| pool.select().unwrap()).collect();
+        assert_eq!(picks, vec![0, 1, 2, 0]);
+    }
+    
+    #[test]
+    fn test_least_outstanding() {
+        let mut pool = InstancePool::from_endpoints(&endpoints(3, Selection::LeastOutstanding, None)).unwrap();
+        
+        // Three in flight, one per instance; finishing 1 makes it the least loaded
+        let held: Vec<usize> = (0..3).map(|_| pool.select().unwrap()).collect();
+        assert_eq!(held, vec![0, 1, 2]);
+        pool.release(1, None);
+        assert_eq!(pool.select(), Some(1));
+        
+        // All equal again: ties continue round-robin from the last pick
+        pool.release(0, None);
+        pool.release(1, None);
+        pool.release(2, None);
+        assert_eq!(pool.select(), Some(2));
+    }
+    
+    #[test]
+    fn test_failing_instances_leave_rotation() {
+        let mut pool = InstancePool::from_endpoints(&endpoints(2, Selection::RoundRobin, check(2))).unwrap();
+        let probe = FakeProbe(RefCell::new(HashSet::from(["health-0".to_string()])));
+        let start = Instant::now();
+        
+        assert!(pool.probe_due(start));
+        pool.probe(&probe, start);
+        assert!(!pool.probe_due(start + Duration::from_millis(999)));
+        assert_eq!(pool.status()[0].state, "healthy"); // below threshold
+        
+        pool.probe(&probe, start + Duration::from_secs(1));
+        assert_eq!(pool.status()[0].state, "unhealthy");
+        assert_eq!(pool.status()[0].last_error.as_deref(), Some("connection refused"));
+        assert_eq!((0..3).map(|_| pool.select().unwrap()).collect::<Vec<_>>(), vec![1, 1, 1]);
+        
+        // Failed invocations count too; with no instance left there is nothing to select
+        for _ in 0..2 {
+            pool.release(1, Some("crashed"));
+        }
+        pool.release(1, None);
+        assert_eq!(pool.status()[1].state, "unhealthy");
+        assert_eq!(pool.select(), None);
+        
+        // A passing probe brings instances back
+        probe.0.borrow_mut().clear();
+        pool.probe(&probe, start + Duration::from_secs(2));
+        assert!(pool.status().iter().all(|s| s.state == "healthy"));
+    }
+    
+    #[test]
+    fn test_pool_rebuilt_when_manifest_changes() {
+        let original = endpoints(2, Selection::RoundRobin, None);
+        let pool = InstancePool::from_endpoints(&original).unwrap();
+        
+        assert!(pool.is_for(&original));
+        assert!(!pool.is_for(&endpoints(3, Selection::RoundRobin, None)));
+        assert!(!pool.is_for(&endpoints(2, Selection::LeastOutstanding, None)));
+    }
+    
+    #[test]
+    fn test_connect_probe() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let timeout = Duration::from_millis(200);
+        
+        assert!(ConnectProbe.check(&format!("http://127.0.0.1:{}/health", port), timeout).is_ok());
+        drop(listener);
+        assert!(ConnectProbe.check(&format!("tcp://127.0.0.1:{}", port), timeout).is_err());
+        assert!(ConnectProbe.check("unix:///nonexistent/instance.sock", timeout).is_err());
+        assert!(ConnectProbe.check("ftp://host/health", timeout).is_err());
+    }
+}
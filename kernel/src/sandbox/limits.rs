@@ -1,12 +1,23 @@
 // Resource Limits
 // Enforces CPU, memory, time, and output limits on modules
 
+use crate::config::module_registry::ModuleRegistry;
+use crate::sandbox::backend::BackendPolicy;
+use crate::sandbox::circuit_breaker::CircuitBreakerPolicy;
+use crate::sandbox::network::NetworkGrant;
+use crate::sandbox::retry::RetryPolicy;
+use crate::sandbox::seccomp::SeccompPolicy;
+use crate::sandbox::wasm::WasmPolicy;
+use crate::sandbox::workers::WorkerPolicy;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::error::Error;
 use std::fs;
 use std::time::Duration;
 
+/// Partition of the state kept by requests made without a tenant
+pub const SHARED_PARTITION: &str = "_shared";
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct ModuleLimits {
     pub timeout_ms: u64,
@@ -16,12 +27,30 @@ pub struct ModuleLimits {
     pub max_input_bytes: u64,
     pub allowed_file_paths: Option<Vec<String>>,
     pub readonly_paths: Option<Vec<String>>,
+    /// Name of a profile in the `seccomp` section; the default profile if unset
+    #[serde(default)]
+    pub seccomp_profile: Option<String>,
+    /// Network the module's process gets; none unless granted
+    #[serde(default)]
+    pub network: NetworkGrant,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct LimitsPolicy {
     pub defaults: ModuleLimits,
     pub module_limits: HashMap<String, ModuleLimits>,
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerPolicy,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    #[serde(default)]
+    pub seccomp: SeccompPolicy,
+    #[serde(default)]
+    pub workers: WorkerPolicy,
+    #[serde(default)]
+    pub wasm: WasmPolicy,
+    #[serde(default)]
+    pub sandbox: BackendPolicy,
 }
 
 /// Loads limits from system/policy/limits.yaml
@@ -30,19 +59,60 @@ pub fn load_limits() -> Result<LimitsPolicy, Box<dyn Error>> {
     let content = fs::read_to_string(policy_path)
         .map_err(|e| format!("Failed to read limits policy: {}", e))?;
     
-    let policy: LimitsPolicy = serde_yaml::from_str(&content)
+    parse_limits(&content)
+}
+
+/// Parses limits policy content
+pub fn parse_limits(content: &str) -> Result<LimitsPolicy, Box<dyn Error>> {
+    let policy: LimitsPolicy = serde_yaml::from_str(content)
         .map_err(|e| format!("Failed to parse limits policy: {}", e))?;
+    policy.retry.validate()?;
+    policy.circuit_breaker.validate()?;
+    policy.seccomp.validate()?;
+    policy.wasm.validate()?;
+    let modules = policy.module_limits.iter().map(|(id, limits)| (id.as_str(), limits));
+    for (module_id, limits) in std::iter::once(("defaults", &policy.defaults)).chain(modules) {
+        if let Some(profile) = &limits.seccomp_profile {
+            policy.seccomp.profile(profile)?;
+        }
+        limits.network.validate(module_id)?;
+    }
     
     Ok(policy)
 }
 
-/// Gets limits for a specific module
+impl LimitsPolicy {
+    /// Re-keys `module_limits` and the per-module sections by canonical module ID
+    pub fn resolve_modules(&mut self, modules: &ModuleRegistry) -> Result<(), Box<dyn Error>> {
+        self.module_limits = modules.rekey(std::mem::take(&mut self.module_limits), "Limits policy")?;
+        self.circuit_breaker.resolve_modules(modules)?;
+        self.workers.resolve_modules(modules)?;
+        self.sandbox.resolve_modules(modules)
+    }
+}
+
+/// Gets limits for a specific module (by canonical ID)
 pub fn get_module_limits(module_id: &str, policy: &LimitsPolicy) -> ModuleLimits {
     policy.module_limits.get(module_id)
         .cloned()
         .unwrap_or_else(|| policy.defaults.clone())
 }
 
+/// Partitions a module's writable state per tenant
+/// Each allowed (writable) path gets a `tenants/<tenant_id>` suffix; readonly paths stay shared
+/// Requests without a tenant get `tenants/_shared`, which no tenant ID can name
+/// The module is given the result through sandbox::tenant_state
+pub fn partition_for_tenant(limits: &ModuleLimits, tenant_id: Option<&str>) -> ModuleLimits {
+    let partition = tenant_id.unwrap_or(SHARED_PARTITION);
+    let mut partitioned = limits.clone();
+    partitioned.allowed_file_paths = limits.allowed_file_paths.as_ref().map(|paths| {
+        paths.iter()
+            .map(|path| format!("{}/tenants/{}", path.trim_end_matches('/'), partition))
+            .collect()
+    });
+    partitioned
+}
+
 /// Validates input size against limits
 pub fn check_input_size(input: &str, limits: &ModuleLimits) -> Result<(), Box<dyn Error>> {
     let size = input.len() as u64;
@@ -97,6 +167,8 @@ mod tests {
             max_input_bytes: 1024,
             allowed_file_paths: None,
             readonly_paths: None,
+            seccomp_profile: None,
+            network: NetworkGrant::default(),
         };
         
         let input = "test";
@@ -113,6 +185,8 @@ mod tests {
             max_input_bytes: 10,
             allowed_file_paths: None,
             readonly_paths: None,
+            seccomp_profile: None,
+            network: NetworkGrant::default(),
         };
         
         let input = "this is a long input that exceeds the limit";
@@ -129,9 +203,39 @@ mod tests {
             max_input_bytes: 1024,
             allowed_file_paths: None,
             readonly_paths: None,
+            seccomp_profile: None,
+            network: NetworkGrant::default(),
         };
         
         assert!(check_timeout(500, &limits).is_ok());
         assert!(check_timeout(1500, &limits).is_err());
     }
+    
+    #[test]
+    fn test_partition_for_tenant() {
+        let limits = ModuleLimits {
+            timeout_ms: 1000,
+            max_memory_mb: 512,
+            max_cpu_percent: 80,
+            max_output_bytes: 1024,
+            max_input_bytes: 1024,
+            allowed_file_paths: Some(vec!["/var/cabinet/data/storage/".to_string()]),
+            readonly_paths: Some(vec!["/etc/cabinet".to_string()]),
+            seccomp_profile: None,
+            network: NetworkGrant::default(),
+        };
+        
+        let partitioned = partition_for_tenant(&limits, Some("dealer-a"));
+        assert_eq!(
+            partitioned.allowed_file_paths,
+            Some(vec!["/var/cabinet/data/storage/tenants/dealer-a".to_string()])
+        );
+        assert_eq!(partitioned.readonly_paths, limits.readonly_paths);
+        
+        let shared = partition_for_tenant(&limits, None);
+        assert_eq!(
+            shared.allowed_file_paths,
+            Some(vec!["/var/cabinet/data/storage/tenants/_shared".to_string()])
+        );
+    }
 }
@@ -4,3 +4,13 @@
 pub mod spawn;
 pub mod limits;
 pub mod fs_jail;
+pub mod instances;
+pub mod circuit_breaker;
+pub mod retry;
+pub mod seccomp;
+pub mod network;
+pub mod workers;
+pub mod wasm;
+pub mod backend;
+pub mod tenant_state;
+pub mod sys;
@@ -0,0 +1,189 @@
+// Network Isolation
+// Runs each module process in its own network namespace
+//
+// A fresh namespace has only a loopback interface, and it is down: no network at all.
+// Limits can grant a module:
+//   loopback      lo is brought up, and IP sockets may be created (they reach nothing else)
+//   unix_sockets  the kernel connects to these local sockets and hands the module the
+//                 connections as file descriptors 3, 4, ... (listed in CABINET_UNIX_SOCKETS)
+// Modules never create Unix sockets themselves, so a path-bound socket outside the grant
+// stays out of reach even though the filesystem is shared. Creating a socket the grant does
+// not cover kills the module with SIGSYS, reported as SECURITY_VIOLATION.
+
+use seccompiler::{
+    BpfProgram, SeccompAction, SeccompCmpArgLen, SeccompCmpOp, SeccompCondition, SeccompFilter, SeccompRule,
+};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::os::fd::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+
+use crate::sandbox::sys::check;
+
+/// Environment variable telling the module which descriptor is which socket ("path=fd,...")
+pub const UNIX_SOCKETS_ENV: &str = "CABINET_UNIX_SOCKETS";
+
+/// First descriptor granted sockets are passed as (after stdin, stdout, stderr)
+const FIRST_SOCKET_FD: RawFd = 3;
+
+/// `network` entry of a module's limits; the default grants nothing
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkGrant {
+    #[serde(default)]
+    pub loopback: bool,
+    /// Absolute paths of Unix sockets the module is connected to
+    #[serde(default)]
+    pub unix_sockets: Vec<String>,
+}
+
+impl NetworkGrant {
+    pub fn validate(&self, module_id: &str) -> Result<(), Box<dyn Error>> {
+        for path in &self.unix_sockets {
+            if !path.starts_with('/') || path.split('/').any(|part| part == "..") {
+                return Err(format!(
+                    "Limits policy: module '{}': unix socket '{}' must be an absolute path without '..'",
+                    module_id, path
+                ).into());
+            }
+        }
+        Ok(())
+    }
+    
+    /// Short description for errors and audit events
+    pub fn describe(&self) -> String {
+        match (self.loopback, self.unix_sockets.len()) {
+            (false, 0) => "no network".to_string(),
+            (true, 0) => "loopback only".to_string(),
+            (false, n) => format!("{} unix socket(s)", n),
+            (true, n) => format!("loopback and {} unix socket(s)", n),
+        }
+    }
+}
+
+/// A grant made ready for one module process: sockets connected, filter compiled
+/// Everything the child needs is prepared here, so `enter` does not allocate
+pub struct NetworkJail {
+    loopback: bool,
+    sockets: Vec<UnixStream>,
+    socket_list: String,
+    filter: BpfProgram,
+}
+
+impl NetworkJail {
+    pub fn prepare(module_id: &str, grant: &NetworkGrant) -> Result<Self, Box<dyn Error>> {
+        let mut sockets = Vec::new();
+        let mut entries = Vec::new();
+        for (index, path) in grant.unix_sockets.iter().enumerate() {
+            let socket = UnixStream::connect(path)
+                .map_err(|e| format!("Module '{}': cannot connect granted unix socket '{}': {}", module_id, path, e))?;
+            sockets.push(socket);
+            entries.push(format!("{}={}", path, FIRST_SOCKET_FD + index as RawFd));
+        }
+        
+        Ok(NetworkJail {
+            loopback: grant.loopback,
+            sockets,
+            socket_list: entries.join(","),
+            filter: compile_filter(grant.loopback)?,
+        })
+    }
+    
+    /// Value of CABINET_UNIX_SOCKETS
+    pub fn socket_list(&self) -> &str {
+        &self.socket_list
+    }
+    
+    /// Moves the calling process into a new network namespace and locks it there
+    /// Meant for the child between fork and exec
+    pub fn enter(&self) -> std::io::Result<()> {
+        unshare_network()?;
+        if self.loopback {
+            loopback_up()?;
+        }
+        self.pass_sockets()?;
+        seccompiler::apply_filter(&self.filter).map_err(|e| std::io::Error::other(e.to_string()))
+    }
+    
+    /// Puts the granted sockets on descriptors 3, 4, ..., open across exec
+    fn pass_sockets(&self) -> std::io::Result<()> {
+        // Duplicate above the target range first, so no source is overwritten before it is moved
+        let above = FIRST_SOCKET_FD + self.sockets.len() as RawFd;
+        for (index, socket) in self.sockets.iter().enumerate() {
+            let moved = check(unsafe { libc::fcntl(socket.as_raw_fd(), libc::F_DUPFD_CLOEXEC, above) })?;
+            check(unsafe { libc::dup2(moved, FIRST_SOCKET_FD + index as RawFd) })?;
+        }
+        Ok(())
+    }
+}
+
+/// A namespace of our own: directly when privileged, inside a new user namespace otherwise
+fn unshare_network() -> std::io::Result<()> {
+    if unsafe { libc::unshare(libc::CLONE_NEWNET) } == 0 {
+        return Ok(());
+    }
+    check(unsafe { libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) }).map(|_| ())
+}
+
+fn loopback_up() -> std::io::Result<()> {
+    let socket = check(unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) })?;
+    let mut request: libc::ifreq = unsafe { std::mem::zeroed() };
+    for (slot, byte) in request.ifr_name.iter_mut().zip(b"lo") {
+        *slot = *byte as libc::c_char;
+    }
+    
+    let result = check(unsafe { libc::ioctl(socket, libc::SIOCGIFFLAGS as _, &mut request) }).and_then(|_| {
+        unsafe { request.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short };
+        check(unsafe { libc::ioctl(socket, libc::SIOCSIFFLAGS as _, &request) })
+    });
+    unsafe { libc::close(socket) };
+    result.map(|_| ())
+}
+
+/// Kills the module on sockets outside the grant, and on attempts to leave the namespace
+fn compile_filter(loopback: bool) -> Result<BpfProgram, Box<dyn Error>> {
+    let mut rules: BTreeMap<i64, Vec<SeccompRule>> = BTreeMap::new();
+    if loopback {
+        // IP sockets only; the namespace gives them nothing but lo
+        rules.insert(libc::SYS_socket, vec![SeccompRule::new(vec![
+            SeccompCondition::new(0, SeccompCmpArgLen::Dword, SeccompCmpOp::Ne, libc::AF_INET as u64)?,
+            SeccompCondition::new(0, SeccompCmpArgLen::Dword, SeccompCmpOp::Ne, libc::AF_INET6 as u64)?,
+        ])?]);
+    } else {
+        rules.insert(libc::SYS_socket, Vec::new());
+    }
+    rules.insert(libc::SYS_setns, Vec::new());
+    rules.insert(libc::SYS_unshare, Vec::new());
+    
+    let arch = std::env::consts::ARCH.try_into()?;
+    Ok(SeccompFilter::new(rules, SeccompAction::Allow, SeccompAction::KillProcess, arch)?.try_into()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_grant_validation() {
+        let grant = NetworkGrant {
+            loopback: true,
+            unix_sockets: vec!["/run/cabinet/storage.sock".to_string()],
+        };
+        grant.validate("storage").unwrap();
+        assert_eq!(grant.describe(), "loopback and 1 unix socket(s)");
+        assert_eq!(NetworkGrant::default().describe(), "no network");
+        
+        let relative = NetworkGrant { loopback: false, unix_sockets: vec!["run/x.sock".to_string()] };
+        assert!(relative.validate("storage").is_err());
+        let traversal = NetworkGrant { loopback: false, unix_sockets: vec!["/run/../etc/x.sock".to_string()] };
+        assert!(traversal.validate("storage").is_err());
+    }
+    
+    #[test]
+    fn test_prepare_fails_on_missing_socket() {
+        let grant = NetworkGrant { loopback: false, unix_sockets: vec!["/nonexistent/cabinet.sock".to_string()] };
+        let error = NetworkJail::prepare("storage", &grant).err().unwrap();
+        assert!(error.to_string().contains("cannot connect granted unix socket"));
+    }
+}
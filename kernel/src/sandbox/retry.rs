@@ -0,0 +1,224 @@
+// Retries
+// Kernel-side retries of idempotent commands after transient module failures
+//
+// A command is idempotent if it is a `query` or carries `options.idempotency_key`;
+// anything else runs at most once. Retries back off exponentially with jitter and
+// never start past the request deadline.
+
+use crate::primitives::capability_pattern::CapabilityPattern;
+use crate::sandbox::limits::ModuleLimits;
+use serde::Deserialize;
+use serde_json::Value;
+use std::error::Error;
+use std::time::Duration;
+
+/// `retry` section of limits.yaml
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryPolicy {
+    #[serde(default)]
+    pub defaults: RetrySettings,
+    /// Per-capability overrides; the first matching pattern wins
+    #[serde(default)]
+    pub capabilities: Vec<CapabilityRetry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct RetrySettings {
+    /// Attempts in total, the first one included (1 = never retry)
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub multiplier: f64,
+    /// Each delay is randomized by up to this percentage either way
+    pub jitter_percent: u32,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        RetrySettings {
+            max_attempts: 3,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 2000,
+            multiplier: 2.0,
+            jitter_percent: 20,
+        }
+    }
+}
+
+/// Override for the capabilities matching `capability`; unset fields keep the defaults
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CapabilityRetry {
+    pub capability: String,
+    pub max_attempts: Option<u32>,
+    pub initial_backoff_ms: Option<u64>,
+    pub max_backoff_ms: Option<u64>,
+    pub multiplier: Option<f64>,
+    pub jitter_percent: Option<u32>,
+}
+
+impl RetryPolicy {
+    /// Rejects invalid capability patterns and settings
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        validate_settings("defaults", &self.defaults)?;
+        for entry in &self.capabilities {
+            CapabilityPattern::parse(&entry.capability)
+                .map_err(|e| format!("Limits policy: retry: {}", e))?;
+            validate_settings(&entry.capability, &self.settings_for(&entry.capability))?;
+        }
+        Ok(())
+    }
+    
+    /// Settings for a capability: the first matching override applied to the defaults
+    pub fn settings_for(&self, capability: &str) -> RetrySettings {
+        let mut settings = self.defaults.clone();
+        let matching = self.capabilities.iter().find(|entry| {
+            CapabilityPattern::parse(&entry.capability).is_ok_and(|pattern| pattern.matches(capability))
+        });
+        
+        if let Some(entry) = matching {
+            settings.max_attempts = entry.max_attempts.unwrap_or(settings.max_attempts);
+            settings.initial_backoff_ms = entry.initial_backoff_ms.unwrap_or(settings.initial_backoff_ms);
+            settings.max_backoff_ms = entry.max_backoff_ms.unwrap_or(settings.max_backoff_ms);
+            settings.multiplier = entry.multiplier.unwrap_or(settings.multiplier);
+            settings.jitter_percent = entry.jitter_percent.unwrap_or(settings.jitter_percent);
+        }
+        settings
+    }
+}
+
+fn validate_settings(name: &str, settings: &RetrySettings) -> Result<(), Box<dyn Error>> {
+    if settings.max_attempts == 0 {
+        return Err(format!("Limits policy: retry '{}': max_attempts must be at least 1", name).into());
+    }
+    if settings.multiplier < 1.0 {
+        return Err(format!("Limits policy: retry '{}': multiplier must be at least 1", name).into());
+    }
+    if settings.jitter_percent > 100 {
+        return Err(format!("Limits policy: retry '{}': jitter_percent must be at most 100", name).into());
+    }
+    Ok(())
+}
+
+impl RetrySettings {
+    /// Delay before retry number `retry` (1 = first retry)
+    /// `random` in [0, 1) picks where in the jitter range the delay lands
+    pub fn backoff(&self, retry: u32, random: f64) -> Duration {
+        let exponent = retry.saturating_sub(1).min(32) as i32;
+        let base = (self.initial_backoff_ms as f64 * self.multiplier.powi(exponent))
+            .min(self.max_backoff_ms as f64);
+        let jitter = self.jitter_percent as f64 / 100.0;
+        let factor = 1.0 - jitter + 2.0 * jitter * random.clamp(0.0, 1.0);
+        
+        Duration::from_millis((base * factor).round() as u64)
+    }
+}
+
+/// True if running the command twice has the same effect as running it once
+pub fn is_idempotent(command: &Value) -> bool {
+    let query = command.get("command_type").and_then(|t| t.as_str()) == Some("query");
+    let keyed = command.get("options")
+        .and_then(|o| o.get("idempotency_key"))
+        .and_then(|k| k.as_str())
+        .is_some_and(|key| !key.is_empty());
+    
+    query || keyed
+}
+
+/// True if a module failure may go away on its own
+/// Limit and security violations would only happen again
+pub fn is_transient(error: &str) -> bool {
+    !["LIMIT_EXCEEDED", "SECURITY_VIOLATION"].iter().any(|code| error.starts_with(code))
+}
+
+/// Error code for a transient module failure
+pub fn error_code(error: &str) -> &'static str {
+    if error.starts_with("TIMEOUT") {
+        "TIMEOUT"
+    } else {
+        "MODULE_ERROR"
+    }
+}
+
+/// Time the whole request may take: `options.timeout_ms`, capped by the module's timeout
+pub fn request_timeout(command: &Value, limits: &ModuleLimits) -> Duration {
+    let requested = command.get("options")
+        .and_then(|o| o.get("timeout_ms"))
+        .and_then(|t| t.as_u64())
+        .unwrap_or(limits.timeout_ms);
+    
+    Duration::from_millis(requested.min(limits.timeout_ms))
+}
+
+/// Uniform random number in [0, 1) for jitter
+/// (the low 53 bits of a v4 UUID are all random)
+pub fn jitter_random() -> f64 {
+    let bits = uuid::Uuid::new_v4().as_u128() as u64 & ((1u64 << 53) - 1);
+    bits as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        let settings = RetrySettings { jitter_percent: 0, ..RetrySettings::default() };
+        
+        assert_eq!(settings.backoff(1, 0.5), Duration::from_millis(100));
+        assert_eq!(settings.backoff(2, 0.5), Duration::from_millis(200));
+        assert_eq!(settings.backoff(3, 0.5), Duration::from_millis(400));
+        assert_eq!(settings.backoff(10, 0.5), Duration::from_millis(2000));
+    }
+    
+    #[test]
+    fn test_backoff_jitter_range() {
+        let settings = RetrySettings::default();
+        
+        assert_eq!(settings.backoff(1, 0.0), Duration::from_millis(80));
+        assert_eq!(settings.backoff(1, 0.999999), Duration::from_millis(120));
+        let random = jitter_random();
+        assert!((0.0..1.0).contains(&random));
+    }
+    
+    #[test]
+    fn test_is_idempotent() {
+        assert!(is_idempotent(&json!({"command_type": "query", "target": {"capability": "storage.listings.get"}})));
+        assert!(is_idempotent(&json!({"command_type": "invoke", "options": {"idempotency_key": "create-1"}})));
+        assert!(!is_idempotent(&json!({"command_type": "invoke", "options": {"idempotency_key": ""}})));
+        assert!(!is_idempotent(&json!({"command_type": "invoke"})));
+    }
+    
+    #[test]
+    fn test_capability_overrides() {
+        let policy: RetryPolicy = serde_yaml::from_str(r#"
+defaults:
+  max_attempts: 3
+capabilities:
+  - capability: "automation.**"
+    max_attempts: 1
+  - capability: "storage.listings.{get,list}"
+    max_attempts: 5
+    initial_backoff_ms: 50
+"#).unwrap();
+        policy.validate().unwrap();
+        
+        assert_eq!(policy.settings_for("automation.jobs.run").max_attempts, 1);
+        let storage = policy.settings_for("storage.listings.get");
+        assert_eq!((storage.max_attempts, storage.initial_backoff_ms, storage.max_backoff_ms), (5, 50, 2000));
+        assert_eq!(policy.settings_for("pricing.quote").max_attempts, 3);
+        
+        let invalid: RetryPolicy = serde_yaml::from_str("capabilities:\n  - capability: \"storage*\"\n").unwrap();
+        assert!(invalid.validate().is_err());
+    }
+    
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient("TIMEOUT: Execution time 31000 ms exceeds limit 30000 ms"));
+        assert!(is_transient("connection refused"));
+        assert!(!is_transient("LIMIT_EXCEEDED: Output size 20 bytes exceeds limit 10 bytes"));
+    }
+}
@@ -0,0 +1,223 @@
+// Seccomp Profiles
+// Syscall filters installed in a module's process before it execs
+//
+// Every module gets the `default_deny` list from limits.yaml. Its profile then adds the
+// syscall groups it does not toggle on:
+//   fork     new processes (threads stay allowed; clone3 returns ENOSYS so libc falls back to clone)
+//   ptrace   tracing and reading or writing other processes' memory
+// A denied syscall kills the module with SIGSYS, which the kernel reports as SECURITY_VIOLATION.
+// Sockets are not a profile group: the module's network grant alone decides which it may
+// create (see network.rs).
+
+use seccompiler::{
+    BpfProgram, SeccompAction, SeccompCmpArgLen, SeccompCmpOp, SeccompCondition, SeccompFilter, SeccompRule,
+};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+
+/// `seccomp` section of limits.yaml
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SeccompPolicy {
+    /// Syscalls denied to every module, whatever its profile
+    #[serde(default)]
+    pub default_deny: Vec<String>,
+    #[serde(default)]
+    pub profiles: HashMap<String, SeccompProfile>,
+    /// Profile of modules whose limits name none; no filter if unset
+    pub default_profile: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SeccompProfile {
+    #[serde(default)]
+    pub fork: bool,
+    #[serde(default)]
+    pub ptrace: bool,
+    /// Syscalls denied on top of the default list
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+impl SeccompPolicy {
+    /// Rejects unknown syscall names and a default profile that does not exist
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        for name in self.default_deny.iter().chain(self.profiles.values().flat_map(|p| p.deny.iter())) {
+            syscall_number(name)
+                .ok_or_else(|| format!("Limits policy: seccomp: unknown syscall '{}'", name))?;
+        }
+        if let Some(name) = &self.default_profile {
+            self.profile(name)?;
+        }
+        Ok(())
+    }
+    
+    pub fn profile(&self, name: &str) -> Result<&SeccompProfile, Box<dyn Error>> {
+        self.profiles.get(name)
+            .ok_or_else(|| format!("Limits policy: unknown seccomp profile '{}'", name).into())
+    }
+    
+    /// Filter for a module naming `profile` in its limits (or none)
+    pub fn filter_for(&self, profile: Option<&str>) -> Result<Option<SyscallFilter>, Box<dyn Error>> {
+        match profile.or(self.default_profile.as_deref()) {
+            Some(name) => SyscallFilter::compile(&self.default_deny, self.profile(name)?).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Compiled BPF programs, ready to install in a child process
+#[derive(Debug, Clone)]
+pub struct SyscallFilter {
+    programs: Vec<BpfProgram>,
+}
+
+impl SyscallFilter {
+    pub fn compile(default_deny: &[String], profile: &SeccompProfile) -> Result<Self, Box<dyn Error>> {
+        let mut kill: BTreeMap<i64, Vec<SeccompRule>> = BTreeMap::new();
+        let mut enosys: BTreeMap<i64, Vec<SeccompRule>> = BTreeMap::new();
+        
+        for name in default_deny.iter().chain(profile.deny.iter()) {
+            let number = syscall_number(name).ok_or_else(|| format!("Unknown syscall '{}'", name))?;
+            kill.insert(number, Vec::new());
+        }
+        
+        if !profile.fork {
+            // clone without CLONE_THREAD starts a process; clone3 hides its flags in memory
+            let no_thread_flag = SeccompCondition::new(
+                0,
+                SeccompCmpArgLen::Qword,
+                SeccompCmpOp::MaskedEq(libc::CLONE_THREAD as u64),
+                0,
+            )?;
+            kill.entry(libc::SYS_clone).or_insert(vec![SeccompRule::new(vec![no_thread_flag])?]);
+            #[cfg(target_arch = "x86_64")]
+            for number in [libc::SYS_fork, libc::SYS_vfork] {
+                kill.insert(number, Vec::new());
+            }
+            enosys.insert(libc::SYS_clone3, Vec::new());
+        }
+        
+        if !profile.ptrace {
+            for number in [libc::SYS_ptrace, libc::SYS_process_vm_readv, libc::SYS_process_vm_writev] {
+                kill.insert(number, Vec::new());
+            }
+        }
+        
+        let arch = std::env::consts::ARCH.try_into()?;
+        let mut programs: Vec<BpfProgram> = vec![
+            SeccompFilter::new(kill, SeccompAction::Allow, SeccompAction::KillProcess, arch)?.try_into()?,
+        ];
+        if !enosys.is_empty() {
+            let action = SeccompAction::Errno(libc::ENOSYS as u32);
+            programs.push(SeccompFilter::new(enosys, SeccompAction::Allow, action, arch)?.try_into()?);
+        }
+        
+        Ok(SyscallFilter { programs })
+    }
+    
+    /// Installs the filter in the calling process; meant for the child between fork and exec
+    /// (sets no_new_privs, so the filter cannot be shed by exec'ing a setuid binary)
+    pub fn install(&self) -> std::io::Result<()> {
+        for program in &self.programs {
+            seccompiler::apply_filter(program)
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Syscall number for a name usable in deny lists
+pub fn syscall_number(name: &str) -> Option<i64> {
+    let number = match name {
+        "mount" => libc::SYS_mount,
+        "umount2" => libc::SYS_umount2,
+        "pivot_root" => libc::SYS_pivot_root,
+        "chroot" => libc::SYS_chroot,
+        "reboot" => libc::SYS_reboot,
+        "kexec_load" => libc::SYS_kexec_load,
+        "kexec_file_load" => libc::SYS_kexec_file_load,
+        "init_module" => libc::SYS_init_module,
+        "finit_module" => libc::SYS_finit_module,
+        "delete_module" => libc::SYS_delete_module,
+        "swapon" => libc::SYS_swapon,
+        "swapoff" => libc::SYS_swapoff,
+        "setns" => libc::SYS_setns,
+        "unshare" => libc::SYS_unshare,
+        "bpf" => libc::SYS_bpf,
+        "perf_event_open" => libc::SYS_perf_event_open,
+        "keyctl" => libc::SYS_keyctl,
+        "add_key" => libc::SYS_add_key,
+        "request_key" => libc::SYS_request_key,
+        "userfaultfd" => libc::SYS_userfaultfd,
+        "io_uring_setup" => libc::SYS_io_uring_setup,
+        "acct" => libc::SYS_acct,
+        "settimeofday" => libc::SYS_settimeofday,
+        "clock_settime" => libc::SYS_clock_settime,
+        "open_by_handle_at" => libc::SYS_open_by_handle_at,
+        "quotactl" => libc::SYS_quotactl,
+        "syslog" => libc::SYS_syslog,
+        "ptrace" => libc::SYS_ptrace,
+        "process_vm_readv" => libc::SYS_process_vm_readv,
+        "process_vm_writev" => libc::SYS_process_vm_writev,
+        "socket" => libc::SYS_socket,
+        "clone" => libc::SYS_clone,
+        "clone3" => libc::SYS_clone3,
+        "execve" => libc::SYS_execve,
+        "execveat" => libc::SYS_execveat,
+        #[cfg(target_arch = "x86_64")]
+        "fork" => libc::SYS_fork,
+        #[cfg(target_arch = "x86_64")]
+        "vfork" => libc::SYS_vfork,
+        #[cfg(target_arch = "x86_64")]
+        "iopl" => libc::SYS_iopl,
+        #[cfg(target_arch = "x86_64")]
+        "ioperm" => libc::SYS_ioperm,
+        _ => return None,
+    };
+    Some(number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn policy() -> SeccompPolicy {
+        serde_yaml::from_str(r#"
+default_deny: [mount, reboot, bpf]
+profiles:
+  strict: {}
+  worker:
+    fork: true
+    deny: [chroot]
+default_profile: strict
+"#).unwrap()
+    }
+    
+    #[test]
+    fn test_policy_validation() {
+        policy().validate().unwrap();
+        
+        let mut unknown_syscall = policy();
+        unknown_syscall.default_deny.push("not_a_syscall".to_string());
+        assert!(unknown_syscall.validate().unwrap_err().to_string().contains("unknown syscall 'not_a_syscall'"));
+        
+        let mut unknown_profile = policy();
+        unknown_profile.default_profile = Some("missing".to_string());
+        assert!(unknown_profile.validate().is_err());
+    }
+    
+    #[test]
+    fn test_filters_compile() {
+        let policy = policy();
+        let strict = policy.filter_for(None).unwrap().unwrap();
+        assert_eq!(strict.programs.len(), 2);
+        
+        let worker = policy.filter_for(Some("worker")).unwrap().unwrap();
+        assert_eq!(worker.programs.len(), 1);
+        
+        assert!(SeccompPolicy::default().filter_for(None).unwrap().is_none());
+    }
+}
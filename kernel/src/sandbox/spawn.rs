@@ -1,31 +1,412 @@
 // Spawn Module Process
 // Spawns and manages module processes
 
+use crate::sandbox::backend::{CannedReply, ResourceUsage, SandboxBackend, SandboxedModule};
+use crate::sandbox::limits::ModuleLimits;
+use crate::sandbox::network::{NetworkGrant, NetworkJail, UNIX_SOCKETS_ENV};
+use crate::sandbox::seccomp::SyscallFilter;
+use crate::sandbox::tenant_state::{StateJail, TenantState};
+use crate::sandbox::sys::check;
 use std::error::Error;
-use std::process::{Command, Stdio};
+use std::io::{Read, Write};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How often a running module is checked for having exited
+pub const POLL_INTERVAL: Duration = Duration::from_millis(5);
 
 pub struct SpawnConfig {
     pub module_id: String,
     pub endpoint: String,
     pub stdin_data: String,
+    /// Time the module may run before it is killed
+    pub timeout: Duration,
+    /// Bytes the module may write to stdout, and to stderr; it is killed as soon as either goes over
+    pub max_output_bytes: u64,
+    /// Installed in the module's process before it execs
+    pub syscall_filter: Option<SyscallFilter>,
+    /// Network the process gets inside its own network namespace
+    pub network: NetworkGrant,
+    /// The request's partition of the module's state directories (its tenant's, or the shared one)
+    pub tenant_state: Option<TenantState>,
+}
+
+impl SpawnConfig {
+    /// A module invocation for tests: `{}` on stdin, 5 s to run, 1024 bytes of output,
+    /// no syscall filter, network or tenant state
+    pub fn for_test(module_id: &str, endpoint: &str) -> Self {
+        SpawnConfig {
+            module_id: module_id.to_string(),
+            endpoint: endpoint.to_string(),
+            stdin_data: "{}".to_string(),
+            timeout: Duration::from_secs(5),
+            max_output_bytes: 1024,
+            syscall_filter: None,
+            network: NetworkGrant::default(),
+            tenant_state: None,
+        }
+    }
 }
 
-/// Spawns a module process (simulated - in real impl would be actual IPC)
+/// Spawns a module process in its network namespace, with its seccomp filter
+/// `exec:<program> <args...>` endpoints run as a child process (arguments split on whitespace,
+/// no quoting); other endpoints are simulated
 /// Returns: process output or error
 pub fn spawn_module(config: SpawnConfig) -> Result<String, Box<dyn Error>> {
-    // In a real implementation, this would:
-    // 1. Fork/exec the module process
-    // 2. Set up IPC channels (stdin/stdout)
-    // 3. Apply cgroups/namespaces for isolation
-    // 4. Monitor the process
+    let module = ProcessBackend::jailed().start(&config, None)?;
+    crate::sandbox::backend::run(module, &config).0
+}
+
+/// Ceilings set on a module process with setrlimit, from its limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessLimits {
+    /// RLIMIT_DATA (heap and other private memory), from `max_memory_mb`
+    pub memory_bytes: u64,
+    /// RLIMIT_CPU: `max_cpu_percent` of one CPU over the whole timeout, in whole seconds
+    pub cpu_seconds: u64,
+}
+
+impl ProcessLimits {
+    pub fn from_limits(limits: &ModuleLimits) -> Self {
+        let cpu_ms = limits.timeout_ms.saturating_mul(u64::from(limits.max_cpu_percent)) / 100;
+        ProcessLimits {
+            memory_bytes: limits.max_memory_mb.saturating_mul(1024 * 1024),
+            cpu_seconds: cpu_ms.div_ceil(1000).max(1),
+        }
+    }
+    
+    /// Lowers the calling process's limits; meant for the child between fork and exec
+    /// The CPU limit sends SIGXCPU, and SIGKILL a second later if that is ignored
+    fn apply(&self) -> std::io::Result<()> {
+        lower_rlimit(libc::RLIMIT_DATA, self.memory_bytes, self.memory_bytes)?;
+        lower_rlimit(libc::RLIMIT_CPU, self.cpu_seconds, self.cpu_seconds + 1)
+    }
+}
+
+/// Sets a resource limit, never above the hard limit the process already has
+fn lower_rlimit(resource: libc::__rlimit_resource_t, soft: u64, hard: u64) -> std::io::Result<()> {
+    let mut current = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    check(unsafe { libc::getrlimit(resource, &mut current) })?;
+    let limit = libc::rlimit {
+        rlim_cur: soft.min(current.rlim_max),
+        rlim_max: hard.min(current.rlim_max),
+    };
+    check(unsafe { libc::setrlimit(resource, &limit) }).map(|_| ())
+}
+
+/// Runs modules as local child processes with their memory and CPU limits;
+/// `jailed` adds the network namespace and seccomp filter
+pub struct ProcessBackend {
+    jailed: bool,
+}
+
+impl ProcessBackend {
+    /// Plain child process: timeout, output caps and memory and CPU rlimits, for trusted modules
+    pub fn plain() -> Self {
+        ProcessBackend { jailed: false }
+    }
+    
+    /// Child process in its own network namespace, with its seccomp filter
+    pub fn jailed() -> Self {
+        ProcessBackend { jailed: true }
+    }
+    
+    fn start<'a>(
+        &self,
+        config: &'a SpawnConfig,
+        limits: Option<ProcessLimits>,
+    ) -> Result<Box<dyn SandboxedModule + 'a>, Box<dyn Error>> {
+        let Some(command_line) = config.endpoint.strip_prefix("exec:") else {
+            // In a real implementation, this would:
+            // 1. Set up IPC channels to the remote endpoint
+            // 2. Apply cgroups/namespaces for isolation
+            // 3. Monitor the process
+            
+            // For now, simulate with a simple marker
+            // In production, this would use actual process spawning
+            let simulated = format!(
+                "{{\"simulated\": true, \"module\": \"{}\", \"endpoint\": \"{}\"}}",
+                config.module_id, config.endpoint
+            );
+            return Ok(Box::new(CannedReply::new(config, Ok(simulated))));
+        };
+        
+        let mut child = start_process(config, command_line, &[], self.jailed, limits)?;
+        // Both streams are read as they are written, so output never piles up past the cap
+        let (overflow_tx, overflow) = mpsc::channel();
+        let stdout = child.stdout.take().ok_or("Module stdout missing")?;
+        let stderr = child.stderr.take().ok_or("Module stderr missing")?;
+        
+        Ok(Box::new(ModuleProcess {
+            config,
+            child,
+            started: Instant::now(),
+            writer: None,
+            stdout: Some(read_capped(stdout, "stdout", config.max_output_bytes, true, overflow_tx.clone())),
+            stderr: Some(read_capped(stderr, "stderr", config.max_output_bytes, false, overflow_tx)),
+            overflow,
+            exited: None,
+        }))
+    }
+}
+
+impl SandboxBackend for ProcessBackend {
+    fn spawn<'a>(
+        &'a mut self,
+        config: &'a SpawnConfig,
+        limits: &'a ModuleLimits,
+    ) -> Result<Box<dyn SandboxedModule + 'a>, Box<dyn Error>> {
+        self.start(config, Some(ProcessLimits::from_limits(limits)))
+    }
+}
+
+/// Output of a stream, or None if it went over the cap
+type StreamReader = JoinHandle<std::io::Result<Option<Vec<u8>>>>;
+
+/// A running module process: the command on stdin, the result on stdout
+struct ModuleProcess<'a> {
+    config: &'a SpawnConfig,
+    child: Child,
+    started: Instant,
+    writer: Option<JoinHandle<()>>,
+    stdout: Option<StreamReader>,
+    stderr: Option<StreamReader>,
+    /// Name of the first stream to go over the cap
+    overflow: Receiver<&'static str>,
+    /// Exit status and resource usage, once reaped
+    exited: Option<(ExitStatus, libc::rusage)>,
+}
+
+impl ModuleProcess<'_> {
+    /// Reaps the process if it has exited (blocking if `wait`), keeping its resource usage
+    fn reap(&mut self, wait: bool) -> std::io::Result<Option<ExitStatus>> {
+        if let Some((status, _)) = self.exited {
+            return Ok(Some(status));
+        }
+        let mut status = 0;
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        let flags = if wait { 0 } else { libc::WNOHANG };
+        let pid = unsafe { libc::wait4(self.child.id() as libc::pid_t, &mut status, flags, &mut usage) };
+        if pid < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        if pid == 0 {
+            return Ok(None);
+        }
+        let status = ExitStatus::from_raw(status);
+        self.exited = Some((status, usage));
+        Ok(Some(status))
+    }
+}
+
+impl SandboxedModule for ModuleProcess<'_> {
+    fn write_input(&mut self, input: &[u8]) -> Result<(), Box<dyn Error>> {
+        let mut stdin = self.child.stdin.take().ok_or("Module stdin missing")?;
+        let input = input.to_vec();
+        // Written alongside reading the output, so neither side blocks the other
+        self.writer = Some(std::thread::spawn(move || {
+            // A module that exits without reading its input is not an error here
+            let _ = stdin.write_all(&input);
+        }));
+        Ok(())
+    }
+    
+    fn read_output(&mut self) -> Result<String, Box<dyn Error>> {
+        let config = self.config;
+        let deadline = self.started + config.timeout;
+        let status = loop {
+            if let Some(status) = self.reap(false)? {
+                break status;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                self.kill();
+                return Err(format!(
+                    "TIMEOUT: Module '{}' did not finish within {} ms",
+                    config.module_id, config.timeout.as_millis()
+                ).into());
+            }
+            // Wakes up the moment a stream goes over its cap
+            let wait = POLL_INTERVAL.min(deadline - now);
+            match self.overflow.recv_timeout(wait) {
+                Ok(stream) => {
+                    self.kill();
+                    return Err(output_exceeded(config, stream));
+                }
+                Err(RecvTimeoutError::Disconnected) => std::thread::sleep(wait),
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+        };
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+        let output = join_reader(self.stdout.take())?;
+        let errors = join_reader(self.stderr.take())?;
+        
+        if status.signal() == Some(libc::SIGSYS) {
+            return Err(exit_error(config, status));
+        }
+        // Partial output is discarded
+        let output = match (output, errors) {
+            (Some(output), Some(_)) => output,
+            (None, _) => return Err(output_exceeded(config, "stdout")),
+            (_, None) => return Err(output_exceeded(config, "stderr")),
+        };
+        if !status.success() {
+            return Err(exit_error(config, status));
+        }
+        
+        String::from_utf8(output)
+            .map_err(|_| format!("Module '{}' wrote output that is not UTF-8", config.module_id).into())
+    }
     
-    // For now, simulate with a simple marker
-    // In production, this would use actual process spawning
+    fn kill(&mut self) {
+        if self.exited.is_none() {
+            let _ = self.child.kill();
+            let _ = self.reap(true);
+        }
+    }
+    
+    fn usage(&self) -> ResourceUsage {
+        let mut usage = ResourceUsage {
+            wall_ms: self.started.elapsed().as_millis() as u64,
+            ..ResourceUsage::default()
+        };
+        if let Some((_, rusage)) = &self.exited {
+            let cpu = |time: libc::timeval| time.tv_sec as u64 * 1000 + time.tv_usec as u64 / 1000;
+            usage.cpu_ms = Some(cpu(rusage.ru_utime) + cpu(rusage.ru_stime));
+            // ru_maxrss is in kilobytes on Linux
+            usage.peak_memory_bytes = Some(rusage.ru_maxrss as u64 * 1024);
+        }
+        usage
+    }
+}
+
+fn join_reader(reader: Option<StreamReader>) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+    let reader = reader.ok_or("Module output already read")?;
+    Ok(reader.join().map_err(|_| "Module output reader panicked")??)
+}
+
+/// Starts a module process, with stdin, stdout and stderr piped
+/// `env` is added to the environment; `jailed` puts it in its sandbox; `limits` are set
+/// before either
+pub fn start_process(
+    config: &SpawnConfig,
+    command_line: &str,
+    env: &[(&str, &str)],
+    jailed: bool,
+    limits: Option<ProcessLimits>,
+) -> Result<Child, Box<dyn Error>> {
+    let mut parts = command_line.split_whitespace();
+    let program = parts.next()
+        .ok_or_else(|| format!("Module '{}' has an empty exec endpoint", config.module_id))?;
+    
+    let mut command = Command::new(program);
+    command.args(parts)
+        .envs(env.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(state) = &config.tenant_state {
+        command.envs(state.env());
+    }
+    if let Some(limits) = limits {
+        // Runs in the child between fork and exec; getrlimit and setrlimit do not allocate
+        unsafe {
+            command.pre_exec(move || limits.apply());
+        }
+    }
+    if !jailed {
+        return command.spawn()
+            .map_err(|e| format!("Failed to start module '{}': {}", config.module_id, e).into());
+    }
+    let state_jail = config.tenant_state.as_ref().map(StateJail::prepare).transpose()?;
+    let jail = NetworkJail::prepare(&config.module_id, &config.network)?;
+    if !jail.socket_list().is_empty() {
+        command.env(UNIX_SOCKETS_ENV, jail.socket_list());
+    }
+    let filter = config.syscall_filter.clone();
+    // Runs in the child between fork and exec: everything was prepared beforehand, so it does
+    // not allocate. The namespaces come first; the seccomp profile may deny unshare and mount
+    unsafe {
+        command.pre_exec(move || {
+            if let Some(state_jail) = &state_jail {
+                state_jail.enter()?;
+            }
+            jail.enter()?;
+            match &filter {
+                Some(filter) => filter.install(),
+                None => Ok(()),
+            }
+        });
+    }
     
-    Ok(format!(
-        "{{\"simulated\": true, \"module\": \"{}\", \"endpoint\": \"{}\"}}",
-        config.module_id, config.endpoint
-    ))
+    let spawned = command.spawn();
+    // Closes our ends of the granted sockets' connections; the module holds its own
+    drop(command);
+    spawned.map_err(|e| format!("Failed to start module '{}': {}", config.module_id, e).into())
+}
+
+/// Error for a module that exited unsuccessfully; SIGSYS means the sandbox killed it, SIGXCPU
+/// that it ran out of CPU time
+pub fn exit_error(config: &SpawnConfig, status: ExitStatus) -> Box<dyn Error> {
+    if status.signal() == Some(libc::SIGSYS) {
+        return format!(
+            "SECURITY_VIOLATION: Module '{}' made a system call its seccomp profile or network grant ({}) forbids",
+            config.module_id, config.network.describe()
+        ).into();
+    }
+    if status.signal() == Some(libc::SIGXCPU) {
+        return format!("LIMIT_EXCEEDED: Module '{}' used more CPU time than its limits allow", config.module_id).into();
+    }
+    format!("Module '{}' exited with {}", config.module_id, status).into()
+}
+
+/// Reads a module stream as it is written, keeping the bytes only if `keep`
+/// Past `cap` bytes it reports the stream's name on `overflow`, stops reading and returns None
+fn read_capped(
+    mut stream: impl Read + Send + 'static,
+    name: &'static str,
+    cap: u64,
+    keep: bool,
+    overflow: Sender<&'static str>,
+) -> StreamReader {
+    std::thread::spawn(move || {
+        let mut kept = Vec::new();
+        let mut total: u64 = 0;
+        let mut chunk = [0u8; 8192];
+        loop {
+            let read = match stream.read(&mut chunk) {
+                Ok(0) => return Ok(Some(kept)),
+                Ok(read) => read,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            total += read as u64;
+            if total > cap {
+                let _ = overflow.send(name);
+                return Ok(None);
+            }
+            if keep {
+                kept.extend_from_slice(&chunk[..read]);
+            }
+        }
+    })
+}
+
+pub fn output_exceeded(config: &SpawnConfig, stream: &str) -> Box<dyn Error> {
+    format!(
+        "LIMIT_EXCEEDED: Module '{}' wrote more than {} bytes to {}",
+        config.module_id, config.max_output_bytes, stream
+    ).into()
+}
+
+pub fn kill_process(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
 }
 
 /// Kills a running module process
@@ -41,6 +422,7 @@ pub fn kill_module(pid: u32) -> Result<(), Box<dyn Error>> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sandbox::seccomp::SeccompProfile;
     
     #[test]
     fn test_spawn_module() {
@@ -48,9 +430,82 @@ mod tests {
             module_id: "storage".to_string(),
             endpoint: "http://storage:8080/invoke".to_string(),
             stdin_data: "{}".to_string(),
+            timeout: Duration::from_secs(30),
+            max_output_bytes: 1024,
+            syscall_filter: None,
+            network: NetworkGrant::default(),
+            tenant_state: None,
         };
         
         let result = spawn_module(config);
         assert!(result.is_ok());
     }
+    
+    #[test]
+    fn test_seccomp_profile_kills_forbidden_fork() {
+        let strict = SyscallFilter::compile(&["mount".to_string()], &SeccompProfile::default()).unwrap();
+        
+        let cat = SpawnConfig::for_test("pricing", "exec:/bin/cat");
+        let output = spawn_module(SpawnConfig { syscall_filter: Some(strict.clone()), ..cat }).unwrap();
+        assert_eq!(output, "{}");
+        
+        // The pipeline makes the shell fork
+        let pipeline = || SpawnConfig::for_test("pricing", "exec:/bin/sh -c true|true");
+        let error = spawn_module(SpawnConfig { syscall_filter: Some(strict), ..pipeline() }).unwrap_err();
+        assert!(error.to_string().starts_with("SECURITY_VIOLATION"), "{}", error);
+        
+        let forking = SeccompProfile { fork: true, ..SeccompProfile::default() };
+        let filter = SyscallFilter::compile(&[], &forking).unwrap();
+        assert!(spawn_module(SpawnConfig { syscall_filter: Some(filter), ..pipeline() }).is_ok());
+    }
+    
+    #[test]
+    fn test_exec_timeout() {
+        let mut config = SpawnConfig::for_test("pricing", "exec:/bin/sleep 5");
+        config.timeout = Duration::from_millis(50);
+        
+        let error = spawn_module(config).unwrap_err();
+        assert!(error.to_string().starts_with("TIMEOUT"), "{}", error);
+    }
+    
+    #[test]
+    fn test_output_cap_kills_runaway_module() {
+        // `yes` never stops on its own; the cap must end it long before the timeout
+        let started = Instant::now();
+        let error = spawn_module(SpawnConfig::for_test("pricing", "exec:/usr/bin/yes")).unwrap_err();
+        assert_eq!(error.to_string(), "LIMIT_EXCEEDED: Module 'pricing' wrote more than 1024 bytes to stdout");
+        assert!(started.elapsed() < Duration::from_secs(2));
+        
+        let error = spawn_module(SpawnConfig::for_test("pricing", "exec:/bin/sh -c exec>&2;yes")).unwrap_err();
+        assert!(error.to_string().ends_with("bytes to stderr"), "{}", error);
+        
+        let mut at_cap = SpawnConfig::for_test("pricing", "exec:/bin/cat");
+        at_cap.stdin_data = "x".repeat(1024);
+        assert_eq!(spawn_module(at_cap).unwrap().len(), 1024);
+    }
+    
+    #[test]
+    fn test_plain_process_gets_memory_and_cpu_limits() {
+        // 20% of 5 s is one second of CPU time
+        let limits: ModuleLimits = serde_yaml::from_str(
+            "{ timeout_ms: 5000, max_memory_mb: 64, max_cpu_percent: 20, max_output_bytes: 1024, max_input_bytes: 64 }",
+        ).unwrap();
+        assert_eq!(ProcessLimits::from_limits(&limits), ProcessLimits { memory_bytes: 64 << 20, cpu_seconds: 1 });
+        let mut backend = ProcessBackend::plain();
+        
+        let config = SpawnConfig::for_test("pricing", "exec:/bin/grep -E ^Max.(cpu.time|data.size) /proc/self/limits");
+        let output = crate::sandbox::backend::run(backend.spawn(&config, &limits).unwrap(), &config).0.unwrap();
+        let limits_of = |name: &str| output.lines()
+            .find(|line| line.starts_with(name))
+            .map(|line| line.split_whitespace().skip(3).take(2).collect::<Vec<_>>())
+            .unwrap();
+        assert_eq!(limits_of("Max cpu time"), ["1", "2"]);
+        assert_eq!(limits_of("Max data size"), ["67108864", "67108864"]);
+        
+        let started = Instant::now();
+        let config = SpawnConfig::for_test("pricing", "exec:/usr/bin/sha256sum /dev/zero");
+        let error = crate::sandbox::backend::run(backend.spawn(&config, &limits).unwrap(), &config).0.unwrap_err();
+        assert_eq!(error.to_string(), "LIMIT_EXCEEDED: Module 'pricing' used more CPU time than its limits allow");
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
 }
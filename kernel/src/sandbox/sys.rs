@@ -0,0 +1,12 @@
+// System Calls
+// Helpers for the raw libc calls the sandbox makes in a child between fork and exec
+
+/// A libc return value as an io::Result: negative means failure, described by errno
+/// Does not allocate, so it is safe to use between fork and exec
+pub fn check(result: libc::c_int) -> std::io::Result<libc::c_int> {
+    if result < 0 {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(result)
+    }
+}
@@ -0,0 +1,171 @@
+// Tenant State
+// Gives a module invoked for a tenant its own state directories, and only those
+//
+// Writable paths are partitioned to `<path>/tenants/<tenant_id>` (see limits::partition_for_tenant);
+// requests without a tenant get the `_shared` partition, so no request works in `<path>` itself.
+// The kernel creates a partition's directories on first use and tells the module where they are:
+//   CABINET_TENANT_ID   the tenant the request is for, unset without one
+//   CABINET_STATE_DIRS  its writable directories, comma-separated, in limits order
+// A jailed module process also gets a mount namespace of its own, in which each `<path>/tenants`
+// holds only its partition's directory: other tenants' state is not there to be read.
+
+use crate::sandbox::limits::{ModuleLimits, SHARED_PARTITION};
+use crate::sandbox::sys::check;
+use std::error::Error;
+use std::ffi::CString;
+use std::fs::DirBuilder;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming the tenant
+pub const TENANT_ENV: &str = "CABINET_TENANT_ID";
+
+/// Environment variable listing the tenant's writable directories
+pub const STATE_DIRS_ENV: &str = "CABINET_STATE_DIRS";
+
+/// A tenant's partition of one module's writable state, or the shared one without a tenant
+#[derive(Debug, Clone)]
+pub struct TenantState {
+    tenant_id: Option<String>,
+    dirs: Vec<PathBuf>,
+}
+
+impl TenantState {
+    /// Creates the tenant's directories that are missing; `limits` must already be partitioned
+    pub fn prepare(module_id: &str, tenant_id: Option<&str>, limits: &ModuleLimits) -> Result<Self, Box<dyn Error>> {
+        let partition = tenant_id.unwrap_or(SHARED_PARTITION);
+        let dirs: Vec<PathBuf> = limits.allowed_file_paths.iter().flatten().map(PathBuf::from).collect();
+        for dir in &dirs {
+            if dir.file_name().and_then(|name| name.to_str()) != Some(partition) {
+                return Err(format!("Module '{}': '{}' is not partitioned for '{}'", module_id, dir.display(), partition).into());
+            }
+            DirBuilder::new().recursive(true).mode(0o750).create(dir).map_err(|e| {
+                format!("Module '{}': cannot create state directory '{}': {}", module_id, dir.display(), e)
+            })?;
+        }
+        
+        Ok(TenantState { tenant_id: tenant_id.map(str::to_string), dirs })
+    }
+    
+    pub fn tenant_id(&self) -> Option<&str> {
+        self.tenant_id.as_deref()
+    }
+    
+    pub fn dirs(&self) -> &[PathBuf] {
+        &self.dirs
+    }
+    
+    /// CABINET_STATE_DIRS, and CABINET_TENANT_ID for a tenant
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        let dirs: Vec<String> = self.dirs.iter().map(|dir| dir.display().to_string()).collect();
+        let mut env = vec![(STATE_DIRS_ENV, dirs.join(","))];
+        if let Some(tenant_id) = &self.tenant_id {
+            env.push((TENANT_ENV, tenant_id.clone()));
+        }
+        env
+    }
+}
+
+/// The bind mounts that confine one module process to its partition's state directories
+/// Paths and mount arguments are held as C strings already, for use in the child
+pub struct StateJail {
+    mounts: Vec<StateMount>,
+    root: CString,
+    here: CString,
+    tmpfs: CString,
+    options: CString,
+}
+
+struct StateMount {
+    /// `<path>/tenants`, covered by an empty tmpfs
+    tenants: CString,
+    /// `<path>/tenants/<tenant_id>`, recreated in the tmpfs and bound to the real directory
+    own: CString,
+}
+
+impl StateJail {
+    pub fn prepare(state: &TenantState) -> Result<Self, Box<dyn Error>> {
+        let mut mounts = Vec::new();
+        for dir in &state.dirs {
+            let tenants = dir.parent().ok_or("State directory without parent")?;
+            mounts.push(StateMount { tenants: c_path(tenants)?, own: c_path(dir)? });
+        }
+        
+        Ok(StateJail {
+            mounts,
+            root: CString::new("/")?,
+            here: CString::new(".")?,
+            tmpfs: CString::new("tmpfs")?,
+            options: CString::new("mode=0755")?,
+        })
+    }
+    
+    /// Moves the calling process into a new mount namespace that shows only its tenant's state
+    /// Meant for the child between fork and exec, before the network jail moves descriptors
+    pub fn enter(&self) -> std::io::Result<()> {
+        if unsafe { libc::unshare(libc::CLONE_NEWNS) } != 0 {
+            check(unsafe { libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNS) })?;
+        }
+        // Nothing mounted here propagates back to the host
+        check(unsafe {
+            libc::mount(std::ptr::null(), self.root.as_ptr(), std::ptr::null(), libc::MS_REC | libc::MS_PRIVATE, std::ptr::null())
+        })?;
+        let cwd = check(unsafe { libc::open(self.here.as_ptr(), libc::O_PATH | libc::O_DIRECTORY | libc::O_CLOEXEC) })?;
+        let result = self.mount_all();
+        unsafe {
+            libc::fchdir(cwd);
+            libc::close(cwd);
+        }
+        result
+    }
+    
+    fn mount_all(&self) -> std::io::Result<()> {
+        for mount in &self.mounts {
+            // The real directory stays reachable as the working directory once the tmpfs covers it;
+            // a bind source has to be found in this namespace, so an fd opened by the parent will not do
+            check(unsafe { libc::chdir(mount.own.as_ptr()) })?;
+            let flags = libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC;
+            check(unsafe {
+                libc::mount(self.tmpfs.as_ptr(), mount.tenants.as_ptr(), self.tmpfs.as_ptr(), flags, self.options.as_ptr().cast())
+            })?;
+            check(unsafe { libc::mkdir(mount.own.as_ptr(), 0o750) })?;
+            check(unsafe {
+                libc::mount(self.here.as_ptr(), mount.own.as_ptr(), std::ptr::null(), libc::MS_BIND, std::ptr::null())
+            })?;
+        }
+        Ok(())
+    }
+}
+
+fn c_path(path: &Path) -> Result<CString, Box<dyn Error>> {
+    Ok(CString::new(path.as_os_str().as_bytes())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sandbox::limits::partition_for_tenant;
+    
+    #[test]
+    fn test_prepare_creates_partition() {
+        let base = std::env::temp_dir().join(format!("cabinet-tenant-state-{}", std::process::id()));
+        let limits: ModuleLimits = serde_yaml::from_str(&format!(
+            "{{ timeout_ms: 1000, max_memory_mb: 64, max_cpu_percent: 50, max_output_bytes: 64, max_input_bytes: 64, allowed_file_paths: ['{}'] }}",
+            base.display()
+        )).unwrap();
+        
+        let state = TenantState::prepare("pricing", Some("dealer-a"), &partition_for_tenant(&limits, Some("dealer-a"))).unwrap();
+        assert!(base.join("tenants/dealer-a").is_dir());
+        assert_eq!(state.env()[0], (STATE_DIRS_ENV, base.join("tenants/dealer-a").display().to_string()));
+        
+        // Without a tenant the module still gets a partition of its own, and no tenant ID
+        let shared = TenantState::prepare("pricing", None, &partition_for_tenant(&limits, None)).unwrap();
+        assert_eq!(shared.env(), [(STATE_DIRS_ENV, base.join("tenants/_shared").display().to_string())]);
+        
+        // Unpartitioned limits are refused rather than handing out the whole directory
+        assert!(TenantState::prepare("pricing", Some("dealer-a"), &limits).is_err());
+        assert!(TenantState::prepare("pricing", None, &limits).is_err());
+        std::fs::remove_dir_all(base).unwrap();
+    }
+}
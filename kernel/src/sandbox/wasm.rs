@@ -0,0 +1,503 @@
+// WASM Runtime
+// Runs modules compiled to WebAssembly (WASI preview 1) inside the kernel process
+//
+// A module opts in with `runtime.wasm: <file>.wasm` in its manifest (relative to its directory);
+// the kernel then invokes it as endpoint `wasm:<path>` on this backend (see sandbox::backend).
+// It uses the same envelope protocol as a process: the command on stdin, the result on stdout.
+// No Linux privileges are needed; the limits come from the engine instead:
+//   CPU       fuel: `wasm.fuel_per_ms` x the invocation's timeout; running out is LIMIT_EXCEEDED
+//   time      the invocation's timeout, enforced while the module runs: an epoch deadline stops
+//             running code, and a module waiting in a WASI call (poll_oneoff, a read) is dropped
+//   memory    linear memory may not grow past max_memory_mb
+//   files     only allowed_file_paths (read-write) and readonly_paths (read-only) directories
+//             are preopened, at the same paths; file entries and missing paths are not exposed.
+//             A tenant's state directories exist by then (see sandbox::tenant_state)
+//   output    stdout is capped at max_output_bytes; stderr is discarded
+// There is no network: WASI preview 1 has no sockets.
+
+use crate::sandbox::backend::{ResourceUsage, SandboxBackend, SandboxedModule};
+use crate::sandbox::limits::{LimitsPolicy, ModuleLimits};
+use crate::sandbox::spawn::{self, SpawnConfig};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use wasmtime::{Config, Engine, Linker, Module, ResourceLimiter, Store, Trap};
+use wasmtime_wasi::pipe::{MemoryInputPipe, SinkOutputStream};
+use wasmtime_wasi::preview1::{self, WasiP1Ctx};
+use wasmtime_wasi::{
+    DirPerms, FilePerms, I32Exit, OutputStream, Pollable, StdoutStream, StreamError, WasiCtxBuilder,
+};
+
+/// Interval at which the engine's epoch advances; timeouts are enforced to this granularity
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// `wasm` section of limits.yaml
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct WasmPolicy {
+    /// Fuel (roughly, WebAssembly instructions) a module may burn per millisecond of its timeout
+    pub fuel_per_ms: u64,
+}
+
+impl Default for WasmPolicy {
+    fn default() -> Self {
+        WasmPolicy { fuel_per_ms: 100_000 }
+    }
+}
+
+impl WasmPolicy {
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if self.fuel_per_ms == 0 {
+            return Err("Limits policy: wasm.fuel_per_ms must be greater than 0".into());
+        }
+        Ok(())
+    }
+}
+
+/// Store data of one invocation
+struct Invocation {
+    wasi: WasiP1Ctx,
+    memory: MemoryCap,
+}
+
+/// Refuses linear memory growth past the module's limit, and remembers that it did
+struct MemoryCap {
+    max_bytes: usize,
+    exceeded: bool,
+    peak_bytes: usize,
+}
+
+impl ResourceLimiter for MemoryCap {
+    fn memory_growing(&mut self, _current: usize, desired: usize, _maximum: Option<usize>) -> wasmtime::Result<bool> {
+        if desired > self.max_bytes {
+            self.exceeded = true;
+            return Err(wasmtime::Error::msg("memory limit reached"));
+        }
+        self.peak_bytes = self.peak_bytes.max(desired);
+        Ok(true)
+    }
+    
+    fn table_growing(&mut self, _current: usize, _desired: usize, _maximum: Option<usize>) -> wasmtime::Result<bool> {
+        Ok(true)
+    }
+}
+
+/// Module stdout, kept in memory; a write past `capacity` traps, ending the module
+#[derive(Clone)]
+struct CappedOutput {
+    capacity: usize,
+    buffer: Arc<Mutex<CappedBuffer>>,
+}
+
+#[derive(Default)]
+struct CappedBuffer {
+    bytes: Vec<u8>,
+    exceeded: bool,
+}
+
+impl CappedOutput {
+    fn new(capacity: usize) -> Self {
+        CappedOutput { capacity, buffer: Arc::default() }
+    }
+}
+
+impl StdoutStream for CappedOutput {
+    fn stream(&self) -> Box<dyn OutputStream> {
+        Box::new(self.clone())
+    }
+    
+    fn isatty(&self) -> bool {
+        false
+    }
+}
+
+impl OutputStream for CappedOutput {
+    fn write(&mut self, bytes: bytes::Bytes) -> Result<(), StreamError> {
+        let mut buffer = self.buffer.lock().unwrap();
+        if buffer.bytes.len() + bytes.len() > self.capacity {
+            buffer.exceeded = true;
+            return Err(StreamError::Trap(wasmtime::Error::msg("stdout limit reached")));
+        }
+        buffer.bytes.extend_from_slice(&bytes);
+        Ok(())
+    }
+    
+    fn flush(&mut self) -> Result<(), StreamError> {
+        Ok(())
+    }
+    
+    /// Always room for one byte more than is left, so an oversized write reaches `write`
+    fn check_write(&mut self) -> Result<usize, StreamError> {
+        let written = self.buffer.lock().unwrap().bytes.len();
+        Ok(self.capacity.saturating_sub(written) + 1)
+    }
+}
+
+#[wasmtime_wasi::async_trait]
+impl Pollable for CappedOutput {
+    async fn ready(&mut self) {}
+}
+
+/// Compiles and runs WASM modules; compiled modules are kept until their file changes
+pub struct WasmRuntime {
+    engine: Engine,
+    linker: Linker<Invocation>,
+    modules: HashMap<PathBuf, (SystemTime, Module)>,
+    policy: WasmPolicy,
+}
+
+impl WasmRuntime {
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        let mut config = Config::new();
+        config.consume_fuel(true).epoch_interruption(true).async_support(true);
+        let engine = Engine::new(&config).map_err(|e| format!("WASM engine: {}", e))?;
+        let mut linker = Linker::new(&engine);
+        preview1::add_to_linker_async(&mut linker, |invocation: &mut Invocation| &mut invocation.wasi)
+            .map_err(|e| format!("WASM engine: {}", e))?;
+        
+        // Advances the epoch until the engine is dropped
+        let ticker = engine.weak();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(EPOCH_TICK);
+            match ticker.upgrade() {
+                Some(engine) => engine.increment_epoch(),
+                None => return,
+            }
+        });
+        
+        Ok(WasmRuntime { engine, linker, modules: HashMap::new(), policy: WasmPolicy::default() })
+    }
+    
+    /// Compiled module, recompiled when the file's modification time changes
+    fn module(&mut self, module_id: &str, wasm_path: &Path) -> Result<Module, Box<dyn Error>> {
+        let modified = std::fs::metadata(wasm_path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|e| format!("Module '{}': cannot read '{}': {}", module_id, wasm_path.display(), e))?;
+        if let Some((compiled_at, module)) = self.modules.get(wasm_path) {
+            if *compiled_at == modified {
+                return Ok(module.clone());
+            }
+        }
+        
+        let module = Module::from_file(&self.engine, wasm_path)
+            .map_err(|e| format!("Module '{}': invalid WASM module: {}", module_id, e))?;
+        self.modules.insert(wasm_path.to_path_buf(), (modified, module.clone()));
+        Ok(module)
+    }
+}
+
+impl SandboxBackend for WasmRuntime {
+    fn spawn<'a>(
+        &'a mut self,
+        config: &'a SpawnConfig,
+        limits: &'a ModuleLimits,
+    ) -> Result<Box<dyn SandboxedModule + 'a>, Box<dyn Error>> {
+        let wasm_path = config.endpoint.strip_prefix("wasm:").ok_or_else(|| {
+            format!("Module '{}' runs on the wasm backend but its manifest declares no runtime.wasm", config.module_id)
+        })?;
+        let module = self.module(&config.module_id, Path::new(wasm_path))?;
+        
+        Ok(Box::new(WasmModule {
+            runtime: self,
+            module,
+            config,
+            limits,
+            input: Vec::new(),
+            usage: ResourceUsage::default(),
+        }))
+    }
+    
+    fn configure(&mut self, policy: &LimitsPolicy) {
+        self.policy = policy.wasm.clone();
+    }
+}
+
+/// One invocation; the module runs to completion inside `read_output`
+struct WasmModule<'a> {
+    runtime: &'a WasmRuntime,
+    module: Module,
+    config: &'a SpawnConfig,
+    limits: &'a ModuleLimits,
+    input: Vec<u8>,
+    usage: ResourceUsage,
+}
+
+impl SandboxedModule for WasmModule<'_> {
+    fn write_input(&mut self, input: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.input = input.to_vec();
+        Ok(())
+    }
+    
+    fn read_output(&mut self) -> Result<String, Box<dyn Error>> {
+        let (config, limits) = (self.config, self.limits);
+        let stdout = CappedOutput::new(config.max_output_bytes as usize);
+        let mut wasi = WasiCtxBuilder::new();
+        wasi.stdin(MemoryInputPipe::new(std::mem::take(&mut self.input)))
+            .stdout(stdout.clone())
+            .stderr(SinkOutputStream)
+            .args(&[config.module_id.as_str()]);
+        if let Some(state) = &config.tenant_state {
+            for (name, value) in state.env() {
+                wasi.env(name, value);
+            }
+        }
+        for (path, readonly) in preopens(limits) {
+            let (dir_perms, file_perms) = if readonly {
+                (DirPerms::READ, FilePerms::READ)
+            } else {
+                (DirPerms::all(), FilePerms::all())
+            };
+            wasi.preopened_dir(&path, path.to_string_lossy(), dir_perms, file_perms)
+                .map_err(|e| format!("Module '{}': cannot preopen '{}': {}", config.module_id, path.display(), e))?;
+        }
+        
+        let memory = MemoryCap {
+            max_bytes: (limits.max_memory_mb * 1024 * 1024) as usize,
+            exceeded: false,
+            peak_bytes: 0,
+        };
+        let mut store = Store::new(&self.runtime.engine, Invocation { wasi: wasi.build_p1(), memory });
+        store.limiter(|invocation| &mut invocation.memory);
+        let fuel = self.runtime.policy.fuel_per_ms.saturating_mul(config.timeout.as_millis() as u64);
+        store.set_fuel(fuel).map_err(|e| e.to_string())?;
+        store.epoch_deadline_trap();
+        store.set_epoch_deadline((config.timeout.as_millis() / EPOCH_TICK.as_millis()) as u64 + 1);
+        
+        let started = Instant::now();
+        let (linker, module) = (&self.runtime.linker, &self.module);
+        let call = async {
+            let instance = linker.instantiate_async(&mut store, module).await?;
+            let start = instance.get_typed_func::<(), ()>(&mut store, "_start")?;
+            start.call_async(&mut store, ()).await
+        };
+        let outcome = wasmtime_wasi::runtime::in_tokio(async { tokio::time::timeout(config.timeout, call).await });
+        self.usage = ResourceUsage {
+            wall_ms: started.elapsed().as_millis() as u64,
+            cpu_ms: None,
+            peak_memory_bytes: Some(store.data().memory.peak_bytes as u64),
+            fuel: Some(fuel - store.get_fuel().unwrap_or(0)),
+        };
+        
+        let timed_out = || -> Box<dyn Error> {
+            format!("TIMEOUT: Module '{}' did not finish within {} ms", config.module_id, config.timeout.as_millis()).into()
+        };
+        let Ok(outcome) = outcome else {
+            return Err(timed_out());
+        };
+        if let Err(error) = outcome {
+            if stdout.buffer.lock().unwrap().exceeded {
+                return Err(spawn::output_exceeded(config, "stdout"));
+            }
+            if store.data().memory.exceeded {
+                return Err(format!(
+                    "LIMIT_EXCEEDED: Module '{}' needed more than {} MB of memory",
+                    config.module_id, limits.max_memory_mb
+                ).into());
+            }
+            match error.downcast_ref::<Trap>() {
+                Some(Trap::OutOfFuel) => {
+                    return Err(format!(
+                        "LIMIT_EXCEEDED: Module '{}' used up its CPU budget ({} fuel)",
+                        config.module_id, fuel
+                    ).into());
+                }
+                Some(Trap::Interrupt) => return Err(timed_out()),
+                _ => {}
+            }
+            match error.downcast_ref::<I32Exit>() {
+                Some(I32Exit(0)) => {}
+                Some(exit) => return Err(format!("Module '{}' exited with code {}", config.module_id, exit.0).into()),
+                None => return Err(format!("Module '{}' trapped: {}", config.module_id, error).into()),
+            }
+        }
+        
+        let output = std::mem::take(&mut stdout.buffer.lock().unwrap().bytes);
+        String::from_utf8(output)
+            .map_err(|_| format!("Module '{}' wrote output that is not UTF-8", config.module_id).into())
+    }
+    
+    /// Nothing to stop: the module only runs inside `read_output`, bounded by its timeout
+    fn kill(&mut self) {}
+    
+    fn usage(&self) -> ResourceUsage {
+        self.usage.clone()
+    }
+}
+
+/// Directories the module may open, and whether each is read-only
+fn preopens(limits: &ModuleLimits) -> Vec<(PathBuf, bool)> {
+    let writable = limits.allowed_file_paths.iter().flatten().map(|path| (path, false));
+    let readonly = limits.readonly_paths.iter().flatten().map(|path| (path, true));
+    
+    writable.chain(readonly)
+        .map(|(path, readonly)| (PathBuf::from(path), readonly))
+        .filter(|(path, _)| path.is_dir())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sandbox::backend::run;
+    use crate::sandbox::network::NetworkGrant;
+    use std::time::Duration;
+    
+    const WASI_IMPORTS: &str = r#"
+  (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)"#;
+    
+    /// Copies stdin to stdout
+    const ECHO: &str = r#"
+  (func (export "_start")
+    (i32.store (i32.const 0) (i32.const 64))
+    (i32.store (i32.const 4) (i32.const 1024))
+    (drop (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8)))
+    (i32.store (i32.const 4) (i32.load (i32.const 8)))
+    (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))))"#;
+    
+    /// Writes 64 bytes at a time, forever
+    const FLOOD: &str = r#"
+  (func (export "_start")
+    (i32.store (i32.const 0) (i32.const 64))
+    (i32.store (i32.const 4) (i32.const 64))
+    (loop $again
+      (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+      (br $again))))"#;
+    
+    const SPIN: &str = r#"
+  (func (export "_start") (loop $again (br $again))))"#;
+    
+    const GROW: &str = r#"
+  (func (export "_start") (drop (memory.grow (i32.const 64)))))"#;
+    
+    /// Sleeps for 10 s in poll_oneoff: one relative subscription on the monotonic clock
+    const SLEEP: &str = r#"
+  (func (export "_start")
+    (i32.store (i32.const 120) (i32.const 1))
+    (i64.store (i32.const 128) (i64.const 10000000000))
+    (drop (call $poll_oneoff (i32.const 104) (i32.const 200) (i32.const 1) (i32.const 300)))))"#;
+    
+    /// A directory of the test's own for its module files, since tests run in parallel
+    fn test_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cabinet-wasm-{}-{}", test, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+    
+    /// Writes the module as WAT text, which `Module::from_file` also accepts
+    fn module_file(dir: &Path, name: &str, body: &str) -> PathBuf {
+        let path = dir.join(format!("{}.wasm", name));
+        std::fs::write(&path, format!("(module{}{}", WASI_IMPORTS, body)).unwrap();
+        path
+    }
+    
+    fn config(wasm_path: &Path, stdin_data: &str) -> SpawnConfig {
+        let endpoint = format!("wasm:{}", wasm_path.display());
+        SpawnConfig { stdin_data: stdin_data.to_string(), ..SpawnConfig::for_test("pricing", &endpoint) }
+    }
+    
+    fn limits() -> ModuleLimits {
+        ModuleLimits {
+            timeout_ms: 5000,
+            max_memory_mb: 1,
+            max_cpu_percent: 80,
+            max_output_bytes: 1024,
+            max_input_bytes: 1024,
+            allowed_file_paths: None,
+            readonly_paths: None,
+            seccomp_profile: None,
+            network: NetworkGrant::default(),
+        }
+    }
+    
+    fn invoke(runtime: &mut WasmRuntime, wasm_path: &Path, stdin_data: &str) -> (Result<String, Box<dyn Error>>, ResourceUsage) {
+        invoke_config(runtime, config(wasm_path, stdin_data))
+    }
+    
+    fn invoke_config(runtime: &mut WasmRuntime, config: SpawnConfig) -> (Result<String, Box<dyn Error>>, ResourceUsage) {
+        let limits = limits();
+        let started = match runtime.spawn(&config, &limits) {
+            Ok(module) => module,
+            Err(e) => return (Err(e), ResourceUsage::default()),
+        };
+        run(started, &config)
+    }
+    
+    #[test]
+    fn test_envelope_over_stdin_and_stdout() {
+        let mut runtime = WasmRuntime::new().unwrap();
+        let dir = test_dir("envelope");
+        let echo = module_file(&dir, "echo", ECHO);
+        let request = r#"{"status":"success","data":{"price":100}}"#;
+        
+        let (output, usage) = invoke(&mut runtime, &echo, request);
+        assert_eq!(output.unwrap(), request);
+        assert_eq!(usage.peak_memory_bytes, Some(64 * 1024));
+        assert!(usage.fuel.is_some_and(|fuel| fuel > 0));
+        // Served from the compiled module cache the second time
+        assert_eq!(invoke(&mut runtime, &echo, "{}").0.unwrap(), "{}");
+        assert_eq!(runtime.modules.len(), 1);
+        
+        let mut not_wasm = config(&echo, "{}");
+        not_wasm.endpoint = "exec:/opt/pricing/run".to_string();
+        assert!(runtime.spawn(&not_wasm, &limits()).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+    
+    #[test]
+    fn test_limits() {
+        let mut runtime = WasmRuntime::new().unwrap();
+        runtime.policy = WasmPolicy { fuel_per_ms: 10 };
+        let dir = test_dir("limits");
+        
+        let error = invoke(&mut runtime, &module_file(&dir, "spin", SPIN), "").0.unwrap_err();
+        assert!(error.to_string().starts_with("LIMIT_EXCEEDED: Module 'pricing' used up its CPU budget"), "{}", error);
+        
+        let error = invoke(&mut runtime, &module_file(&dir, "grow", GROW), "").0.unwrap_err();
+        assert_eq!(error.to_string(), "LIMIT_EXCEEDED: Module 'pricing' needed more than 1 MB of memory");
+        
+        runtime.policy = WasmPolicy::default();
+        let error = invoke(&mut runtime, &module_file(&dir, "flood", FLOOD), "").0.unwrap_err();
+        assert_eq!(error.to_string(), "LIMIT_EXCEEDED: Module 'pricing' wrote more than 1024 bytes to stdout");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+    
+    #[test]
+    fn test_timeout_stops_running_and_waiting_modules() {
+        let mut runtime = WasmRuntime::new().unwrap();
+        // Fuel to spare, so the clock rather than the CPU budget stops the spinning module
+        runtime.policy = WasmPolicy { fuel_per_ms: u64::MAX / 1_000_000 };
+        let dir = test_dir("timeout");
+        
+        for (name, body) in [("spin", SPIN), ("sleep", SLEEP)] {
+            let mut config = config(&module_file(&dir, name, body), "");
+            config.timeout = Duration::from_millis(200);
+            
+            let started = Instant::now();
+            let error = invoke_config(&mut runtime, config).0.unwrap_err();
+            assert_eq!(error.to_string(), "TIMEOUT: Module 'pricing' did not finish within 200 ms");
+            assert!(started.elapsed() < Duration::from_secs(2), "{} ran for {:?}", name, started.elapsed());
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+    
+    #[test]
+    fn test_preopens_only_existing_directories() {
+        let dir = test_dir("preopens");
+        std::fs::create_dir_all(dir.join("state")).unwrap();
+        std::fs::write(dir.join("routing.yaml"), "").unwrap();
+        let path = |name: &str| dir.join(name).display().to_string();
+        
+        let mut limits = limits();
+        limits.allowed_file_paths = Some(vec![path("state"), path("missing")]);
+        limits.readonly_paths = Some(vec![path("routing.yaml"), dir.display().to_string()]);
+        
+        assert_eq!(preopens(&limits), vec![(dir.join("state"), false), (dir.clone(), true)]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,436 @@
+// Worker Pool
+// Keeps processes of modules that support it warm between requests
+//
+// A module opts in with `runtime.persistent_worker: true` in its manifest; only `exec:`
+// endpoints run as workers. A worker reads requests from stdin and writes results to stdout
+// as frames: a 4-byte big-endian length, then that many bytes of JSON. The
+// CABINET_WORKER_PROTOCOL variable tells the process it runs as a worker.
+//
+// Workers are retired after `max_requests`, once their peak memory reaches
+// `memory_high_water_mb`, or after `idle_timeout_ms` without a request. A worker that times
+// out, exceeds the output cap or dies is discarded, and the next request starts a fresh one.
+// Each invocation keeps its own timeout and output cap; workers are never shared by tenants.
+// Workers get no memory or CPU rlimits: their CPU time adds up over many invocations, and
+// memory_high_water_mb bounds their memory instead.
+
+use crate::config::module_registry::ModuleRegistry;
+use crate::sandbox::backend::ResourceUsage;
+use crate::sandbox::spawn::{self, SpawnConfig};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const WORKER_PROTOCOL_ENV: &str = "CABINET_WORKER_PROTOCOL";
+pub const WORKER_PROTOCOL: &str = "framed-v1";
+
+/// `workers` section of limits.yaml
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkerPolicy {
+    #[serde(default)]
+    pub defaults: WorkerSettings,
+    /// Per-module settings (replace the defaults for that module)
+    #[serde(default)]
+    pub modules: HashMap<String, WorkerSettings>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct WorkerSettings {
+    /// Warm workers kept per module endpoint (and tenant)
+    pub pool_size: usize,
+    /// Requests a worker serves before it is replaced
+    pub max_requests: u32,
+    /// Peak resident memory at which a worker is replaced after its current request
+    pub memory_high_water_mb: u64,
+    /// How long an unused worker is kept
+    pub idle_timeout_ms: u64,
+}
+
+impl Default for WorkerSettings {
+    fn default() -> Self {
+        WorkerSettings {
+            pool_size: 2,
+            max_requests: 500,
+            memory_high_water_mb: 256,
+            idle_timeout_ms: 60000,
+        }
+    }
+}
+
+impl WorkerPolicy {
+    /// Re-keys `modules` by canonical module ID
+    pub fn resolve_modules(&mut self, modules: &ModuleRegistry) -> Result<(), Box<dyn Error>> {
+        self.modules = modules.rekey(std::mem::take(&mut self.modules), "Limits policy: workers")?;
+        Ok(())
+    }
+    
+    /// Settings for a module (by canonical ID)
+    pub fn settings_for(&self, module_id: &str) -> &WorkerSettings {
+        self.modules.get(module_id).unwrap_or(&self.defaults)
+    }
+}
+
+/// What a worker's stdout reader saw
+enum Frame {
+    Data(Vec<u8>),
+    /// A frame announced a length over the invocation's output cap
+    TooLarge,
+    /// stdout closed: the worker exited or broke the framing
+    Closed,
+}
+
+struct Worker {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    frames: Receiver<Frame>,
+    /// Output cap of the current invocation, read by the stdout reader
+    output_cap: Arc<AtomicU64>,
+    requests: u32,
+    last_used: Instant,
+}
+
+impl Worker {
+    fn start(config: &SpawnConfig, command_line: &str) -> Result<Self, Box<dyn Error>> {
+        let mut child = spawn::start_process(config, command_line, &[(WORKER_PROTOCOL_ENV, WORKER_PROTOCOL)], true, None)?;
+        let stdin = child.stdin.take().ok_or("Worker stdin missing")?;
+        let stdout = child.stdout.take().ok_or("Worker stdout missing")?;
+        let mut stderr = child.stderr.take().ok_or("Worker stderr missing")?;
+        
+        // stderr is discarded in worker mode; it is drained so the worker never blocks on it
+        std::thread::spawn(move || std::io::copy(&mut stderr, &mut std::io::sink()));
+        let output_cap = Arc::new(AtomicU64::new(config.max_output_bytes));
+        let (frames_tx, frames) = mpsc::channel();
+        let cap = Arc::clone(&output_cap);
+        std::thread::spawn(move || read_frames(stdout, cap, frames_tx));
+        
+        Ok(Worker {
+            child,
+            stdin: Some(stdin),
+            frames,
+            output_cap,
+            requests: 0,
+            last_used: Instant::now(),
+        })
+    }
+    
+    fn is_alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+    
+    /// Sends one request and waits for its result under the invocation's limits
+    /// On error the worker must be discarded
+    fn call(&mut self, config: &SpawnConfig) -> Result<String, Box<dyn Error>> {
+        let deadline = Instant::now() + config.timeout;
+        let timed_out = || -> Box<dyn Error> {
+            format!("TIMEOUT: Module '{}' did not finish within {} ms", config.module_id, config.timeout.as_millis()).into()
+        };
+        self.requests += 1;
+        self.output_cap.store(config.max_output_bytes, Ordering::SeqCst);
+        
+        // Written off-thread: a worker that stops reading must not block past the timeout
+        let mut stdin = self.stdin.take().ok_or("Worker stdin missing")?;
+        let request = config.stdin_data.clone().into_bytes();
+        let writer = std::thread::spawn(move || {
+            let length = u32::try_from(request.len()).map_err(|_| std::io::Error::other("request too large"))?;
+            stdin.write_all(&length.to_be_bytes())?;
+            stdin.write_all(&request)?;
+            stdin.flush().map(|_| stdin)
+        });
+        
+        let frame = match self.frames.recv_timeout(config.timeout) {
+            Ok(frame) => frame,
+            Err(RecvTimeoutError::Timeout) => return Err(timed_out()),
+            Err(RecvTimeoutError::Disconnected) => Frame::Closed,
+        };
+        
+        match frame {
+            Frame::Data(output) => {
+                let stdin = writer.join().map_err(|_| "Worker request writer panicked")??;
+                self.stdin = Some(stdin);
+                self.last_used = Instant::now();
+                String::from_utf8(output)
+                    .map_err(|_| format!("Module '{}' wrote output that is not UTF-8", config.module_id).into())
+            }
+            Frame::TooLarge => Err(spawn::output_exceeded(config, "stdout")),
+            // A worker that closed stdout but keeps running is waited for only until the deadline
+            Frame::Closed => loop {
+                if let Some(status) = self.child.try_wait()? {
+                    return Err(spawn::exit_error(config, status));
+                }
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err(timed_out());
+                }
+                std::thread::sleep(spawn::POLL_INTERVAL.min(deadline - now));
+            },
+        }
+    }
+    
+    /// Peak resident memory so far, from /proc
+    fn peak_memory_kb(&self) -> Option<u64> {
+        let status = std::fs::read_to_string(format!("/proc/{}/status", self.child.id())).ok()?;
+        status.lines()
+            .find_map(|line| line.strip_prefix("VmHWM:"))
+            .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+    }
+    
+    fn should_retire(&self, settings: &WorkerSettings) -> bool {
+        self.requests >= settings.max_requests
+            || self.peak_memory_kb().is_some_and(|kb| kb >= settings.memory_high_water_mb * 1024)
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        spawn::kill_process(&mut self.child);
+    }
+}
+
+fn read_frames(mut stdout: ChildStdout, cap: Arc<AtomicU64>, frames: Sender<Frame>) {
+    loop {
+        let mut header = [0u8; 4];
+        if stdout.read_exact(&mut header).is_err() {
+            let _ = frames.send(Frame::Closed);
+            return;
+        }
+        // Checked before anything is read, so an oversized result is never buffered
+        let length = u32::from_be_bytes(header) as u64;
+        if length > cap.load(Ordering::SeqCst) {
+            let _ = frames.send(Frame::TooLarge);
+            return;
+        }
+        let mut body = vec![0u8; length as usize];
+        if stdout.read_exact(&mut body).is_err() {
+            let _ = frames.send(Frame::Closed);
+            return;
+        }
+        if frames.send(Frame::Data(body)).is_err() {
+            return;
+        }
+    }
+}
+
+#[derive(Default)]
+struct WorkerPool {
+    idle: Vec<Worker>,
+    idle_timeout: Duration,
+}
+
+/// Warm workers per exec endpoint and tenant
+#[derive(Default)]
+pub struct WorkerPools {
+    pools: HashMap<String, WorkerPool>,
+}
+
+impl WorkerPools {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Runs one invocation on a warm worker, starting one if none is idle
+    /// Endpoints other than `exec:` are not kept warm and go to `spawn_module`
+    /// Usage is the call's wall time and, for a worker, its peak memory since it started
+    pub fn invoke(
+        &mut self,
+        config: SpawnConfig,
+        settings: &WorkerSettings,
+        tenant_id: Option<&str>,
+    ) -> (Result<String, Box<dyn Error>>, ResourceUsage) {
+        let started = Instant::now();
+        let mut usage = ResourceUsage::default();
+        let result = match config.endpoint.strip_prefix("exec:") {
+            Some(command_line) => self.call_worker(&config, command_line, settings, tenant_id, &mut usage),
+            None => spawn::spawn_module(config),
+        };
+        usage.wall_ms = started.elapsed().as_millis() as u64;
+        (result, usage)
+    }
+    
+    fn call_worker(
+        &mut self,
+        config: &SpawnConfig,
+        command_line: &str,
+        settings: &WorkerSettings,
+        tenant_id: Option<&str>,
+        usage: &mut ResourceUsage,
+    ) -> Result<String, Box<dyn Error>> {
+        self.evict_idle(Instant::now());
+        
+        let key = format!("{}#{}", config.endpoint, tenant_id.unwrap_or_default());
+        let pool = self.pools.entry(key).or_default();
+        pool.idle_timeout = Duration::from_millis(settings.idle_timeout_ms);
+        // Workers that died while idle are replaced without the caller noticing
+        let mut worker = loop {
+            match pool.idle.pop() {
+                Some(mut worker) => {
+                    if worker.is_alive() {
+                        break worker;
+                    }
+                }
+                None => break Worker::start(config, command_line)?,
+            }
+        };
+        
+        let result = worker.call(config);
+        usage.peak_memory_bytes = worker.peak_memory_kb().map(|kb| kb * 1024);
+        
+        if result.is_ok() && !worker.should_retire(settings) && pool.idle.len() < settings.pool_size {
+            pool.idle.push(worker);
+        }
+        result
+    }
+    
+    /// Drops workers idle for longer than their module's `idle_timeout_ms`
+    pub fn evict_idle(&mut self, now: Instant) {
+        for pool in self.pools.values_mut() {
+            let idle_timeout = pool.idle_timeout;
+            pool.idle.retain(|worker| now.duration_since(worker.last_used) < idle_timeout);
+        }
+        self.pools.retain(|_, pool| !pool.idle.is_empty());
+    }
+    
+    /// Drops all idle workers, e.g. after a policy reload changed their sandbox
+    pub fn clear(&mut self) {
+        self.pools.clear();
+    }
+    
+    /// Idle workers across all pools
+    pub fn idle_count(&self) -> usize {
+        self.pools.values().map(|pool| pool.idle.len()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::path::PathBuf;
+    
+    /// Answers every frame with its pid and request count; `mode` changes its behaviour
+    const ECHO_WORKER: &str = r#"
+n=0
+while header=$(dd bs=1 count=4 2>/dev/null | od -An -tu1); [ -n "$header" ]; do
+  set -- $header
+  size=$(( ($1 << 24) | ($2 << 16) | ($3 << 8) | $4 ))
+  request=$(dd bs=1 count=$size 2>/dev/null)
+  n=$((n + 1))
+  case "$request" in
+    *crash*) exit 3 ;;
+    *hang*) sleep 10 ;;
+    *detach*) exec 1>&-; sleep 10 ;;
+    *flood*) body=$(printf '%02000d' 0) ;;
+    *) body="{\"pid\":$$,\"requests\":$n,\"protocol\":\"$CABINET_WORKER_PROTOCOL\"}" ;;
+  esac
+  length=${#body}
+  printf "\\$(printf %03o $((length >> 24 & 255)))\\$(printf %03o $((length >> 16 & 255)))"
+  printf "\\$(printf %03o $((length >> 8 & 255)))\\$(printf %03o $((length & 255)))%s" "$body"
+done
+"#;
+    
+    /// Writes the worker script to a path of the test's own, since tests run in parallel
+    fn worker_script(test: &str) -> (PathBuf, String) {
+        let path = std::env::temp_dir().join(format!("cabinet-worker-{}-{}.sh", test, std::process::id()));
+        std::fs::write(&path, ECHO_WORKER).unwrap();
+        let endpoint = format!("exec:/bin/bash {}", path.display());
+        (path, endpoint)
+    }
+    
+    fn call(pools: &mut WorkerPools, endpoint: &str, request: &str, settings: &WorkerSettings) -> Result<Value, String> {
+        let config = SpawnConfig { stdin_data: request.to_string(), ..SpawnConfig::for_test("storage", endpoint) };
+        pools.invoke(config, settings, Some("dealer-a")).0
+            .map(|output| serde_json::from_str(&output).unwrap())
+            .map_err(|e| e.to_string())
+    }
+    
+    #[test]
+    fn test_worker_stays_warm_and_is_recycled() {
+        let (script, endpoint) = worker_script("recycled");
+        let settings = WorkerSettings { max_requests: 3, ..WorkerSettings::default() };
+        let mut pools = WorkerPools::new();
+        
+        let first = call(&mut pools, &endpoint, "{}", &settings).unwrap();
+        assert_eq!(first["protocol"], "framed-v1");
+        let second = call(&mut pools, &endpoint, "{}", &settings).unwrap();
+        assert_eq!(second["pid"], first["pid"]);
+        assert_eq!(second["requests"], 2);
+        
+        // The third request is the last one this worker serves
+        call(&mut pools, &endpoint, "{}", &settings).unwrap();
+        assert_eq!(pools.idle_count(), 0);
+        let fourth = call(&mut pools, &endpoint, "{}", &settings).unwrap();
+        assert_ne!(fourth["pid"], first["pid"]);
+        assert_eq!(fourth["requests"], 1);
+        std::fs::remove_file(script).unwrap();
+    }
+    
+    #[test]
+    fn test_crash_recovery() {
+        let (script, endpoint) = worker_script("crash");
+        let settings = WorkerSettings::default();
+        let mut pools = WorkerPools::new();
+        
+        let first = call(&mut pools, &endpoint, "{}", &settings).unwrap();
+        let error = call(&mut pools, &endpoint, "crash", &settings).unwrap_err();
+        assert!(error.contains("exited with"), "{}", error);
+        assert!(crate::sandbox::retry::is_transient(&error));
+        
+        let next = call(&mut pools, &endpoint, "{}", &settings).unwrap();
+        assert_ne!(next["pid"], first["pid"]);
+        
+        // A worker killed while idle is replaced on the next request
+        let pid = next["pid"].as_i64().unwrap() as libc::pid_t;
+        unsafe { libc::kill(pid, libc::SIGKILL) };
+        std::thread::sleep(Duration::from_millis(50));
+        let replaced = call(&mut pools, &endpoint, "{}", &settings).unwrap();
+        assert_ne!(replaced["pid"], next["pid"]);
+        std::fs::remove_file(script).unwrap();
+    }
+    
+    #[test]
+    fn test_invocation_limits() {
+        let (script, endpoint) = worker_script("limits");
+        let settings = WorkerSettings::default();
+        let mut pools = WorkerPools::new();
+        
+        let error = call(&mut pools, &endpoint, "flood", &settings).unwrap_err();
+        assert_eq!(error, "LIMIT_EXCEEDED: Module 'storage' wrote more than 1024 bytes to stdout");
+        assert_eq!(pools.idle_count(), 0);
+        
+        let mut hanging = SpawnConfig { stdin_data: "hang".to_string(), ..SpawnConfig::for_test("storage", &endpoint) };
+        hanging.timeout = Duration::from_millis(100);
+        let (result, usage) = pools.invoke(hanging, &settings, None);
+        let error = result.unwrap_err();
+        assert!(usage.wall_ms >= 100);
+        assert!(error.to_string().starts_with("TIMEOUT"), "{}", error);
+        assert_eq!(pools.idle_count(), 0);
+        
+        // Closing stdout does not get a worker out of the timeout
+        let mut detached = SpawnConfig { stdin_data: "detach".to_string(), ..SpawnConfig::for_test("storage", &endpoint) };
+        detached.timeout = Duration::from_millis(100);
+        let started = Instant::now();
+        let error = pools.invoke(detached, &settings, None).0.unwrap_err();
+        assert!(error.to_string().starts_with("TIMEOUT"), "{}", error);
+        assert!(started.elapsed() < Duration::from_secs(2));
+        std::fs::remove_file(script).unwrap();
+    }
+    
+    #[test]
+    fn test_idle_eviction() {
+        let (script, endpoint) = worker_script("eviction");
+        let settings = WorkerSettings { idle_timeout_ms: 0, ..WorkerSettings::default() };
+        let mut pools = WorkerPools::new();
+        
+        call(&mut pools, &endpoint, "{}", &settings).unwrap();
+        assert_eq!(pools.idle_count(), 1);
+        pools.evict_idle(Instant::now());
+        assert_eq!(pools.idle_count(), 0);
+        std::fs::remove_file(script).unwrap();
+    }
+}
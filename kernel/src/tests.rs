@@ -90,7 +90,9 @@ mod attack_tests {
         let auth_context = authz::authorize::extract_auth_context(&command).unwrap();
         
         // Load actual policies
-        let roles = authz::roles::load_roles().unwrap();
+        let roles = authz::roles::load_roles()
+            .and_then(authz::roles::RoleIndex::compile)
+            .unwrap();
         let requirements = authz::capabilities::load_capability_requirements().unwrap();
         
         let result = authz::authorize::authorize(
@@ -100,8 +102,8 @@ mod attack_tests {
             &requirements,
         );
         
-        assert!(result.is_err(), "Viewer should not be able to delete");
-        assert!(result.unwrap_err().to_string().contains("PERMISSION_DENIED"));
+        assert!(!result.is_allowed(), "Viewer should not be able to delete");
+        assert!(result.reason().contains("PERMISSION_DENIED"));
     }
 
     #[test]
@@ -123,7 +125,9 @@ mod attack_tests {
         });
         
         let auth_context = authz::authorize::extract_auth_context(&command).unwrap();
-        let roles = authz::roles::load_roles().unwrap();
+        let roles = authz::roles::load_roles()
+            .and_then(authz::roles::RoleIndex::compile)
+            .unwrap();
         let requirements = authz::capabilities::load_capability_requirements().unwrap();
         
         let result = authz::authorize::authorize(
@@ -133,7 +137,7 @@ mod attack_tests {
             &requirements,
         );
         
-        assert!(result.is_err(), "Missing scope should deny access");
+        assert!(!result.is_allowed(), "Missing scope should deny access");
     }
 
     #[test]
@@ -155,7 +159,9 @@ mod attack_tests {
         });
         
         let auth_context = authz::authorize::extract_auth_context(&command).unwrap();
-        let roles = authz::roles::load_roles().unwrap();
+        let roles = authz::roles::load_roles()
+            .and_then(authz::roles::RoleIndex::compile)
+            .unwrap();
         let requirements = authz::capabilities::load_capability_requirements().unwrap();
         
         let result = authz::authorize::authorize(
@@ -165,7 +171,7 @@ mod attack_tests {
             &requirements,
         );
         
-        assert!(result.is_err(), "Undefined capability should be denied by default");
+        assert!(!result.is_allowed(), "Undefined capability should be denied by default");
     }
 
     // ========================================
@@ -181,6 +187,10 @@ mod attack_tests {
             actor_type: "user".to_string(),
             role: "admin".to_string(),
             scopes: vec!["storage:write".to_string()],
+            args: serde_json::Value::Null,
+            request_time: chrono::Utc::now(),
+            tenant_id: None,
+            caller: Default::default(),
         };
         
         let result = routing::authorize_route::authorize_route(
@@ -194,8 +204,8 @@ mod attack_tests {
             None,
         );
         
-        assert!(result.is_err(), "Route without allowlist edge should be denied");
-        assert!(result.unwrap_err().to_string().contains("ROUTING_DENIED"));
+        assert!(!result.is_allowed(), "Route without allowlist edge should be denied");
+        assert!(result.reason().contains("ROUTING_DENIED"));
     }
 
     #[test]
@@ -207,6 +217,10 @@ mod attack_tests {
             actor_type: "user".to_string(),
             role: "admin".to_string(),
             scopes: vec!["admin".to_string()],
+            args: serde_json::Value::Null,
+            request_time: chrono::Utc::now(),
+            tenant_id: None,
+            caller: Default::default(),
         };
         
         // Try to route storage.imports.register directly from UI (should be internal only)
@@ -222,7 +236,7 @@ mod attack_tests {
         );
         
         // This should fail because it's not in the allowed capabilities for main_ui -> storage
-        assert!(result.is_err(), "Internal capability should not be directly routable from UI");
+        assert!(!result.is_allowed(), "Internal capability should not be directly routable from UI");
     }
 
     #[test]
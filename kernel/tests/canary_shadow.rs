@@ -0,0 +1,167 @@
+// Canary Shadow Pipeline Tests
+// Requests run through the kernel on a route with a shadow canary; the fake backend answers
+// for the module, so the stable and candidate versions can be scripted separately
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ed25519_dalek::SigningKey;
+use kernel::authz::tokens::{self, sign_token, ActorClaims, SigningMaterial};
+use kernel::config::policy_set::{PolicySet, PolicySources};
+use kernel::sandbox::backend::{BackendKind, FakeBackend, FakeCall};
+use kernel::Kernel;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const STABLE_RESULT: &str = r#"{"status":"success","data":{"id":"listing-1"}}"#;
+
+/// storage.listings.get in v1.0.0 and v2.0.0, one instance that leaves rotation on its first failure
+const MANIFEST: &str = r#"
+module:
+  id: storage-module
+  name: "Storage Module"
+capabilities:
+  - id: storage.listings.get
+    handler: "handlers/get.handler"
+    version: v1.0.0
+  - id: storage.listings.get
+    handler: "handlers/get_v2.handler"
+    version: v2.0.0
+endpoints:
+  invoke: "unix:///run/storage.sock"
+  health: "unix:///run/storage.sock"
+  instances:
+    - id: storage-1
+      invoke: "unix:///run/storage-1.sock"
+  health_check:
+    interval_ms: 3600000
+    unhealthy_threshold: 1
+"#;
+
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cabinet-canary-shadow-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(dir.join("modules/storage")).unwrap();
+    dir
+}
+
+/// Repository policy with a test signing key, modules and storage state under `dir`, storage
+/// on the jailed process backend, and every storage request on main-ui-to-storage shadowed to v2.0.0
+fn policy(dir: &Path, signing_key: &SigningKey) -> PolicySet {
+    let policy_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../system/policy");
+    let mut sources = PolicySources::read_dir(&policy_dir).unwrap();
+    
+    let public_key = STANDARD.encode(signing_key.verifying_key().to_bytes());
+    sources.insert(tokens::KEYRING_SOURCE, &format!("keys:\n  - kid: test\n    alg: EdDSA\n    public_key: \"{}\"\n", public_key));
+    sources.insert(tokens::REVOCATION_LIST_SOURCE, "revoked_token_ids: []\n");
+    
+    let mut routing: serde_yaml::Value = serde_yaml::from_str(sources.get("routing.yaml").unwrap()).unwrap();
+    let route = routing["routes"].as_sequence_mut().unwrap().iter_mut()
+        .find(|route| route["id"].as_str() == Some("main-ui-to-storage"))
+        .unwrap();
+    route["canary"] = serde_yaml::from_str("{ stable: v1.0.0, candidate: v2.0.0, weight_percent: 100, mode: shadow }").unwrap();
+    sources.insert("routing.yaml", &serde_yaml::to_string(&routing).unwrap());
+    
+    let mut limits: serde_yaml::Value = serde_yaml::from_str(sources.get("limits.yaml").unwrap()).unwrap();
+    limits["sandbox"]["modules"] = serde_yaml::from_str("{ storage: jailed_process }").unwrap();
+    let state = dir.join("state").display().to_string();
+    limits["module_limits"]["storage-module"]["allowed_file_paths"] = serde_yaml::from_str(&format!("['{}']", state)).unwrap();
+    sources.insert("limits.yaml", &serde_yaml::to_string(&limits).unwrap());
+    
+    let modules = format!("{}\nmodules_dir: {}\n", sources.get("modules.yaml").unwrap(), dir.join("modules").display());
+    sources.insert("modules.yaml", &modules);
+    
+    std::fs::write(dir.join("modules/storage/manifest.yaml"), MANIFEST).unwrap();
+    PolicySet::from_sources(&sources).unwrap()
+}
+
+fn kernel(dir: &Path, fake: FakeBackend) -> (Kernel, SigningKey, Arc<Mutex<Vec<FakeCall>>>) {
+    let signing_key = SigningKey::from_bytes(&[5u8; 32]);
+    let mut kernel = Kernel::from_policy(policy(dir, &signing_key)).unwrap();
+    let calls = fake.calls();
+    // The fake answers in place of the backend storage runs on
+    kernel.set_backend(BackendKind::JailedProcess, Box::new(fake));
+    (kernel, signing_key, calls)
+}
+
+fn request(signing_key: &SigningKey, timeout_ms: u64) -> String {
+    let now = chrono::Utc::now().timestamp();
+    let claims = ActorClaims {
+        sub: "user-123".to_string(),
+        typ: "user".to_string(),
+        roles: vec!["admin".to_string()],
+        scopes: vec!["storage:read".to_string()],
+        aud: "cabinet-kernel".to_string(),
+        exp: now + 600,
+        iat: now,
+        jti: uuid::Uuid::new_v4().to_string(),
+        tenant_id: None,
+    };
+    let token = sign_token(&claims, "test", &SigningMaterial::Ed25519(signing_key.clone())).unwrap();
+    
+    json!({
+        "version": "v1.0.0",
+        "message_id": uuid::Uuid::new_v4().to_string(),
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "message_type": "command",
+        "payload": {
+            "command_type": "query",
+            "target": { "capability": "storage.listings.get" },
+            "args": { "id": "listing-1" },
+            "context": { "actor_token": token },
+            "options": { "timeout_ms": timeout_ms }
+        }
+    }).to_string()
+}
+
+fn version(call: &FakeCall) -> String {
+    let command: Value = serde_json::from_str(&call.input).unwrap();
+    command["target"]["version"].as_str().unwrap().to_string()
+}
+
+#[test]
+fn test_failing_shadow_leaves_stable_instance_healthy() {
+    let dir = temp_dir();
+    let fake = FakeBackend::default()
+        .reply("storage", STABLE_RESULT)
+        .fail("storage@v2.0.0", "Module 'storage' exited with code 1");
+    let (mut kernel, signing_key, calls) = kernel(&dir, fake);
+    
+    // The instance leaves rotation on its first failure, so a shadow failure counted against
+    // it would fail every request after the first
+    for _ in 0..3 {
+        let response: Value = serde_json::from_str(&kernel.process_request(&request(&signing_key, 2000)).unwrap()).unwrap();
+        assert_eq!(response["message_type"], "result", "{}", response);
+        assert_eq!(response["payload"]["data"]["data"]["id"], "listing-1", "{}", response);
+    }
+    
+    kernel.run_shadows();
+    
+    let calls = calls.lock().unwrap();
+    let versions: Vec<String> = calls.iter().map(version).collect();
+    assert_eq!(versions, ["v1.0.0", "v2.0.0", "v1.0.0", "v2.0.0", "v1.0.0", "v2.0.0"]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_shadow_runs_after_the_response() {
+    let dir = temp_dir();
+    let fake = FakeBackend::default()
+        .reply("storage", STABLE_RESULT)
+        .reply("storage@v2.0.0", r#"{"status":"success","data":{"id":"listing-2"}}"#);
+    let (mut kernel, signing_key, calls) = kernel(&dir, fake);
+    
+    // The request is answered by the stable version alone
+    kernel.process_request(&request(&signing_key, 2000)).unwrap();
+    assert_eq!(calls.lock().unwrap().len(), 1);
+    
+    // The shadow then runs on the same instance, with a full time budget of its own
+    kernel.run_shadows();
+    let calls = calls.lock().unwrap();
+    assert_eq!(calls.len(), 2);
+    let (stable, shadow) = (&calls[0], &calls[1]);
+    assert_eq!(version(shadow), "v2.0.0");
+    assert_eq!(shadow.endpoint, stable.endpoint);
+    assert!(stable.timeout <= std::time::Duration::from_millis(2000));
+    assert_eq!(shadow.timeout, std::time::Duration::from_millis(2000));
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,84 @@
+// Policy Conditions Conformance Tests
+// Runs shared/conformance/suites/policy_conditions.yaml against the kernel's authz code
+
+use chrono::{DateTime, Utc};
+use kernel::authz::authorize::AuthContext;
+use kernel::authz::capabilities::{check_capability_allowed, CapabilityRequirement};
+use kernel::authz::conditions::Condition;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+const CAPABILITY: &str = "conformance.capability";
+
+fn load_suite() -> Value {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../shared/conformance/suites/policy_conditions.yaml");
+    let content = fs::read_to_string(&path).expect("Failed to read policy_conditions suite");
+    serde_yaml::from_str(&content).expect("Failed to parse policy_conditions suite")
+}
+
+fn string_list(value: &Value) -> Vec<String> {
+    value.as_array()
+        .map(|arr| arr.iter().filter_map(|v| v.as_str()).map(|s| s.to_string()).collect())
+        .unwrap_or_default()
+}
+
+fn build_context(request: &Value) -> AuthContext {
+    let actor = &request["actor"];
+    let time: DateTime<Utc> = request["time"].as_str()
+        .expect("test request must set time")
+        .parse()
+        .expect("invalid test time");
+    
+    AuthContext {
+        actor_id: actor["id"].as_str().unwrap_or_default().to_string(),
+        actor_type: actor["type"].as_str().unwrap_or_default().to_string(),
+        role: string_list(&actor["roles"]).first().cloned().unwrap_or_default(),
+        scopes: string_list(&actor["scopes"]),
+        args: request.get("args").cloned().unwrap_or(Value::Null),
+        request_time: time,
+        tenant_id: None,
+        caller: Default::default(),
+    }
+}
+
+#[test]
+fn test_policy_conditions_suite() {
+    let suite = load_suite();
+    let tests = suite["tests"].as_array().expect("suite must have tests");
+    assert!(!tests.is_empty());
+    
+    for case in tests {
+        let id = case["id"].as_str().unwrap();
+        let requirement: CapabilityRequirement = serde_json::from_value(case["requirement"].clone())
+            .unwrap_or_else(|e| panic!("{}: invalid requirement: {}", id, e));
+        
+        let mut requirements = HashMap::new();
+        requirements.insert(CAPABILITY.to_string(), requirement);
+        
+        let context = build_context(&case["request"]);
+        let result = check_capability_allowed(CAPABILITY, &context, &requirements);
+        
+        match case["expected"].as_str().unwrap() {
+            "ALLOW" => assert!(result.is_ok(), "{}: expected ALLOW, got {:?}", id, result.err().map(|e| e.to_string())),
+            "DENY" => {
+                let err = result.err().unwrap_or_else(|| panic!("{}: expected DENY", id)).to_string();
+                if let Some(code) = case["error_code"].as_str() {
+                    assert!(err.contains(code), "{}: expected {} in '{}'", id, code, err);
+                }
+            }
+            other => panic!("{}: unknown expectation {}", id, other),
+        }
+    }
+}
+
+#[test]
+fn test_policy_conditions_invalid_rejected() {
+    let suite = load_suite();
+    
+    for source in string_list(&suite["invalid_conditions"]) {
+        assert!(Condition::parse(&source).is_err(), "'{}' should not parse", source);
+    }
+}
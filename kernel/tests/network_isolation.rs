@@ -0,0 +1,91 @@
+// Network Isolation Integration Tests
+// Rust counterpart of tests/test-network-isolation.sh, for modules the kernel runs itself:
+// each module process gets its own network namespace and only what its limits grant
+
+use kernel::sandbox::limits::parse_limits;
+use kernel::sandbox::network::NetworkGrant;
+use kernel::sandbox::seccomp::SyscallFilter;
+use kernel::sandbox::spawn::{spawn_module, SpawnConfig};
+use std::io::Read;
+use std::os::unix::net::UnixListener;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Writes a bash script for the module to run; endpoints do not support quoting
+fn probe(name: &str, script: &str) -> (PathBuf, String) {
+    let dir = std::env::temp_dir().join(format!("cabinet-netns-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("probe.sh");
+    std::fs::write(&path, script).unwrap();
+    let endpoint = format!("exec:/bin/bash {}", path.display());
+    (dir, endpoint)
+}
+
+fn run(endpoint: &str, network: NetworkGrant, syscall_filter: Option<SyscallFilter>) -> Result<String, String> {
+    spawn_module(SpawnConfig {
+        timeout: Duration::from_secs(10),
+        max_output_bytes: 4096,
+        syscall_filter,
+        network,
+        ..SpawnConfig::for_test("pricing", endpoint)
+    }).map_err(|e| e.to_string())
+}
+
+#[test]
+fn test_module_sees_only_its_own_loopback() {
+    // Like "adapters are on mesh network only": no host interface is visible
+    let (dir, endpoint) = probe("interfaces", "tail -n +3 /proc/net/dev | cut -d: -f1 | tr -d ' '\n");
+    let output = run(&endpoint, NetworkGrant::default(), None).unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    
+    assert_eq!(output.lines().collect::<Vec<_>>(), vec!["lo"]);
+}
+
+#[test]
+fn test_no_network_by_default() {
+    // Like "adapter cannot reach UI": opening any socket is a violation
+    let (dir, endpoint) = probe("none", "echo ping >/dev/tcp/127.0.0.1/80\n");
+    let error = run(&endpoint, NetworkGrant::default(), None).unwrap_err();
+    std::fs::remove_dir_all(dir).unwrap();
+    
+    assert!(error.starts_with("SECURITY_VIOLATION"), "{}", error);
+    assert!(error.contains("no network"), "{}", error);
+}
+
+#[test]
+fn test_loopback_grant() {
+    // lo is up (the connection is refused rather than unreachable); nothing else is there.
+    // The repository's default seccomp profile is installed too: the grant alone decides
+    let script = "{ echo ping >/dev/tcp/127.0.0.1/9; } 2>&1\n{ echo ping >/dev/tcp/10.0.0.1/80; } 2>&1\ntrue\n";
+    let (dir, endpoint) = probe("loopback", script);
+    let limits = parse_limits(include_str!("../../system/policy/limits.yaml")).unwrap();
+    let filter = limits.seccomp.filter_for(None).unwrap();
+    assert!(filter.is_some());
+    let output = run(&endpoint, NetworkGrant { loopback: true, unix_sockets: vec![] }, filter).unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    
+    assert!(output.contains("Connection refused"), "{}", output);
+    assert!(output.contains("Network is unreachable"), "{}", output);
+}
+
+#[test]
+fn test_unix_socket_grant() {
+    // Like "only platform can communicate with adapters": the module talks to the one
+    // socket it was granted, over the descriptor the kernel passed it
+    let (dir, endpoint) = probe("unix", "echo \"$CABINET_UNIX_SOCKETS\"\necho hello >&3\n");
+    let socket_path = dir.join("platform.sock");
+    let listener = UnixListener::bind(&socket_path).unwrap();
+    let grant = NetworkGrant {
+        loopback: false,
+        unix_sockets: vec![socket_path.display().to_string()],
+    };
+    
+    let output = run(&endpoint, grant, None).unwrap();
+    let (mut connection, _) = listener.accept().unwrap();
+    let mut received = String::new();
+    connection.read_to_string(&mut received).unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    
+    assert_eq!(output.trim(), format!("{}=3", socket_path.display()));
+    assert_eq!(received, "hello\n");
+}
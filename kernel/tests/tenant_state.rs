@@ -0,0 +1,87 @@
+// Tenant State Integration Tests
+// A module invoked for one tenant works in that tenant's state directory and cannot see
+// any other tenant's; nor can a module invoked without a tenant
+
+use kernel::sandbox::limits::{partition_for_tenant, ModuleLimits};
+use kernel::sandbox::spawn::{spawn_module, SpawnConfig};
+use kernel::sandbox::tenant_state::TenantState;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Module state lives in `<dir>/state`; the module runs `<dir>/probe.sh`
+fn module_dir(name: &str, script: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cabinet-tenants-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(dir.join("state")).unwrap();
+    std::fs::write(dir.join("probe.sh"), script).unwrap();
+    dir
+}
+
+fn run(dir: &Path, tenant_id: Option<&str>) -> Result<String, String> {
+    let limits: ModuleLimits = serde_yaml::from_str(&format!(
+        "{{ timeout_ms: 10000, max_memory_mb: 64, max_cpu_percent: 50, max_output_bytes: 4096, max_input_bytes: 4096, allowed_file_paths: ['{}'] }}",
+        dir.join("state").display()
+    )).unwrap();
+    let limits = partition_for_tenant(&limits, tenant_id);
+    let tenant_state = TenantState::prepare("pricing", tenant_id, &limits).map_err(|e| e.to_string())?;
+    
+    let endpoint = format!("exec:/bin/bash {}", dir.join("probe.sh").display());
+    spawn_module(SpawnConfig {
+        timeout: Duration::from_secs(10),
+        max_output_bytes: 4096,
+        tenant_state: Some(tenant_state),
+        ..SpawnConfig::for_test("pricing", &endpoint)
+    }).map_err(|e| e.to_string())
+}
+
+#[test]
+fn test_tenant_sees_only_its_own_state() {
+    // Each tenant writes a secret to its state, then looks for everyone's
+    let script = r#"
+echo "secret of $CABINET_TENANT_ID" > "$CABINET_STATE_DIRS/secret"
+state="${CABINET_STATE_DIRS%/tenants/*}"
+echo "tenants: $(ls "$state/tenants" | tr '\n' ' ')"
+cat "$state"/tenants/*/secret
+"#;
+    let dir = module_dir("isolation", script);
+    
+    let dealer_b = run(&dir, Some("dealer-b")).unwrap();
+    let dealer_a = run(&dir, Some("dealer-a")).unwrap();
+    // The kernel itself still sees both partitions
+    let secret_b = std::fs::read_to_string(dir.join("state/tenants/dealer-b/secret"));
+    std::fs::remove_dir_all(&dir).unwrap();
+    
+    assert_eq!(dealer_b, "tenants: dealer-b \nsecret of dealer-b\n");
+    assert_eq!(dealer_a, "tenants: dealer-a \nsecret of dealer-a\n");
+    assert_eq!(secret_b.unwrap(), "secret of dealer-b\n");
+}
+
+#[test]
+fn test_tenantless_module_cannot_see_tenant_state() {
+    let script = r#"
+state="${CABINET_STATE_DIRS%/tenants/*}"
+echo "tenant: ${CABINET_TENANT_ID:-none} dir: ${CABINET_STATE_DIRS#$state}"
+echo "tenants: $(ls "$state/tenants" | tr '\n' ' ')"
+cat "$state/tenants/dealer-a/secret" 2>/dev/null || echo "no secret"
+"#;
+    let dir = module_dir("tenantless", script);
+    std::fs::create_dir_all(dir.join("state/tenants/dealer-a")).unwrap();
+    std::fs::write(dir.join("state/tenants/dealer-a/secret"), "secret of dealer-a\n").unwrap();
+    
+    let output = run(&dir, None);
+    std::fs::remove_dir_all(&dir).unwrap();
+    
+    assert_eq!(output.unwrap(), "tenant: none dir: /tenants/_shared\ntenants: _shared \nno secret\n");
+}
+
+#[test]
+fn test_state_directory_created_on_first_use() {
+    let dir = module_dir("created", "echo \"$CABINET_TENANT_ID $CABINET_STATE_DIRS\"; test -d \"$CABINET_STATE_DIRS\"\n");
+    
+    let output = run(&dir, Some("dealer-new")).unwrap();
+    let created = dir.join("state/tenants/dealer-new");
+    let exists = created.is_dir();
+    std::fs::remove_dir_all(&dir).unwrap();
+    
+    assert_eq!(output.trim(), format!("dealer-new {}", created.display()));
+    assert!(exists);
+}
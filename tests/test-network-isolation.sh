@@ -1,6 +1,7 @@
 #!/bin/bash
 # Phase 6.1 Network Isolation Test
 # Tests that adapters are isolated from each other and only accessible to platform
+# (modules the kernel runs itself are covered by kernel/tests/network_isolation.rs)
 #
 # NOTE: CI/GitHub Actions Sandbox Behavior
 # ========================================
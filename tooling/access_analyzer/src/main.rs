@@ -0,0 +1,162 @@
+//! Access Analyzer
+//! Answers who-can / what-can questions over access and routing policy
+//!
+//! Uses the kernel's own authz and routing checks, so answers match what the
+//! kernel would decide for an actor holding all of a role's scopes.
+//!
+//! Inputs (read-only):
+//!   - system/policy/access.yaml
+//!   - system/policy/routing.yaml
+//!   - system/policy/modules.yaml (module references in routing resolve to canonical IDs)
+//!   - system/policy/grants.yaml (optional)
+//!
+//! Outputs:
+//!   - stdout only (table or JSON)
+//!
+//! Usage:
+//!   access_analyzer who-can <capability>
+//!   access_analyzer what-can <role> <ui_id>
+//!   access_analyzer unreachable
+//!   access_analyzer orphan-routes
+//!   access_analyzer routing-check        (exit 1 if the routing graph has errors)
+//!   access_analyzer reachability
+//!   access_analyzer export dot|mermaid
+//!
+//! Options:
+//!   --format table|json   Output format for tables (default: table)
+//!   --policy-dir <dir>    Policy directory (default: system/policy)
+
+use kernel::authz::access_analysis::{render_table, AccessModel, TableRow};
+use kernel::authz::{capabilities, grants, roles};
+use kernel::config::module_registry;
+use kernel::routing::analysis;
+use kernel::routing::graph::RoutingGraph;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+struct Options {
+    command: Vec<String>,
+    json: bool,
+    policy_dir: String,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            eprintln!("Usage: access_analyzer <who-can CAP | what-can ROLE UI | unreachable | orphan-routes | routing-check | reachability | export dot|mermaid> [--format table|json] [--policy-dir DIR]");
+            process::exit(2);
+        }
+    };
+
+    let model = match load_model(Path::new(&options.policy_dir)) {
+        Ok(model) => model,
+        Err(e) => {
+            eprintln!("❌ Failed to load policy: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let command: Vec<&str> = options.command.iter().map(|s| s.as_str()).collect();
+    let output = match command.as_slice() {
+        ["who-can", capability] => render(&model.who_can(capability), options.json),
+        ["what-can", role, ui] => {
+            if !model.roles.roles().contains_key(*role) {
+                eprintln!("❌ Unknown role: {}", role);
+                process::exit(1);
+            }
+            render(&model.what_can(role, ui), options.json)
+        }
+        ["unreachable"] => render(&model.unreachable_capabilities(), options.json),
+        ["orphan-routes"] => render(&model.orphan_routes(), options.json),
+        ["routing-check"] => {
+            let routing = analysis::analyze(&model.graph);
+            print!("{}", render(&routing.findings, options.json));
+            if routing.has_errors() {
+                eprintln!("❌ Routing graph has {} error(s)", routing.errors().count());
+                process::exit(1);
+            }
+            return;
+        }
+        ["reachability"] => render(&analysis::analyze(&model.graph).reachability, options.json),
+        ["export", "dot"] => analysis::to_dot(&model.graph),
+        ["export", "mermaid"] => analysis::to_mermaid(&model.graph),
+        _ => {
+            eprintln!("❌ Unknown or incomplete command: {}", options.command.join(" "));
+            process::exit(2);
+        }
+    };
+
+    print!("{}", output);
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut command = Vec::new();
+    let mut json = false;
+    let mut policy_dir = "system/policy".to_string();
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--format" => match iter.next().map(|s| s.as_str()) {
+                Some("json") => json = true,
+                Some("table") => json = false,
+                other => return Err(format!("Invalid --format: {}", other.unwrap_or(""))),
+            },
+            "--policy-dir" => {
+                policy_dir = iter.next()
+                    .ok_or("--policy-dir requires a value")?
+                    .clone();
+            }
+            _ => command.push(arg.clone()),
+        }
+    }
+
+    if command.is_empty() {
+        return Err("No command given".to_string());
+    }
+
+    Ok(Options { command, json, policy_dir })
+}
+
+fn load_model(policy_dir: &Path) -> Result<AccessModel, String> {
+    let read = |name: &str| fs::read_to_string(policy_dir.join(name))
+        .map_err(|e| format!("{}: {}", name, e));
+
+    let access = read("access.yaml")?;
+    let routing = read("routing.yaml")?;
+    let modules = module_registry::parse_module_registry(&read("modules.yaml")?)
+        .map_err(|e| e.to_string())?;
+
+    // Grants are optional for analysis
+    let grants = match read("grants.yaml") {
+        Ok(content) => grants::parse_grants(&content).map_err(|e| e.to_string())?,
+        Err(_) => Vec::new(),
+    };
+
+    Ok(AccessModel {
+        roles: roles::parse_roles(&access)
+            .and_then(roles::RoleIndex::compile)
+            .map_err(|e| e.to_string())?,
+        capability_requirements: capabilities::parse_capability_requirements(&access)
+            .map_err(|e| e.to_string())?,
+        grants,
+        graph: RoutingGraph::parse_resolved(&routing, &modules).map_err(|e| e.to_string())?,
+    })
+}
+
+fn render<T: TableRow + Serialize>(rows: &[T], json: bool) -> String {
+    if json {
+        let mut out = serde_json::to_string_pretty(rows).unwrap_or_else(|_| "[]".to_string());
+        out.push('\n');
+        out
+    } else if rows.is_empty() {
+        "(no results)\n".to_string()
+    } else {
+        render_table(rows)
+    }
+}
@@ -20,6 +20,12 @@ use std::fs;
 use std::path::Path;
 use std::process;
 use serde_json::{json, Value};
+use kernel::config::module_registry::{self, ModuleRegistry};
+use kernel::primitives::capability_pattern;
+use kernel::routing::analysis;
+use kernel::routing::graph::RoutingGraph;
+use kernel::routing::model::RoutingModel;
+use kernel::sandbox::limits;
 
 fn main() {
     println!("🔍 System Validator - Validating system/ data against schemas and invariants...");
@@ -36,10 +42,24 @@ fn main() {
     println!("\nValidating system/policy/ files...");
     errors.extend(validate_policy_files());
     
+    // Analyze routing graph shape (cycles, exposed internal routes, dead ends)
+    println!("\nAnalyzing system/policy/routing.yaml graph...");
+    let (routing_errors, routing_warnings) = validate_routing_graph();
+    errors.extend(routing_errors);
+    warnings.extend(routing_warnings);
+    
+    // Every module reference must resolve to exactly one canonical module
+    println!("\nResolving module references via system/policy/modules.yaml...");
+    errors.extend(validate_module_identities());
+    
     // Validate invariants files exist
     println!("\nValidating system/invariants/ configuration...");
     errors.extend(validate_invariants_structure());
     
+    // Capability invariants use the kernel's capability pattern language
+    println!("\nChecking capability invariants...");
+    errors.extend(validate_capability_invariants());
+    
     // Check schemas exist
     println!("\nValidating shared/schemas/ are available...");
     errors.extend(validate_schemas_available());
@@ -174,6 +194,127 @@ fn validate_policy_files() -> Vec<String> {
     errors
 }
 
+fn validate_routing_graph() -> (Vec<String>, Vec<String>) {
+    let path = Path::new("system/policy/routing.yaml");
+    if !path.exists() {
+        return (Vec::new(), Vec::new());
+    }
+    
+    // Module nodes are analyzed under their canonical IDs
+    let graph = match fs::read_to_string("system/policy/modules.yaml")
+        .map_err(|e| e.to_string())
+        .and_then(|content| module_registry::parse_module_registry(&content).map_err(|e| e.to_string()))
+        .and_then(|registry| {
+            let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+            RoutingGraph::parse_resolved(&content, &registry).map_err(|e| e.to_string())
+        })
+    {
+        Ok(graph) => graph,
+        Err(e) => return (vec![format!("Invalid routing policy: {}", e)], Vec::new()),
+    };
+    
+    let report = analysis::analyze(&graph);
+    let describe = |f: &analysis::Finding| format!("Routing {} ({}): {}", f.kind, f.subject, f.detail);
+    let errors: Vec<String> = report.errors().map(describe).collect();
+    let warnings: Vec<String> = report.warnings().map(describe).collect();
+    
+    for warning in &warnings {
+        println!("  ⚠️  {}", warning);
+    }
+    if errors.is_empty() {
+        println!("  ✓ routing graph ({} UI reachability paths)", report.reachability.len());
+    }
+    
+    (errors, warnings)
+}
+
+/// Module IDs in intent, routing and limits must resolve through the module registry
+fn validate_module_identities() -> Vec<String> {
+    let registry = match fs::read_to_string("system/policy/modules.yaml")
+        .map_err(|e| e.to_string())
+        .and_then(|content| module_registry::parse_module_registry(&content).map_err(|e| e.to_string()))
+    {
+        Ok(registry) => registry,
+        Err(e) => return vec![format!("Invalid module registry: {}", e)],
+    };
+    
+    let mut errors = Vec::new();
+    
+    if let Ok(content) = fs::read_to_string("system/intent/modules.intent.yaml") {
+        match serde_yaml::from_str::<Value>(&content) {
+            Ok(intent) => {
+                for module in intent["modules"].as_array().into_iter().flatten() {
+                    let references = module["id"].as_str().into_iter()
+                        .chain(module["dependencies"].as_array().into_iter().flatten().filter_map(|d| d.as_str()));
+                    errors.extend(unresolved(&registry, "modules.intent.yaml", references));
+                }
+            }
+            Err(e) => errors.push(format!("Invalid modules.intent.yaml: {}", e)),
+        }
+    }
+    
+    if let Ok(content) = fs::read_to_string("system/policy/routing.yaml") {
+        if let Err(e) = RoutingModel::parse(&content).and_then(|mut model| model.resolve_modules(&registry)) {
+            errors.push(format!("routing.yaml: {}", e));
+        }
+    }
+    
+    if let Ok(content) = fs::read_to_string("system/policy/limits.yaml") {
+        if let Err(e) = limits::parse_limits(&content).and_then(|mut policy| policy.resolve_modules(&registry)) {
+            errors.push(format!("limits.yaml: {}", e));
+        }
+    }
+    
+    if errors.is_empty() {
+        println!("  ✓ {} modules, all references resolved", registry.ids().count());
+    }
+    
+    errors
+}
+
+fn unresolved<'a>(registry: &ModuleRegistry, file: &str, references: impl Iterator<Item = &'a str>) -> Vec<String> {
+    references
+        .filter_map(|reference| registry.resolve(reference).err())
+        .map(|e| format!("{}: {}", file, e))
+        .collect()
+}
+
+/// no_wildcard_access: UI profiles must name capabilities, not patterns
+fn validate_capability_invariants() -> Vec<String> {
+    let mut errors = Vec::new();
+    
+    let content = match fs::read_to_string("system/intent/ui.intent.yaml") {
+        Ok(content) => content,
+        Err(_) => return errors,
+    };
+    let intent: serde_yaml::Value = match serde_yaml::from_str(&content) {
+        Ok(intent) => intent,
+        Err(e) => return vec![format!("Cannot parse system/intent/ui.intent.yaml: {}", e)],
+    };
+    
+    let profiles = intent.get("user_profiles").and_then(|p| p.as_sequence()).cloned().unwrap_or_default();
+    for profile in &profiles {
+        let id = profile.get("id").and_then(|i| i.as_str()).unwrap_or("?");
+        let capabilities = profile.get("capabilities").and_then(|c| c.as_sequence()).cloned().unwrap_or_default();
+        
+        for capability in capabilities.iter().filter_map(|c| c.as_str()) {
+            if let Err(e) = capability_pattern::validate_pattern(capability) {
+                errors.push(format!("UI profile '{}': {}", id, e));
+            } else if capability_pattern::is_pattern(capability) {
+                errors.push(format!(
+                    "Invariant no_wildcard_access: UI profile '{}' grants pattern '{}'",
+                    id, capability
+                ));
+            }
+        }
+    }
+    
+    if errors.is_empty() {
+        println!("  ✓ no_wildcard_access ({} UI profiles)", profiles.len());
+    }
+    errors
+}
+
 fn validate_invariants_structure() -> Vec<String> {
     let mut errors = Vec::new();
     